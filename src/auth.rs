@@ -0,0 +1,259 @@
+use actix_web::{
+    body::{EitherBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    middleware::Next,
+    web, Error, FromRequest, HttpMessage, HttpRequest, HttpResponse,
+};
+use jsonwebtoken::{jwk::JwkSet, Algorithm, DecodingKey, Validation};
+use mongodb::Database;
+use std::future::{ready, Ready};
+use std::sync::Arc;
+use crate::ApiResponse;
+use crate::authz::{Principal, RoutePolicies};
+use crate::config::AuthConfig;
+use crate::services::api_key_service::{ApiKeyService, ServiceCaller};
+
+/// Clé(s) de vérification des signatures
+enum VerificationKeys {
+    Single(DecodingKey),
+    /// Jeu de clés local (JWKS), sélectionnées par `kid`
+    Jwks(JwkSet),
+}
+
+/// Vérifie les JWT émis par l'API principale MeetVoice
+pub struct Authenticator {
+    keys: VerificationKeys,
+    validation: Validation,
+    username_claim: String,
+    admin_claim: String,
+    /// Administrateurs désignés par configuration, en plus du claim
+    admin_usernames: Vec<String>,
+    policies: RoutePolicies,
+}
+
+/// Utilisateur authentifié, injecté dans la requête par le middleware
+#[derive(Debug, Clone)]
+pub struct AuthenticatedUser {
+    pub username: String,
+    pub is_admin: bool,
+}
+
+fn read_file(path: &str) -> Result<Vec<u8>, String> {
+    std::fs::read(path).map_err(|e| format!("Lecture de {} impossible: {}", path, e))
+}
+
+impl Authenticator {
+    /// Construit le vérificateur à partir de la section `auth` de la configuration.
+    ///
+    /// Renvoie `Ok(None)` si l'authentification est désactivée (développement uniquement).
+    pub fn from_config(config: &AuthConfig) -> Result<Option<Self>, String> {
+        if config.disabled {
+            return Ok(None);
+        }
+
+        let algorithm = match config.algorithm.as_str() {
+            "HS256" => Algorithm::HS256,
+            "RS256" => Algorithm::RS256,
+            other => return Err(format!("JWT_ALGORITHM non supporté: {} (attendu HS256 ou RS256)", other)),
+        };
+
+        let keys = match algorithm {
+            Algorithm::HS256 => {
+                let secret = config.secret.as_deref().ok_or("JWT_SECRET requis pour HS256")?;
+                VerificationKeys::Single(DecodingKey::from_secret(secret.as_bytes()))
+            }
+            _ => match (&config.jwks_file, &config.public_key_file) {
+                (Some(path), _) => {
+                    let jwks: JwkSet = serde_json::from_slice(&read_file(path)?)
+                        .map_err(|e| format!("JWKS invalide ({}): {}", path, e))?;
+                    VerificationKeys::Jwks(jwks)
+                }
+                (None, Some(path)) => {
+                    let key = DecodingKey::from_rsa_pem(&read_file(path)?)
+                        .map_err(|e| format!("Clé publique invalide ({}): {}", path, e))?;
+                    VerificationKeys::Single(key)
+                }
+                (None, None) => return Err("JWT_JWKS_FILE ou JWT_PUBLIC_KEY_FILE requis pour RS256".to_string()),
+            },
+        };
+
+        let mut validation = Validation::new(algorithm);
+        match &config.audience {
+            Some(audience) => validation.set_audience(&[audience]),
+            None => validation.validate_aud = false,
+        }
+        if let Some(issuer) = &config.issuer {
+            validation.set_issuer(&[issuer]);
+        }
+
+        Ok(Some(Authenticator {
+            keys,
+            validation,
+            username_claim: config.username_claim.clone(),
+            admin_claim: config.admin_claim.clone(),
+            admin_usernames: config.admin_usernames.clone(),
+            policies: RoutePolicies::default(),
+        }))
+    }
+
+    /// Vérifie le jeton et renvoie l'utilisateur qu'il désigne
+    fn verify(&self, token: &str) -> Result<AuthenticatedUser, String> {
+        let key = match &self.keys {
+            VerificationKeys::Single(key) => key.clone(),
+            VerificationKeys::Jwks(jwks) => {
+                let header = jsonwebtoken::decode_header(token).map_err(|e| e.to_string())?;
+                let kid = header.kid.ok_or("Jeton sans kid")?;
+                let jwk = jwks.find(&kid).ok_or_else(|| format!("Clé inconnue: {}", kid))?;
+                DecodingKey::from_jwk(jwk).map_err(|e| e.to_string())?
+            }
+        };
+
+        let claims = jsonwebtoken::decode::<serde_json::Value>(token, &key, &self.validation)
+            .map_err(|e| e.to_string())?
+            .claims;
+        let username = claims
+            .get(&self.username_claim)
+            .and_then(|v| v.as_str())
+            .filter(|v| !v.is_empty())
+            .ok_or_else(|| format!("Claim {} absent", self.username_claim))?;
+
+        let is_admin = claims.get(&self.admin_claim).and_then(|v| v.as_bool()) == Some(true)
+            || self.admin_usernames.iter().any(|admin| admin == username);
+
+        Ok(AuthenticatedUser {
+            username: username.to_string(),
+            is_admin,
+        })
+    }
+}
+
+/// Jeton de l'en-tête `Authorization: Bearer`, ou du paramètre `access_token`
+/// (EventSource ne permet pas de fixer d'en-tête)
+fn bearer_token(req: &ServiceRequest) -> Option<String> {
+    if let Some(header) = req.headers().get("Authorization").and_then(|v| v.to_str().ok()) {
+        return header.strip_prefix("Bearer ").map(|token| token.trim().to_string());
+    }
+    web::Query::<std::collections::HashMap<String, String>>::from_query(req.query_string())
+        .ok()
+        .and_then(|query| query.get("access_token").cloned())
+}
+
+fn reject<B>(req: ServiceRequest, response: HttpResponse) -> ServiceResponse<EitherBody<B>> {
+    req.into_response(response).map_into_right_body()
+}
+
+/// Middleware d'authentification des routes `/api` : JWT d'utilisateur, ou clé d'API
+/// (`X-Api-Key`) pour les services internes.
+///
+/// Sans authentificateur configuré (`AUTH_DISABLED=true`), les requêtes passent sans contrôle.
+pub async fn authenticate(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let authenticator = req.app_data::<web::Data<Option<Arc<Authenticator>>>>().and_then(|a| a.as_ref().clone());
+    let Some(authenticator) = authenticator.filter(|_| req.path().starts_with("/api/")) else {
+        return Ok(next.call(req).await?.map_into_left_body());
+    };
+
+    if let Some(key) = req.headers().get("X-Api-Key").and_then(|v| v.to_str().ok()).map(str::to_string) {
+        return authenticate_service(&authenticator, &key, req, next).await;
+    }
+
+    let Some(token) = bearer_token(&req) else {
+        let response = HttpResponse::Unauthorized()
+            .json(ApiResponse::<()>::err("Authentification requise".to_string()));
+        return Ok(reject(req, response));
+    };
+
+    let user = match authenticator.verify(&token) {
+        Ok(user) => user,
+        Err(e) => {
+            log::warn!("🔐 Jeton refusé pour {}: {}", req.path(), e);
+            let response = HttpResponse::Unauthorized()
+                .json(ApiResponse::<()>::err("Jeton invalide ou expiré".to_string()));
+            return Ok(reject(req, response));
+        }
+    };
+
+    if let Err(policy) = authenticator.policies.authorize(Principal::User(&user), &req) {
+        log::warn!("⛔ {} refusé sur {} {} ({})", user.username, req.method(), req.path(), policy);
+        let response = HttpResponse::Forbidden()
+            .json(ApiResponse::<()>::err("Accès refusé".to_string()));
+        return Ok(reject(req, response));
+    }
+
+    req.extensions_mut().insert(user);
+    Ok(next.call(req).await?.map_into_left_body())
+}
+
+async fn authenticate_service<B: MessageBody + 'static>(
+    authenticator: &Authenticator,
+    key: &str,
+    req: ServiceRequest,
+    next: Next<B>,
+) -> Result<ServiceResponse<EitherBody<B>>, Error> {
+    let Some(db) = req.app_data::<web::Data<Database>>().cloned() else {
+        let response = HttpResponse::InternalServerError()
+            .json(ApiResponse::<()>::err("Base de données non configurée".to_string()));
+        return Ok(reject(req, response));
+    };
+
+    let service = match ApiKeyService::authenticate(&db, key).await {
+        Ok(Some(service)) => service,
+        Ok(None) => {
+            log::warn!("🔐 Clé d'API refusée pour {}", req.path());
+            let response = HttpResponse::Unauthorized()
+                .json(ApiResponse::<()>::err("Clé d'API invalide ou révoquée".to_string()));
+            return Ok(reject(req, response));
+        }
+        Err(e) => {
+            log::error!("Erreur MongoDB: {}", e);
+            let response = HttpResponse::InternalServerError()
+                .json(ApiResponse::<()>::err(format!("Erreur: {}", e)));
+            return Ok(reject(req, response));
+        }
+    };
+
+    if let Err(policy) = authenticator.policies.authorize(Principal::Service(&service), &req) {
+        log::warn!("⛔ Service {} refusé sur {} {} ({})", service.name, req.method(), req.path(), policy);
+        let response = HttpResponse::Forbidden()
+            .json(ApiResponse::<()>::err("Portée insuffisante pour cette clé".to_string()));
+        return Ok(reject(req, response));
+    }
+
+    req.extensions_mut().insert(service);
+    Ok(next.call(req).await?.map_into_left_body())
+}
+
+/// Auteur d'une action, pour les champs `created_by` : service appelant ou utilisateur.
+///
+/// `None` si l'authentification est désactivée.
+pub struct Caller(pub Option<String>);
+
+impl FromRequest for Caller {
+    type Error = Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut actix_web::dev::Payload) -> Self::Future {
+        let extensions = req.extensions();
+        let name = extensions
+            .get::<ServiceCaller>()
+            .map(|service| service.name.clone())
+            .or_else(|| extensions.get::<AuthenticatedUser>().map(|user| user.username.clone()));
+        ready(Ok(Caller(name)))
+    }
+}
+
+impl FromRequest for AuthenticatedUser {
+    type Error = Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut actix_web::dev::Payload) -> Self::Future {
+        ready(
+            req.extensions()
+                .get::<AuthenticatedUser>()
+                .cloned()
+                .ok_or_else(|| actix_web::error::ErrorUnauthorized("Authentification requise")),
+        )
+    }
+}
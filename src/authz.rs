@@ -0,0 +1,191 @@
+use actix_web::{
+    dev::{Path, ResourceDef, ServiceRequest, Url},
+    HttpResponse,
+};
+use crate::ApiResponse;
+use crate::auth::AuthenticatedUser;
+use crate::services::api_key_service::ServiceCaller;
+
+/// Règle d'accès d'une route
+#[derive(Debug, Clone, Copy)]
+enum Policy {
+    /// Tout utilisateur authentifié
+    Authenticated,
+    /// Le paramètre de chemin désigne l'appelant
+    User(&'static str),
+    /// L'appelant est l'un des deux participants désignés par le chemin
+    Participants(&'static str, &'static str),
+    /// Administrateurs uniquement
+    Admin,
+    /// Administrateurs, ou services dont la clé d'API porte cette portée
+    AdminOrService(&'static str),
+}
+
+/// Appelant authentifié : utilisateur (JWT) ou service interne (clé d'API)
+pub enum Principal<'a> {
+    User(&'a AuthenticatedUser),
+    Service(&'a ServiceCaller),
+}
+
+/// Règles par route : méthode (`*` pour toutes), motif, règle.
+///
+/// La première règle correspondante s'applique ; les routes `/api` non listées exigent
+/// seulement un utilisateur authentifié. Les services n'accèdent qu'aux routes
+/// `AdminOrService` de leurs portées. Les règles dépendant du corps de la requête ou du
+/// document visé (demandeur, propriétaire d'une demande) sont vérifiées par les handlers.
+const ROUTE_POLICIES: &[(&str, &str, Policy)] = &[
+    ("*", "/api/debug/{tail}*", Policy::Admin),
+    ("*", "/api/webhooks", Policy::Admin),
+    ("*", "/api/webhooks/{tail}*", Policy::Admin),
+    ("*", "/api/api-keys", Policy::Admin),
+    ("*", "/api/api-keys/{tail}*", Policy::Admin),
+    ("*", "/api/messages/history/{username}", Policy::User("username")),
+    ("*", "/api/messages/conversation/{reader}/{peer}/read", Policy::User("reader")),
+    ("GET", "/api/messages/conversation/{user1}/{user2}", Policy::Participants("user1", "user2")),
+    // La suppression ne porte que sur les messages envoyés par `user1`
+    ("DELETE", "/api/messages/conversation/{user1}/{user2}", Policy::User("user1")),
+    ("*", "/api/notifications/system-message", Policy::AdminOrService("notifications:system")),
+    ("*", "/api/notifications/campaigns", Policy::AdminOrService("notifications:campaigns")),
+    ("*", "/api/notifications/campaigns/{tail}*", Policy::AdminOrService("notifications:campaigns")),
+    ("*", "/api/notifications/templates", Policy::AdminOrService("notifications:templates")),
+    ("*", "/api/notifications/templates/{tail}*", Policy::AdminOrService("notifications:templates")),
+    ("*", "/api/notifications/{username}{tail}*", Policy::User("username")),
+    // Consulté par les canaux de diffusion externes avant chaque envoi
+    ("GET", "/api/preferences/{username}/check", Policy::AdminOrService("preferences:check")),
+    ("*", "/api/preferences/{username}{tail}*", Policy::User("username")),
+    ("*", "/api/privacy/{username}", Policy::User("username")),
+    ("*", "/api/typing/{username}", Policy::User("username")),
+    ("*", "/api/stream/{username}", Policy::User("username")),
+    ("*", "/api/push/devices/{username}{tail}*", Policy::User("username")),
+    ("GET", "/api/requests/incoming/{username}", Policy::User("username")),
+    ("GET", "/api/requests/outgoing/{username}", Policy::User("username")),
+    ("GET", "/api/requests/{kind}/incoming/{username}", Policy::User("username")),
+    ("GET", "/api/requests/{kind}/outgoing/{username}", Policy::User("username")),
+    // Expiration manuelle : action système
+    ("POST", "/api/requests/{kind}/{id}/expire", Policy::AdminOrService("requests:expire")),
+];
+
+/// Règles compilées, construites une fois au démarrage
+pub struct RoutePolicies {
+    rules: Vec<(&'static str, ResourceDef, Policy)>,
+}
+
+impl Default for RoutePolicies {
+    fn default() -> Self {
+        RoutePolicies {
+            rules: ROUTE_POLICIES
+                .iter()
+                .map(|(method, pattern, policy)| (*method, ResourceDef::new(*pattern), *policy))
+                .collect(),
+        }
+    }
+}
+
+impl RoutePolicies {
+    /// Vérifie que l'appelant peut appeler la route demandée
+    pub fn authorize(&self, principal: Principal<'_>, req: &ServiceRequest) -> Result<(), String> {
+        let mut path = Path::new(Url::new(req.uri().clone()));
+        let policy = self
+            .rules
+            .iter()
+            .find(|(method, resource, _)| {
+                (*method == "*" || *method == req.method().as_str()) && resource.capture_match_info(&mut path)
+            })
+            .map_or(Policy::Authenticated, |(_, _, policy)| *policy);
+
+        let allowed = match (principal, policy) {
+            (Principal::User(_), Policy::Authenticated) => true,
+            (Principal::User(user), Policy::User(param)) => path.get(param) == Some(user.username.as_str()),
+            (Principal::User(user), Policy::Participants(first, second)) => {
+                path.get(first) == Some(user.username.as_str()) || path.get(second) == Some(user.username.as_str())
+            }
+            (Principal::User(user), Policy::Admin | Policy::AdminOrService(_)) => user.is_admin,
+            (Principal::Service(service), Policy::AdminOrService(scope)) => service.scopes.iter().any(|s| s == scope),
+            (Principal::Service(_), _) => false,
+        };
+        if allowed {
+            Ok(())
+        } else {
+            Err(format!("{:?}", policy))
+        }
+    }
+}
+
+fn forbidden() -> HttpResponse {
+    HttpResponse::Forbidden().json(ApiResponse::<()>::err("Accès refusé".to_string()))
+}
+
+/// Vérifie qu'un utilisateur indiqué dans le corps de la requête est bien l'appelant.
+///
+/// `None` (authentification désactivée) : aucun contrôle.
+pub fn ensure_user(user: Option<&AuthenticatedUser>, username: &str) -> Result<(), HttpResponse> {
+    match user {
+        Some(user) if user.username != username => Err(forbidden()),
+        _ => Ok(()),
+    }
+}
+
+/// Vérifie que l'appelant est l'un des utilisateurs autorisés, ou administrateur
+pub fn ensure_one_of(user: Option<&AuthenticatedUser>, allowed: &[&str]) -> Result<(), HttpResponse> {
+    match user {
+        Some(user) if !user.is_admin && !allowed.contains(&user.username.as_str()) => Err(forbidden()),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::TestRequest;
+
+    fn user(username: &str, is_admin: bool) -> AuthenticatedUser {
+        AuthenticatedUser { username: username.to_string(), is_admin }
+    }
+
+    fn service(scopes: &[&str]) -> ServiceCaller {
+        ServiceCaller { name: "mailer".to_string(), scopes: scopes.iter().map(|s| s.to_string()).collect() }
+    }
+
+    fn authorize(principal: Principal<'_>, method: &str, uri: &str) -> bool {
+        let req = TestRequest::default()
+            .method(method.parse().unwrap())
+            .uri(uri)
+            .to_srv_request();
+        RoutePolicies::default().authorize(principal, &req).is_ok()
+    }
+
+    #[test]
+    fn user_routes_require_the_path_user() {
+        let alice = user("alice", false);
+        assert!(authorize(Principal::User(&alice), "GET", "/api/notifications/alice"));
+        assert!(!authorize(Principal::User(&alice), "GET", "/api/notifications/bob"));
+        assert!(authorize(Principal::User(&alice), "GET", "/api/messages/conversation/bob/alice"));
+        assert!(!authorize(Principal::User(&alice), "DELETE", "/api/messages/conversation/bob/alice"));
+        assert!(authorize(Principal::User(&alice), "GET", "/api/users/online"));
+    }
+
+    #[test]
+    fn admin_routes_reject_users_and_unscoped_services() {
+        let alice = user("alice", false);
+        let admin = user("root", true);
+        assert!(!authorize(Principal::User(&alice), "GET", "/api/debug/collections"));
+        assert!(authorize(Principal::User(&admin), "GET", "/api/debug/collections"));
+        assert!(!authorize(Principal::Service(&service(&["notifications:system"])), "GET", "/api/webhooks"));
+    }
+
+    #[test]
+    fn services_only_reach_routes_of_their_scopes() {
+        let caller = service(&["notifications:system"]);
+        assert!(authorize(Principal::Service(&caller), "POST", "/api/notifications/system-message"));
+        assert!(!authorize(Principal::Service(&caller), "POST", "/api/notifications/campaigns"));
+        assert!(!authorize(Principal::Service(&caller), "GET", "/api/notifications/alice"));
+    }
+
+    #[test]
+    fn preference_check_is_open_to_delivery_services() {
+        let caller = service(&["preferences:check"]);
+        assert!(authorize(Principal::Service(&caller), "GET", "/api/preferences/alice/check?type=message"));
+        assert!(!authorize(Principal::Service(&caller), "GET", "/api/preferences/alice"));
+        assert!(!authorize(Principal::Service(&caller), "PUT", "/api/preferences/alice/check"));
+    }
+}
@@ -0,0 +1,996 @@
+pub mod mongo;
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::str::FromStr;
+use std::time::Duration;
+use crate::cors::CorsPolicy;
+use crate::services::request_kinds::REQUEST_KINDS;
+use crate::services::request_service::RequestDescriptor;
+
+const DEFAULT_BIND_ADDRESS: &str = "0.0.0.0:3000";
+const DEFAULT_MONGO_DATABASE: &str = "meetvoice_gateway";
+const DEFAULT_PG_HOST: &str = "localhost";
+const DEFAULT_PG_PORT: u16 = 5432;
+const DEFAULT_PG_DATABASE: &str = "meetvoice_api";
+
+const DEFAULT_JWT_ALGORITHM: &str = "HS256";
+const DEFAULT_USERNAME_CLAIM: &str = "username";
+const DEFAULT_ADMIN_CLAIM: &str = "is_admin";
+/// Taille minimale d'un secret HS256 (celle de l'empreinte SHA-256)
+const MIN_JWT_SECRET_BYTES: usize = 32;
+/// Valeur d'exemple de `config.example.toml`, à ne jamais utiliser telle quelle
+const PLACEHOLDER_SECRET: &str = "CHANGE_ME";
+
+/// Origines du front MeetVoice autorisées par défaut
+const DEFAULT_CORS_ORIGINS: &str = "https://meet-voice.fr,https://*.meet-voice.fr";
+const DEFAULT_CORS_METHODS: &str = "GET,POST,PUT,DELETE,OPTIONS";
+/// En-têtes utilisés par les clients : JWT, clé de service, reprise du flux SSE
+const DEFAULT_CORS_HEADERS: &str = "Authorization,Content-Type,Accept,X-Api-Key,Last-Event-ID";
+
+/// Expéditeur par défaut des emails
+const DEFAULT_MAIL_FROM: &str = "MeetVoice <no-reply@meet-voice.fr>";
+const SMTP_TLS_MODES: [&str; 3] = ["none", "starttls", "tls"];
+
+/// Valeur affichée à la place des secrets par `--print-config`
+const REDACTED: &str = "***";
+
+/// Configuration du serveur : fichier TOML facultatif, surchargé par les variables d'environnement.
+///
+/// Aucun identifiant n'a de valeur par défaut : `MONGO_URI` est obligatoire, PostgreSQL
+/// n'est utilisé que si `DB_USER` est renseigné et `JWT_SECRET` (ou une clé publique)
+/// est requis tant que l'authentification n'est pas désactivée.
+#[derive(Debug, Clone, Serialize)]
+pub struct Config {
+    pub server: ServerConfig,
+    pub mongo: MongoConfig,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub postgres: Option<PostgresConfig>,
+    pub events: EventsConfig,
+    pub health: HealthConfig,
+    pub auth: AuthConfig,
+    pub cors: CorsConfig,
+    pub rate_limit: RateLimitConfig,
+    pub requests: RequestExpiryConfig,
+    pub campaigns: CampaignConfig,
+    pub push: PushConfig,
+    /// Absent sans `SMTP_HOST` : l'envoi d'emails est désactivé
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mail: Option<MailConfig>,
+    pub digest: DigestConfig,
+    pub webhooks: WebhookConfig,
+    pub presence: PresenceConfig,
+    pub typing: TypingConfig,
+    pub stream: StreamConfig,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ServerConfig {
+    /// `BIND_ADDRESS`
+    pub bind_address: SocketAddr,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MongoConfig {
+    /// `MONGO_URI`
+    pub uri: String,
+    /// `MONGO_DATABASE`
+    pub database: String,
+}
+
+/// Base de l'API principale (profils, adresses email)
+#[derive(Debug, Clone, Serialize)]
+pub struct PostgresConfig {
+    /// `DB_HOST`
+    pub host: String,
+    /// `DB_PORT`
+    pub port: u16,
+    /// `DB_USER`
+    pub user: String,
+    /// `DB_PASSWORD`
+    pub password: String,
+    /// `DB_NAME`
+    pub database: String,
+}
+
+impl PostgresConfig {
+    pub fn connection_config(&self) -> tokio_postgres::Config {
+        let mut config = tokio_postgres::Config::new();
+        config
+            .host(&self.host)
+            .port(self.port)
+            .user(&self.user)
+            .password(&self.password)
+            .dbname(&self.database);
+        config
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EventsConfig {
+    /// `EVENT_POLL_INTERVAL_MS` : surveillance des collections `messages` et `notifications`
+    pub poll_interval_ms: u64,
+    /// `NOTIFICATION_PUBLISH_INTERVAL_SECS` : publication des notifications programmées
+    pub notification_publish_interval_secs: u64,
+}
+
+/// Sondes `/health/ready`
+#[derive(Debug, Clone, Serialize)]
+pub struct HealthConfig {
+    /// `HEALTH_TIMEOUT_MS` : délai maximal de chaque vérification
+    pub timeout_ms: u64,
+    /// `HEALTH_REQUIRE_POSTGRES` : PostgreSQL indisponible rend le service non prêt
+    /// (par défaut, les profils sont alors lus depuis MongoDB seul)
+    pub require_postgres: bool,
+}
+
+/// Authentification JWT des routes `/api` (jetons émis par l'API MeetVoice)
+#[derive(Debug, Clone, Serialize)]
+pub struct AuthConfig {
+    /// `AUTH_DISABLED` : développement uniquement
+    pub disabled: bool,
+    /// `JWT_ALGORITHM` : HS256 ou RS256
+    pub algorithm: String,
+    /// `JWT_SECRET` : requis pour HS256
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+    /// `JWT_PUBLIC_KEY_FILE` ou `JWT_JWKS_FILE` : requis pour RS256
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub public_key_file: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jwks_file: Option<String>,
+    /// `JWT_ISSUER`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub issuer: Option<String>,
+    /// `JWT_AUDIENCE`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub audience: Option<String>,
+    /// `JWT_USERNAME_CLAIM`
+    pub username_claim: String,
+    /// `JWT_ADMIN_CLAIM` : claim booléen désignant un administrateur
+    pub admin_claim: String,
+    /// `ADMIN_USERNAMES` : administrateurs supplémentaires
+    pub admin_usernames: Vec<String>,
+}
+
+/// Politique CORS des navigateurs (listes séparées par des virgules dans l'environnement)
+#[derive(Debug, Clone, Serialize)]
+pub struct CorsConfig {
+    /// `CORS_DEV_MODE` : accepte aussi les origines locales (localhost), toute méthode et tout en-tête
+    pub dev_mode: bool,
+    /// `CORS_ALLOWED_ORIGINS` : origines exactes ou sous-domaines (`https://*.meet-voice.fr`)
+    pub allowed_origins: Vec<String>,
+    /// `CORS_ALLOWED_METHODS`
+    pub allowed_methods: Vec<String>,
+    /// `CORS_ALLOWED_HEADERS`
+    pub allowed_headers: Vec<String>,
+    /// `CORS_ALLOW_CREDENTIALS`
+    pub allow_credentials: bool,
+    /// `CORS_MAX_AGE_SECS`
+    pub max_age_secs: usize,
+}
+
+/// Limitation de débit par utilisateur et par adresse IP (seau à jetons : rafale puis débit)
+#[derive(Debug, Clone, Serialize)]
+pub struct RateLimitConfig {
+    /// `RATE_LIMIT_ENABLED`
+    pub enabled: bool,
+    /// `RATE_LIMIT_MESSAGES_PER_MINUTE`
+    pub messages_per_minute: f64,
+    /// `RATE_LIMIT_MESSAGES_BURST`
+    pub messages_burst: f64,
+    /// `RATE_LIMIT_REQUESTS_PER_HOUR` : création de demandes, tous types confondus
+    pub requests_per_hour: f64,
+    /// `RATE_LIMIT_REQUESTS_BURST`
+    pub requests_burst: f64,
+    /// `RATE_LIMIT_IP_FACTOR` : une adresse IP dispose de N fois la limite d'un utilisateur
+    pub ip_factor: f64,
+    /// `RATE_LIMIT_TRUST_FORWARDED` : adresse du client lue dans `X-Forwarded-For` (proxy de confiance)
+    pub trust_forwarded: bool,
+    /// `RATE_LIMIT_REDIS_URL` : seaux partagés entre instances (feature `redis`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub redis_url: Option<String>,
+}
+
+/// Durée de vie des demandes en attente avant expiration automatique
+#[derive(Debug, Clone, Serialize)]
+pub struct RequestExpiryConfig {
+    /// `REQUEST_TTL_HOURS` : durée par défaut en heures (0 = jamais)
+    pub ttl_hours: i64,
+    /// `REQUEST_TTL_HOURS_<KIND>` (ex: `REQUEST_TTL_HOURS_PHOTO_PERMISSION`)
+    pub ttl_hours_by_kind: BTreeMap<String, i64>,
+    /// `REQUEST_EXPIRY_INTERVAL_SECS` : intervalle entre deux passages de la tâche d'expiration
+    pub expiry_interval_secs: u64,
+}
+
+impl RequestExpiryConfig {
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs(self.expiry_interval_secs)
+    }
+
+    pub fn ttl_for(&self, descriptor: &RequestDescriptor) -> Option<chrono::Duration> {
+        let hours = self
+            .ttl_hours_by_kind
+            .get(descriptor.kind)
+            .copied()
+            .unwrap_or(self.ttl_hours);
+        if hours > 0 {
+            Some(chrono::Duration::hours(hours))
+        } else {
+            None
+        }
+    }
+
+    /// Timestamp avant lequel une demande en attente est considérée expirée
+    pub fn cutoff_for(&self, descriptor: &RequestDescriptor) -> Option<String> {
+        self.ttl_for(descriptor)
+            .map(|ttl| (chrono::Utc::now() - ttl).to_rfc3339())
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CampaignConfig {
+    /// `CAMPAIGN_BATCH_SIZE` : notifications insérées par lot
+    pub batch_size: usize,
+}
+
+/// Fournisseurs de push ; un fournisseur n'est activé que si tous ses paramètres sont renseignés
+#[derive(Debug, Clone, Serialize)]
+pub struct PushConfig {
+    /// `PUSH_MOCK` : journalise les push sans les envoyer
+    pub mock: bool,
+    /// `FCM_SERVICE_ACCOUNT_FILE`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fcm_service_account_file: Option<String>,
+    /// `APNS_KEY_FILE`, `APNS_KEY_ID`, `APNS_TEAM_ID`, `APNS_TOPIC`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub apns: Option<ApnsConfig>,
+    /// `VAPID_PRIVATE_KEY_FILE`, `VAPID_PUBLIC_KEY`, `VAPID_SUBJECT`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub web_push: Option<WebPushConfig>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ApnsConfig {
+    pub key_file: String,
+    pub key_id: String,
+    pub team_id: String,
+    /// Bundle id de l'application
+    pub topic: String,
+    /// `APNS_SANDBOX`
+    pub sandbox: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WebPushConfig {
+    pub private_key_file: String,
+    /// Clé publique VAPID encodée en base64url, celle fournie aux navigateurs
+    pub public_key: String,
+    /// Contact de l'émetteur, ex: "mailto:contact@meet-voice.fr"
+    pub subject: String,
+}
+
+/// Envoi d'emails par SMTP
+#[derive(Debug, Clone, Serialize)]
+pub struct MailConfig {
+    /// `SMTP_HOST`
+    pub host: String,
+    /// `SMTP_PORT` : port par défaut du mode TLS sinon
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+    /// `SMTP_TLS` : none (collecteur local, ex: Mailpit), starttls ou tls
+    pub tls: String,
+    /// `SMTP_USERNAME`, `SMTP_PASSWORD`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+    /// `SMTP_FROM`
+    pub from: String,
+}
+
+/// Récapitulatif par email des messages non lus
+#[derive(Debug, Clone, Serialize)]
+pub struct DigestConfig {
+    /// `DIGEST_UNREAD_AFTER_HOURS` : ancienneté d'un message non lu avant de figurer dans un récapitulatif
+    pub unread_after_hours: i64,
+    /// `DIGEST_INTERVAL_SECS`
+    pub interval_secs: u64,
+}
+
+impl DigestConfig {
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs(self.interval_secs)
+    }
+}
+
+/// Webhooks sortants : nouvelle tentative après `backoff_secs`, doublé à chaque échec
+#[derive(Debug, Clone, Serialize)]
+pub struct WebhookConfig {
+    /// `WEBHOOK_DELIVERY_INTERVAL_SECS`
+    pub delivery_interval_secs: u64,
+    /// `WEBHOOK_MAX_ATTEMPTS` : au-delà, la livraison passe en lettre morte
+    pub max_attempts: i32,
+    /// `WEBHOOK_BACKOFF_SECS`
+    pub backoff_secs: i64,
+}
+
+impl WebhookConfig {
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs(self.delivery_interval_secs)
+    }
+}
+
+/// Nettoyage des connexions sans signe de vie
+#[derive(Debug, Clone, Serialize)]
+pub struct PresenceConfig {
+    /// `PRESENCE_INACTIVE_MINUTES` (comme `cleanupInactiveConnections`)
+    pub inactive_minutes: i64,
+    /// `PRESENCE_SWEEP_INTERVAL_SECS`
+    pub sweep_interval_secs: u64,
+}
+
+impl PresenceConfig {
+    pub fn sweep_interval(&self) -> Duration {
+        Duration::from_secs(self.sweep_interval_secs)
+    }
+}
+
+/// Indicateurs de frappe (jamais enregistrés)
+#[derive(Debug, Clone, Serialize)]
+pub struct TypingConfig {
+    /// `TYPING_TTL_SECS` : durée d'un indicateur sans nouvel appel
+    pub ttl_secs: u64,
+    /// `TYPING_THROTTLE_MS` : intervalle minimal entre deux « commence à écrire » diffusés
+    pub throttle_ms: u64,
+}
+
+impl TypingConfig {
+    pub fn ttl(&self) -> Duration {
+        Duration::from_secs(self.ttl_secs)
+    }
+
+    pub fn throttle(&self) -> Duration {
+        Duration::from_millis(self.throttle_ms)
+    }
+}
+
+/// Flux SSE : événements rejouables après reconnexion (`Last-Event-ID`)
+#[derive(Debug, Clone, Serialize)]
+pub struct StreamConfig {
+    /// `STREAM_REPLAY_SIZE` : événements conservés par utilisateur
+    pub replay_size: usize,
+    /// `STREAM_REPLAY_SECS` : ancienneté maximale d'un événement rejouable
+    pub replay_secs: u64,
+}
+
+impl StreamConfig {
+    pub fn replay_window(&self) -> Duration {
+        Duration::from_secs(self.replay_secs)
+    }
+}
+
+/// Contenu du fichier TOML ; toutes les clés sont facultatives
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileConfig {
+    server: FileServer,
+    mongo: FileMongo,
+    postgres: FilePostgres,
+    events: FileEvents,
+    health: FileHealth,
+    auth: FileAuth,
+    cors: FileCors,
+    rate_limit: FileRateLimit,
+    requests: FileRequests,
+    campaigns: FileCampaigns,
+    push: FilePush,
+    mail: FileMail,
+    digest: FileDigest,
+    webhooks: FileWebhooks,
+    presence: FilePresence,
+    typing: FileTyping,
+    stream: FileStream,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileServer {
+    bind_address: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileMongo {
+    uri: Option<String>,
+    database: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FilePostgres {
+    host: Option<String>,
+    port: Option<u16>,
+    user: Option<String>,
+    password: Option<String>,
+    database: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileEvents {
+    poll_interval_ms: Option<u64>,
+    notification_publish_interval_secs: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileHealth {
+    timeout_ms: Option<u64>,
+    require_postgres: Option<bool>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileAuth {
+    disabled: Option<bool>,
+    algorithm: Option<String>,
+    secret: Option<String>,
+    public_key_file: Option<String>,
+    jwks_file: Option<String>,
+    issuer: Option<String>,
+    audience: Option<String>,
+    username_claim: Option<String>,
+    admin_claim: Option<String>,
+    admin_usernames: Option<Vec<String>>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileCors {
+    dev_mode: Option<bool>,
+    allowed_origins: Option<Vec<String>>,
+    allowed_methods: Option<Vec<String>>,
+    allowed_headers: Option<Vec<String>>,
+    allow_credentials: Option<bool>,
+    max_age_secs: Option<usize>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileRateLimit {
+    enabled: Option<bool>,
+    messages_per_minute: Option<f64>,
+    messages_burst: Option<f64>,
+    requests_per_hour: Option<f64>,
+    requests_burst: Option<f64>,
+    ip_factor: Option<f64>,
+    trust_forwarded: Option<bool>,
+    redis_url: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileRequests {
+    ttl_hours: Option<i64>,
+    ttl_hours_by_kind: BTreeMap<String, i64>,
+    expiry_interval_secs: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileCampaigns {
+    batch_size: Option<usize>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FilePush {
+    mock: Option<bool>,
+    fcm_service_account_file: Option<String>,
+    apns_key_file: Option<String>,
+    apns_key_id: Option<String>,
+    apns_team_id: Option<String>,
+    apns_topic: Option<String>,
+    apns_sandbox: Option<bool>,
+    vapid_private_key_file: Option<String>,
+    vapid_public_key: Option<String>,
+    vapid_subject: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileMail {
+    host: Option<String>,
+    port: Option<u16>,
+    tls: Option<String>,
+    username: Option<String>,
+    password: Option<String>,
+    from: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileDigest {
+    unread_after_hours: Option<i64>,
+    interval_secs: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileWebhooks {
+    delivery_interval_secs: Option<u64>,
+    max_attempts: Option<i32>,
+    backoff_secs: Option<i64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FilePresence {
+    inactive_minutes: Option<i64>,
+    sweep_interval_secs: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileTyping {
+    ttl_secs: Option<u64>,
+    throttle_ms: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileStream {
+    replay_size: Option<usize>,
+    replay_secs: Option<u64>,
+}
+
+/// Options de la ligne de commande
+#[derive(Debug, Default)]
+pub struct CliArgs {
+    /// `--config <fichier>` (à défaut `CONFIG_FILE`)
+    pub config_file: Option<String>,
+    /// `--print-config` : affiche la configuration effective, secrets masqués, puis quitte
+    pub print_config: bool,
+}
+
+impl CliArgs {
+    pub fn parse() -> Result<Self, String> {
+        let mut cli = CliArgs::default();
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--print-config" => cli.print_config = true,
+                "--config" => cli.config_file = Some(args.next().ok_or("--config attend un chemin de fichier")?),
+                other => match other.strip_prefix("--config=") {
+                    Some(path) => cli.config_file = Some(path.to_string()),
+                    None => return Err(format!("Option inconnue: {} (attendu --config <fichier>, --print-config)", other)),
+                },
+            }
+        }
+        Ok(cli)
+    }
+}
+
+fn env_var(name: &str) -> Option<String> {
+    std::env::var(name).ok().filter(|v| !v.is_empty())
+}
+
+/// Valeur de l'environnement, sinon du fichier ; les erreurs de format sont cumulées
+fn layered<T: FromStr>(errors: &mut Vec<String>, name: &str, file: Option<T>) -> Option<T> {
+    match env_var(name) {
+        Some(value) => match value.parse() {
+            Ok(value) => Some(value),
+            Err(_) => {
+                errors.push(format!("{} invalide: {}", name, value));
+                None
+            }
+        },
+        None => file,
+    }
+}
+
+/// Booléen de l'environnement (`true`/`1`, `false`/`0`), sinon du fichier
+fn layered_flag(errors: &mut Vec<String>, name: &str, file: Option<bool>) -> Option<bool> {
+    match env_var(name).as_deref() {
+        Some("true" | "1") => Some(true),
+        Some("false" | "0") => Some(false),
+        Some(value) => {
+            errors.push(format!("{} invalide: {} (attendu true ou false)", name, value));
+            None
+        }
+        None => file,
+    }
+}
+
+/// Liste séparée par des virgules dans l'environnement, tableau dans le fichier
+fn layered_list(name: &str, file: Option<Vec<String>>, default: &str) -> Vec<String> {
+    let items = match env_var(name) {
+        Some(value) => value.split(',').map(str::to_string).collect(),
+        None => file.unwrap_or_else(|| default.split(',').map(str::to_string).collect()),
+    };
+    items
+        .iter()
+        .map(|item| item.trim().to_string())
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
+/// Valeur strictement positive, sinon erreur et valeur par défaut
+fn positive<T: PartialOrd + Default + Copy + std::fmt::Display>(
+    errors: &mut Vec<String>,
+    name: &str,
+    value: Option<T>,
+    default: T,
+) -> T {
+    match value {
+        Some(value) if value <= T::default() => {
+            errors.push(format!("{} doit être strictement positif: {}", name, value));
+            default
+        }
+        Some(value) => value,
+        None => default,
+    }
+}
+
+/// Un secret HS256 connu ou trop court permet de forger des jetons
+fn validate_jwt_secret(secret: &str) -> Result<(), String> {
+    if secret.trim() == PLACEHOLDER_SECRET {
+        return Err(format!("JWT_SECRET est la valeur d'exemple {} : générer un secret", PLACEHOLDER_SECRET));
+    }
+    if secret.len() < MIN_JWT_SECRET_BYTES {
+        return Err(format!(
+            "JWT_SECRET trop court : {} octets minimum pour HS256 (ex: openssl rand -base64 48)",
+            MIN_JWT_SECRET_BYTES
+        ));
+    }
+    Ok(())
+}
+
+/// Fichier de clé ou de compte de service lisible au démarrage
+fn check_file(errors: &mut Vec<String>, name: &str, path: &Option<String>) {
+    if let Some(path) = path {
+        if !std::path::Path::new(path).is_file() {
+            errors.push(format!("{}: fichier introuvable: {}", name, path));
+        }
+    }
+}
+
+impl Config {
+    /// Charge le fichier (`path`, à défaut `CONFIG_FILE`) puis l'environnement, et valide
+    /// l'ensemble ; l'erreur liste tous les problèmes rencontrés
+    pub fn load(path: Option<&str>) -> Result<Self, String> {
+        let file = match path.map(str::to_string).or_else(|| env_var("CONFIG_FILE")) {
+            Some(path) => {
+                let content = std::fs::read_to_string(&path)
+                    .map_err(|e| format!("Lecture de {} impossible: {}", path, e))?;
+                toml::from_str::<FileConfig>(&content).map_err(|e| format!("Fichier {} invalide: {}", path, e))?
+            }
+            None => FileConfig::default(),
+        };
+        let mut errors = Vec::new();
+
+        let bind_address = layered(&mut errors, "BIND_ADDRESS", file.server.bind_address)
+            .unwrap_or_else(|| DEFAULT_BIND_ADDRESS.to_string());
+        let bind_address = bind_address.parse::<SocketAddr>().unwrap_or_else(|_| {
+            errors.push(format!("BIND_ADDRESS invalide: {} (attendu hôte:port)", bind_address));
+            SocketAddr::from(([0, 0, 0, 0], 3000))
+        });
+
+        let uri = layered(&mut errors, "MONGO_URI", file.mongo.uri).unwrap_or_default();
+        if uri.is_empty() {
+            errors.push("MONGO_URI requis".to_string());
+        } else if !uri.starts_with("mongodb://") && !uri.starts_with("mongodb+srv://") {
+            errors.push(format!("MONGO_URI invalide: {}", mongo::redact_uri(&uri)));
+        }
+        let mongo = MongoConfig {
+            uri,
+            database: layered(&mut errors, "MONGO_DATABASE", file.mongo.database)
+                .unwrap_or_else(|| DEFAULT_MONGO_DATABASE.to_string()),
+        };
+
+        let pg_host = layered(&mut errors, "DB_HOST", file.postgres.host);
+        let pg_port = layered(&mut errors, "DB_PORT", file.postgres.port);
+        let pg_password = layered(&mut errors, "DB_PASSWORD", file.postgres.password);
+        let pg_database = layered(&mut errors, "DB_NAME", file.postgres.database);
+        let postgres = match layered(&mut errors, "DB_USER", file.postgres.user) {
+            Some(user) => match pg_password {
+                Some(password) => Some(PostgresConfig {
+                    host: pg_host.unwrap_or_else(|| DEFAULT_PG_HOST.to_string()),
+                    port: pg_port.unwrap_or(DEFAULT_PG_PORT),
+                    user,
+                    password,
+                    database: pg_database.unwrap_or_else(|| DEFAULT_PG_DATABASE.to_string()),
+                }),
+                None => {
+                    errors.push("DB_PASSWORD requis avec DB_USER".to_string());
+                    None
+                }
+            },
+            None => None,
+        };
+
+        let events = EventsConfig {
+            poll_interval_ms: layered(&mut errors, "EVENT_POLL_INTERVAL_MS", file.events.poll_interval_ms)
+                .unwrap_or(1000)
+                .max(100),
+            notification_publish_interval_secs: layered(
+                &mut errors,
+                "NOTIFICATION_PUBLISH_INTERVAL_SECS",
+                file.events.notification_publish_interval_secs,
+            )
+            .unwrap_or(30)
+            .max(1),
+        };
+
+        let health = HealthConfig {
+            timeout_ms: layered(&mut errors, "HEALTH_TIMEOUT_MS", file.health.timeout_ms)
+                .unwrap_or(2000)
+                .max(100),
+            require_postgres: layered_flag(&mut errors, "HEALTH_REQUIRE_POSTGRES", file.health.require_postgres)
+                .unwrap_or(false),
+        };
+        if health.require_postgres && postgres.is_none() {
+            errors.push("HEALTH_REQUIRE_POSTGRES=true exige DB_USER et DB_PASSWORD".to_string());
+        }
+
+        let auth = AuthConfig {
+            disabled: layered_flag(&mut errors, "AUTH_DISABLED", file.auth.disabled).unwrap_or(false),
+            algorithm: layered(&mut errors, "JWT_ALGORITHM", file.auth.algorithm)
+                .unwrap_or_else(|| DEFAULT_JWT_ALGORITHM.to_string()),
+            secret: layered(&mut errors, "JWT_SECRET", file.auth.secret),
+            public_key_file: layered(&mut errors, "JWT_PUBLIC_KEY_FILE", file.auth.public_key_file),
+            jwks_file: layered(&mut errors, "JWT_JWKS_FILE", file.auth.jwks_file),
+            issuer: layered(&mut errors, "JWT_ISSUER", file.auth.issuer),
+            audience: layered(&mut errors, "JWT_AUDIENCE", file.auth.audience),
+            username_claim: layered(&mut errors, "JWT_USERNAME_CLAIM", file.auth.username_claim)
+                .unwrap_or_else(|| DEFAULT_USERNAME_CLAIM.to_string()),
+            admin_claim: layered(&mut errors, "JWT_ADMIN_CLAIM", file.auth.admin_claim)
+                .unwrap_or_else(|| DEFAULT_ADMIN_CLAIM.to_string()),
+            admin_usernames: layered_list("ADMIN_USERNAMES", file.auth.admin_usernames, ""),
+        };
+        if !auth.disabled {
+            match auth.algorithm.as_str() {
+                "HS256" if auth.secret.is_none() => {
+                    errors.push("JWT_SECRET requis pour HS256 (ou AUTH_DISABLED=true en développement)".to_string())
+                }
+                "HS256" => {
+                    if let Err(e) = validate_jwt_secret(auth.secret.as_deref().unwrap_or_default()) {
+                        errors.push(e);
+                    }
+                }
+                "RS256" if auth.jwks_file.is_none() && auth.public_key_file.is_none() => {
+                    errors.push("JWT_JWKS_FILE ou JWT_PUBLIC_KEY_FILE requis pour RS256".to_string())
+                }
+                "RS256" => {
+                    check_file(&mut errors, "JWT_JWKS_FILE", &auth.jwks_file);
+                    check_file(&mut errors, "JWT_PUBLIC_KEY_FILE", &auth.public_key_file);
+                }
+                other => errors.push(format!("JWT_ALGORITHM non supporté: {} (attendu HS256 ou RS256)", other)),
+            }
+        }
+
+        let cors = CorsConfig {
+            dev_mode: layered_flag(&mut errors, "CORS_DEV_MODE", file.cors.dev_mode).unwrap_or(false),
+            allowed_origins: layered_list("CORS_ALLOWED_ORIGINS", file.cors.allowed_origins, DEFAULT_CORS_ORIGINS),
+            allowed_methods: layered_list("CORS_ALLOWED_METHODS", file.cors.allowed_methods, DEFAULT_CORS_METHODS),
+            allowed_headers: layered_list("CORS_ALLOWED_HEADERS", file.cors.allowed_headers, DEFAULT_CORS_HEADERS),
+            allow_credentials: layered_flag(&mut errors, "CORS_ALLOW_CREDENTIALS", file.cors.allow_credentials)
+                .unwrap_or(true),
+            max_age_secs: layered(&mut errors, "CORS_MAX_AGE_SECS", file.cors.max_age_secs).unwrap_or(3600),
+        };
+        if let Err(e) = CorsPolicy::from_config(&cors) {
+            errors.push(e);
+        }
+
+        let messages_per_minute =
+            layered(&mut errors, "RATE_LIMIT_MESSAGES_PER_MINUTE", file.rate_limit.messages_per_minute);
+        let messages_burst = layered(&mut errors, "RATE_LIMIT_MESSAGES_BURST", file.rate_limit.messages_burst);
+        let requests_per_hour = layered(&mut errors, "RATE_LIMIT_REQUESTS_PER_HOUR", file.rate_limit.requests_per_hour);
+        let requests_burst = layered(&mut errors, "RATE_LIMIT_REQUESTS_BURST", file.rate_limit.requests_burst);
+        let ip_factor = layered(&mut errors, "RATE_LIMIT_IP_FACTOR", file.rate_limit.ip_factor);
+        let rate_limit = RateLimitConfig {
+            enabled: layered_flag(&mut errors, "RATE_LIMIT_ENABLED", file.rate_limit.enabled).unwrap_or(true),
+            messages_per_minute: positive(&mut errors, "RATE_LIMIT_MESSAGES_PER_MINUTE", messages_per_minute, 30.0),
+            messages_burst: positive(&mut errors, "RATE_LIMIT_MESSAGES_BURST", messages_burst, 10.0),
+            requests_per_hour: positive(&mut errors, "RATE_LIMIT_REQUESTS_PER_HOUR", requests_per_hour, 30.0),
+            requests_burst: positive(&mut errors, "RATE_LIMIT_REQUESTS_BURST", requests_burst, 5.0),
+            ip_factor: positive(&mut errors, "RATE_LIMIT_IP_FACTOR", ip_factor, 5.0),
+            trust_forwarded: layered_flag(&mut errors, "RATE_LIMIT_TRUST_FORWARDED", file.rate_limit.trust_forwarded)
+                .unwrap_or(false),
+            redis_url: layered(&mut errors, "RATE_LIMIT_REDIS_URL", file.rate_limit.redis_url),
+        };
+        if let Some(url) = &rate_limit.redis_url {
+            if !url.starts_with("redis://") && !url.starts_with("rediss://") {
+                errors.push(format!("RATE_LIMIT_REDIS_URL invalide: {}", mongo::redact_uri(url)));
+            }
+        }
+
+        let mut ttl_hours_by_kind = file.requests.ttl_hours_by_kind;
+        if let Some(kind) = ttl_hours_by_kind.keys().find(|kind| !REQUEST_KINDS.iter().any(|d| d.kind == kind.as_str())) {
+            errors.push(format!("Type de demande inconnu dans requests.ttl_hours_by_kind: {}", kind));
+        }
+        for descriptor in &REQUEST_KINDS {
+            let name = format!("REQUEST_TTL_HOURS_{}", descriptor.kind.to_uppercase());
+            if let Some(hours) = layered(&mut errors, &name, None) {
+                ttl_hours_by_kind.insert(descriptor.kind.to_string(), hours);
+            }
+        }
+        let requests = RequestExpiryConfig {
+            ttl_hours: layered(&mut errors, "REQUEST_TTL_HOURS", file.requests.ttl_hours).unwrap_or(168),
+            ttl_hours_by_kind,
+            expiry_interval_secs: layered(&mut errors, "REQUEST_EXPIRY_INTERVAL_SECS", file.requests.expiry_interval_secs)
+                .unwrap_or(300)
+                .max(1),
+        };
+
+        let batch_size = layered(&mut errors, "CAMPAIGN_BATCH_SIZE", file.campaigns.batch_size);
+        let campaigns = CampaignConfig {
+            batch_size: positive(&mut errors, "CAMPAIGN_BATCH_SIZE", batch_size, 500),
+        };
+
+        let push = Self::load_push(&mut errors, file.push);
+        let mail = Self::load_mail(&mut errors, file.mail);
+
+        let unread_after_hours = layered(&mut errors, "DIGEST_UNREAD_AFTER_HOURS", file.digest.unread_after_hours);
+        let digest = DigestConfig {
+            unread_after_hours: positive(&mut errors, "DIGEST_UNREAD_AFTER_HOURS", unread_after_hours, 24),
+            interval_secs: layered(&mut errors, "DIGEST_INTERVAL_SECS", file.digest.interval_secs)
+                .unwrap_or(3600)
+                .max(60),
+        };
+
+        let delivery_interval_secs =
+            layered(&mut errors, "WEBHOOK_DELIVERY_INTERVAL_SECS", file.webhooks.delivery_interval_secs);
+        let max_attempts = layered(&mut errors, "WEBHOOK_MAX_ATTEMPTS", file.webhooks.max_attempts);
+        let backoff_secs = layered(&mut errors, "WEBHOOK_BACKOFF_SECS", file.webhooks.backoff_secs);
+        let webhooks = WebhookConfig {
+            delivery_interval_secs: positive(&mut errors, "WEBHOOK_DELIVERY_INTERVAL_SECS", delivery_interval_secs, 5),
+            max_attempts: positive(&mut errors, "WEBHOOK_MAX_ATTEMPTS", max_attempts, 8),
+            backoff_secs: positive(&mut errors, "WEBHOOK_BACKOFF_SECS", backoff_secs, 30),
+        };
+
+        let inactive_minutes = layered(&mut errors, "PRESENCE_INACTIVE_MINUTES", file.presence.inactive_minutes);
+        let presence = PresenceConfig {
+            inactive_minutes: positive(&mut errors, "PRESENCE_INACTIVE_MINUTES", inactive_minutes, 30),
+            sweep_interval_secs: layered(&mut errors, "PRESENCE_SWEEP_INTERVAL_SECS", file.presence.sweep_interval_secs)
+                .unwrap_or(60)
+                .max(1),
+        };
+
+        let ttl_secs = layered(&mut errors, "TYPING_TTL_SECS", file.typing.ttl_secs);
+        let typing = TypingConfig {
+            ttl_secs: positive(&mut errors, "TYPING_TTL_SECS", ttl_secs, 5),
+            throttle_ms: layered(&mut errors, "TYPING_THROTTLE_MS", file.typing.throttle_ms).unwrap_or(1000),
+        };
+
+        let stream = StreamConfig {
+            replay_size: layered(&mut errors, "STREAM_REPLAY_SIZE", file.stream.replay_size).unwrap_or(100),
+            replay_secs: layered(&mut errors, "STREAM_REPLAY_SECS", file.stream.replay_secs).unwrap_or(600),
+        };
+
+        if !errors.is_empty() {
+            return Err(format!("Configuration invalide:\n  - {}", errors.join("\n  - ")));
+        }
+        Ok(Config {
+            server: ServerConfig { bind_address },
+            mongo,
+            postgres,
+            events,
+            health,
+            auth,
+            cors,
+            rate_limit,
+            requests,
+            campaigns,
+            push,
+            mail,
+            digest,
+            webhooks,
+            presence,
+            typing,
+            stream,
+        })
+    }
+
+    fn load_push(errors: &mut Vec<String>, file: FilePush) -> PushConfig {
+        let fcm_service_account_file = layered(errors, "FCM_SERVICE_ACCOUNT_FILE", file.fcm_service_account_file);
+        check_file(errors, "FCM_SERVICE_ACCOUNT_FILE", &fcm_service_account_file);
+
+        let apns_sandbox = layered_flag(errors, "APNS_SANDBOX", file.apns_sandbox).unwrap_or(false);
+        let apns = match (
+            layered(errors, "APNS_KEY_FILE", file.apns_key_file),
+            layered(errors, "APNS_KEY_ID", file.apns_key_id),
+            layered(errors, "APNS_TEAM_ID", file.apns_team_id),
+            layered(errors, "APNS_TOPIC", file.apns_topic),
+        ) {
+            (Some(key_file), Some(key_id), Some(team_id), Some(topic)) => {
+                check_file(errors, "APNS_KEY_FILE", &Some(key_file.clone()));
+                Some(ApnsConfig { key_file, key_id, team_id, topic, sandbox: apns_sandbox })
+            }
+            (None, None, None, None) => None,
+            _ => {
+                errors.push("APNs : APNS_KEY_FILE, APNS_KEY_ID, APNS_TEAM_ID et APNS_TOPIC vont ensemble".to_string());
+                None
+            }
+        };
+
+        let web_push = match (
+            layered(errors, "VAPID_PRIVATE_KEY_FILE", file.vapid_private_key_file),
+            layered(errors, "VAPID_PUBLIC_KEY", file.vapid_public_key),
+            layered(errors, "VAPID_SUBJECT", file.vapid_subject),
+        ) {
+            (Some(private_key_file), Some(public_key), Some(subject)) => {
+                check_file(errors, "VAPID_PRIVATE_KEY_FILE", &Some(private_key_file.clone()));
+                Some(WebPushConfig { private_key_file, public_key, subject })
+            }
+            (None, None, None) => None,
+            _ => {
+                errors.push(
+                    "Web Push : VAPID_PRIVATE_KEY_FILE, VAPID_PUBLIC_KEY et VAPID_SUBJECT vont ensemble".to_string(),
+                );
+                None
+            }
+        };
+
+        PushConfig {
+            mock: layered_flag(errors, "PUSH_MOCK", file.mock).unwrap_or(false),
+            fcm_service_account_file,
+            apns,
+            web_push,
+        }
+    }
+
+    fn load_mail(errors: &mut Vec<String>, file: FileMail) -> Option<MailConfig> {
+        let port = layered(errors, "SMTP_PORT", file.port);
+        let tls = layered(errors, "SMTP_TLS", file.tls).unwrap_or_else(|| "starttls".to_string());
+        let username = layered(errors, "SMTP_USERNAME", file.username);
+        let password = layered(errors, "SMTP_PASSWORD", file.password);
+        let from = layered(errors, "SMTP_FROM", file.from).unwrap_or_else(|| DEFAULT_MAIL_FROM.to_string());
+        let host = layered(errors, "SMTP_HOST", file.host)?;
+
+        if !SMTP_TLS_MODES.contains(&tls.as_str()) {
+            errors.push(format!("SMTP_TLS invalide: {} (attendu: {})", tls, SMTP_TLS_MODES.join(", ")));
+        }
+        if password.is_some() && username.is_none() {
+            errors.push("SMTP_PASSWORD sans SMTP_USERNAME".to_string());
+        }
+        if let Err(e) = from.parse::<lettre::message::Mailbox>() {
+            errors.push(format!("SMTP_FROM invalide: {}", e));
+        }
+        Some(MailConfig { host, port, tls, username, password, from })
+    }
+
+    /// Configuration au format TOML, mots de passe et secrets masqués
+    pub fn to_redacted_toml(&self) -> Result<String, String> {
+        let mut redacted = self.clone();
+        redacted.mongo.uri = mongo::redact_uri(&self.mongo.uri);
+        if let Some(postgres) = redacted.postgres.as_mut() {
+            postgres.password = REDACTED.to_string();
+        }
+        if redacted.auth.secret.is_some() {
+            redacted.auth.secret = Some(REDACTED.to_string());
+        }
+        if let Some(url) = redacted.rate_limit.redis_url.as_mut() {
+            *url = mongo::redact_uri(url);
+        }
+        if let Some(password) = redacted.mail.as_mut().and_then(|mail| mail.password.as_mut()) {
+            *password = REDACTED.to_string();
+        }
+        toml::to_string_pretty(&redacted).map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn jwt_secret_rejects_the_placeholder_and_short_keys() {
+        assert!(validate_jwt_secret("CHANGE_ME").is_err());
+        assert!(validate_jwt_secret("secret").is_err());
+        assert!(validate_jwt_secret(&"x".repeat(MIN_JWT_SECRET_BYTES - 1)).is_err());
+        assert!(validate_jwt_secret("9f2c4e7a1b8d3f6e0a5c2b9d4e7f1a3c8b6d0e2f").is_ok());
+    }
+}
@@ -0,0 +1,201 @@
+use actix_cors::Cors;
+use actix_web::http::{header::HeaderName, Method};
+use crate::config::CorsConfig;
+
+/// En-têtes de réponse lisibles par le navigateur (limitation de débit)
+const EXPOSED_HEADERS: [&str; 1] = ["Retry-After"];
+
+/// Origine autorisée : exacte, ou tout sous-domaine (`https://*.meet-voice.fr`)
+#[derive(Debug, Clone)]
+enum OriginPattern {
+    Exact(String),
+    Subdomains { scheme: String, suffix: String },
+}
+
+impl OriginPattern {
+    fn parse(origin: &str) -> Result<Self, String> {
+        let origin = origin.trim().trim_end_matches('/').to_ascii_lowercase();
+        let Some((scheme, host)) = origin.split_once("://") else {
+            return Err(format!("Origine CORS invalide: {} (attendu schema://hôte)", origin));
+        };
+        if host.is_empty() || host.contains('/') {
+            return Err(format!("Origine CORS invalide: {}", origin));
+        }
+        match host.strip_prefix("*.") {
+            Some(domain) if !domain.is_empty() && !domain.contains('*') => Ok(OriginPattern::Subdomains {
+                scheme: scheme.to_string(),
+                suffix: format!(".{}", domain),
+            }),
+            None if !host.contains('*') => Ok(OriginPattern::Exact(origin)),
+            _ => Err(format!(
+                "Origine CORS invalide: {} (seul le préfixe *. est accepté)",
+                origin
+            )),
+        }
+    }
+
+    fn matches(&self, origin: &str) -> bool {
+        match self {
+            OriginPattern::Exact(allowed) => allowed.eq_ignore_ascii_case(origin),
+            OriginPattern::Subdomains { scheme, suffix } => {
+                let origin = origin.to_ascii_lowercase();
+                origin
+                    .strip_prefix(scheme.as_str())
+                    .and_then(|rest| rest.strip_prefix("://"))
+                    .and_then(|host| host.strip_suffix(suffix.as_str()))
+                    .is_some_and(|subdomain| !subdomain.is_empty() && !subdomain.contains(['/', ':']))
+            }
+        }
+    }
+}
+
+/// Front lancé sur le poste du développeur : localhost, 127.0.0.1 ou [::1], tout port
+fn is_local_origin(origin: &str) -> bool {
+    let origin = origin.to_ascii_lowercase();
+    let Some(host) = origin.strip_prefix("http://").or_else(|| origin.strip_prefix("https://")) else {
+        return false;
+    };
+    let port = ["localhost", "127.0.0.1", "[::1]"]
+        .iter()
+        .find_map(|name| host.strip_prefix(name));
+    match port {
+        Some("") => true,
+        Some(port) => port
+            .strip_prefix(':')
+            .is_some_and(|port| port.parse::<u16>().is_ok() && port.chars().all(|c| c.is_ascii_digit())),
+        None => false,
+    }
+}
+
+/// Politique CORS des navigateurs, validée à partir de la section `cors` de la configuration
+#[derive(Debug, Clone)]
+pub struct CorsPolicy {
+    /// Développement : toute origine locale (localhost) est acceptée en plus des origines
+    /// configurées, ainsi que toute méthode et tout en-tête
+    dev_mode: bool,
+    origins: Vec<OriginPattern>,
+    methods: Vec<Method>,
+    headers: Vec<HeaderName>,
+    supports_credentials: bool,
+    max_age_secs: usize,
+}
+
+impl CorsPolicy {
+    pub fn from_config(config: &CorsConfig) -> Result<Self, String> {
+        if config.allowed_origins.iter().any(|origin| origin == "*") {
+            return Err("CORS_ALLOWED_ORIGINS=* refusé : lister les origines (CORS_DEV_MODE=true accepte localhost)".to_string());
+        }
+        let origins = config
+            .allowed_origins
+            .iter()
+            .map(|origin| OriginPattern::parse(origin))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let methods = config
+            .allowed_methods
+            .iter()
+            .map(|method| {
+                Method::from_bytes(method.to_ascii_uppercase().as_bytes())
+                    .map_err(|_| format!("Méthode CORS invalide: {}", method))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let headers = config
+            .allowed_headers
+            .iter()
+            .map(|header| {
+                HeaderName::try_from(header.as_str()).map_err(|_| format!("En-tête CORS invalide: {}", header))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(CorsPolicy {
+            dev_mode: config.dev_mode,
+            origins,
+            methods,
+            headers,
+            supports_credentials: config.allow_credentials,
+            max_age_secs: config.max_age_secs,
+        })
+    }
+
+    pub fn describe(&self) -> String {
+        let origins: Vec<String> = self
+            .origins
+            .iter()
+            .map(|origin| match origin {
+                OriginPattern::Exact(origin) => origin.clone(),
+                OriginPattern::Subdomains { scheme, suffix } => format!("{}://*{}", scheme, suffix),
+            })
+            .collect();
+        if self.dev_mode {
+            format!("mode développement (localhost), {}", origins.join(", "))
+        } else {
+            origins.join(", ")
+        }
+    }
+
+    /// Middleware CORS ; l'origine autorisée est renvoyée telle quelle, jamais `*`,
+    /// pour rester compatible avec les requêtes authentifiées
+    pub fn build(&self) -> Cors {
+        let origins = self.origins.clone();
+        let dev_mode = self.dev_mode;
+        let mut cors = Cors::default().allowed_origin_fn(move |origin, _| {
+            origin.to_str().is_ok_and(|origin| {
+                (dev_mode && is_local_origin(origin)) || origins.iter().any(|pattern| pattern.matches(origin))
+            })
+        });
+        cors = if self.dev_mode {
+            cors.allow_any_method().allow_any_header()
+        } else {
+            cors.allowed_methods(self.methods.clone()).allowed_headers(self.headers.clone())
+        };
+        cors = cors.expose_headers(EXPOSED_HEADERS).max_age(self.max_age_secs);
+        if self.supports_credentials {
+            cors = cors.supports_credentials();
+        }
+        cors
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exact_origin_matches_case_insensitively() {
+        let pattern = OriginPattern::parse("https://meet-voice.fr/").unwrap();
+        assert!(pattern.matches("https://meet-voice.fr"));
+        assert!(pattern.matches("HTTPS://Meet-Voice.fr"));
+        assert!(!pattern.matches("http://meet-voice.fr"));
+        assert!(!pattern.matches("https://meet-voice.fr.evil.com"));
+    }
+
+    #[test]
+    fn subdomain_pattern_requires_a_subdomain() {
+        let pattern = OriginPattern::parse("https://*.meet-voice.fr").unwrap();
+        assert!(pattern.matches("https://app.meet-voice.fr"));
+        assert!(pattern.matches("https://a.b.meet-voice.fr"));
+        assert!(!pattern.matches("https://meet-voice.fr"));
+        assert!(!pattern.matches("https://evilmeet-voice.fr"));
+        assert!(!pattern.matches("http://app.meet-voice.fr"));
+        assert!(!pattern.matches("https://app.meet-voice.fr:8443"));
+    }
+
+    #[test]
+    fn wildcards_other_than_a_subdomain_prefix_are_rejected() {
+        assert!(OriginPattern::parse("https://app.*.fr").is_err());
+        assert!(OriginPattern::parse("https://*").is_err());
+        assert!(OriginPattern::parse("meet-voice.fr").is_err());
+    }
+
+    #[test]
+    fn dev_mode_only_accepts_local_origins() {
+        assert!(is_local_origin("http://localhost:5173"));
+        assert!(is_local_origin("http://127.0.0.1"));
+        assert!(is_local_origin("https://[::1]:3000"));
+        assert!(!is_local_origin("http://localhost.evil.com"));
+        assert!(!is_local_origin("http://localhost:80@evil.com"));
+        assert!(!is_local_origin("https://meet-voice.fr"));
+        assert!(!is_local_origin("file://localhost"));
+    }
+}
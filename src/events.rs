@@ -0,0 +1,115 @@
+use futures_util::stream::TryStreamExt;
+use mongodb::{
+    bson::{doc, oid::ObjectId, Document},
+    options::FindOptions,
+    Database,
+};
+use std::collections::HashMap;
+use tokio::sync::broadcast;
+
+/// Nombre d'événements conservés pour un abonné en retard
+const EVENT_BUS_CAPACITY: usize = 16384;
+
+/// Marge de relecture : les `_id` générés côté client peuvent arriver légèrement dans le désordre
+const TAIL_OVERLAP_SECS: u32 = 10;
+
+/// Événement interne diffusé aux abonnés (push, webhooks, flux SSE)
+#[derive(Debug, Clone)]
+pub enum AppEvent {
+    /// Nouveau document de la collection `messages`
+    MessageCreated(Document),
+    /// Notification système devenue visible (créée ou programmée puis publiée)
+    NotificationPublished(Document),
+    /// Messages de `peer` marqués comme lus par `reader` ; avec `receipt_hidden`, seul `reader` en est informé
+    MessagesRead { reader: String, peer: String, count: u64, read_at: String, receipt_hidden: bool },
+    /// Indicateur de frappe, jamais enregistré
+    Typing { from: String, to: String, typing: bool },
+}
+
+/// Remplace `_id` par `id` pour les documents transmis hors du serveur
+pub fn event_data(mut document: Document) -> Document {
+    if let Ok(oid) = document.get_object_id("_id") {
+        document.insert("id", oid.to_hex());
+    }
+    document.remove("_id");
+    document
+}
+
+/// Bus d'événements en mémoire, partagé entre les tâches de fond
+#[derive(Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<AppEvent>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        let (sender, _) = broadcast::channel(EVENT_BUS_CAPACITY);
+        EventBus { sender }
+    }
+}
+
+impl EventBus {
+    /// Publie un événement ; ignoré s'il n'y a aucun abonné
+    pub fn publish(&self, event: AppEvent) {
+        let _ = self.sender.send(event);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<AppEvent> {
+        self.sender.subscribe()
+    }
+}
+
+/// Suivi des insertions d'une collection par scrutation de `_id`.
+///
+/// Les messages sont aussi écrits par la passerelle Node.js : on ne peut pas se
+/// contenter d'émettre les événements depuis nos handlers, et les change streams
+/// exigent un replica set.
+pub struct CollectionTail {
+    collection: &'static str,
+    /// Horodatage (secondes) du `_id` le plus récent vu
+    since: u32,
+    seen: HashMap<ObjectId, u32>,
+    primed: bool,
+}
+
+impl CollectionTail {
+    pub fn new(collection: &'static str) -> Self {
+        CollectionTail {
+            collection,
+            since: chrono::Utc::now().timestamp() as u32,
+            seen: HashMap::new(),
+            primed: false,
+        }
+    }
+
+    /// Documents insérés depuis le dernier appel. Le premier appel ne renvoie rien :
+    /// il marque comme vus les documents déjà présents au démarrage.
+    pub async fn poll(&mut self, db: &Database) -> Result<Vec<Document>, mongodb::error::Error> {
+        let from = ObjectId::from_parts(self.since.saturating_sub(TAIL_OVERLAP_SECS), [0; 5], [0; 3]);
+        let filter = doc! { "_id": { "$gte": from } };
+        let options = FindOptions::builder().sort(doc! { "_id": 1 }).build();
+
+        let mut cursor = db
+            .collection::<Document>(self.collection)
+            .find(filter, options)
+            .await?;
+
+        let mut inserted = Vec::new();
+        while let Some(document) = cursor.try_next().await? {
+            let Ok(oid) = document.get_object_id("_id") else {
+                continue;
+            };
+            let seconds = (oid.timestamp().timestamp_millis() / 1000) as u32;
+            self.since = self.since.max(seconds);
+            if self.seen.insert(oid, seconds).is_none() && self.primed {
+                inserted.push(document);
+            }
+        }
+
+        let horizon = self.since.saturating_sub(TAIL_OVERLAP_SECS);
+        self.seen.retain(|_, seconds| *seconds >= horizon);
+        self.primed = true;
+
+        Ok(inserted)
+    }
+}
@@ -1,9 +1,34 @@
-use actix_web::{web, HttpResponse};
+use actix_web::{web, HttpRequest, HttpResponse};
+use chrono::{DateTime, Utc};
 use mongodb::{bson::doc, Database};
 use serde_json::json;
 use futures_util::stream::TryStreamExt;
+use std::collections::HashMap;
 use std::sync::Arc;
 use crate::{ApiResponse, Message};
+use crate::auth::{AuthenticatedUser, Caller};
+use crate::authz::ensure_user;
+use crate::events::{AppEvent, EventBus};
+use crate::rate_limit::{RateLimitAction, RateLimiter};
+use crate::services::notification_service::{normalize_timestamp, NewNotification, NotificationService};
+use crate::services::presence_service::{PresenceRegistry, PresenceService};
+use crate::services::privacy_service::{PrivacyService, Visibility};
+use crate::services::template_service::TemplateService;
+use crate::services::webhook_service::WebhookService;
+
+pub mod api_keys;
+pub mod debug;
+pub mod health;
+pub mod notifications;
+pub mod preferences;
+pub mod presence;
+pub mod privacy;
+pub mod push;
+pub mod requests;
+pub mod stream;
+pub mod templates;
+pub mod typing;
+pub mod webhooks;
 
 #[derive(serde::Serialize, serde::Deserialize)]
 pub struct UserInfo {
@@ -13,6 +38,10 @@ pub struct UserInfo {
     pub photo: String,
     pub last_message: String,
     pub last_timestamp: String,
+    #[serde(default)]
+    pub is_online: Option<bool>,
+    #[serde(default)]
+    pub last_seen: Option<DateTime<Utc>>,
 }
 
 #[derive(serde::Serialize, serde::Deserialize)]
@@ -27,9 +56,13 @@ pub struct ConversationResponse {
     pub count: usize,
 }
 
+// GET /api/messages/history/{username}
+// Lecture du profil antérieure au passage de clippy, conservée telle quelle
+#[allow(clippy::match_result_ok, clippy::collapsible_match)]
 pub async fn get_history(
     db: web::Data<Database>,
     pg_client: web::Data<Option<Arc<tokio_postgres::Client>>>,
+    presence: web::Data<Arc<PresenceRegistry>>,
     username: web::Path<String>,
 ) -> HttpResponse {
     let username = username.into_inner();
@@ -45,7 +78,6 @@ pub async fn get_history(
 
     match messages_collection.find(filter, None).await {
         Ok(mut cursor) => {
-            use std::collections::HashMap;
             let mut conversations_map: HashMap<String, UserInfo> = HashMap::new();
 
             // Parcourir tous les messages et construire les conversations
@@ -71,6 +103,8 @@ pub async fn get_history(
                         photo: String::new(),
                         last_message: message,
                         last_timestamp: timestamp,
+                        is_online: None,
+                        last_seen: None,
                     });
             }
 
@@ -139,6 +173,30 @@ pub async fn get_history(
                 }
             }
 
+            // Présence de tous les interlocuteurs en une seule requête, filtrée selon la confidentialité
+            let peers: Vec<String> = conversations_map.keys().cloned().collect();
+            let mut statuses = PresenceService::status_many(&presence, pg_client.as_deref(), &peers).await;
+            let mut privacy_users = peers.clone();
+            privacy_users.push(username.clone());
+            let privacy = match PrivacyService::get_many(&db, &privacy_users).await {
+                Ok(privacy) => privacy,
+                Err(e) => {
+                    log::error!("Erreur MongoDB: {}", e);
+                    return HttpResponse::InternalServerError()
+                        .json(ApiResponse::<()>::err(format!("Erreur: {}", e)));
+                }
+            };
+            let viewer_privacy = privacy.get(&username).copied().unwrap_or_default();
+            for conv_info in conversations_map.values_mut() {
+                let peer_privacy = privacy.get(&conv_info.username).copied().unwrap_or_default();
+                let visibility = Visibility::between(&viewer_privacy, &peer_privacy);
+                let status = statuses.remove(&conv_info.username);
+                conv_info.is_online = visibility
+                    .online_status
+                    .then(|| status.as_ref().is_some_and(|p| p.is_online));
+                conv_info.last_seen = status.and_then(|p| p.last_seen).filter(|_| visibility.last_seen);
+            }
+
             let mut conversations: Vec<UserInfo> = conversations_map.into_values().collect();
             // Trier par timestamp décroissant (plus récent en premier)
             conversations.sort_by(|a, b| b.last_timestamp.cmp(&a.last_timestamp));
@@ -158,12 +216,29 @@ pub async fn get_history(
     }
 }
 
+// GET /api/messages/conversation/{user1}/{user2}
+// Conversation vue par l'appelant (à défaut `user1`) : l'état de lecture de ses messages
+// n'apparaît que si aucun des deux ne masque les confirmations de lecture
 pub async fn get_conversation(
+    user: Option<AuthenticatedUser>,
     db: web::Data<Database>,
     path: web::Path<(String, String)>,
 ) -> HttpResponse {
     let (user1, user2) = path.into_inner();
     let messages_collection = db.collection::<mongodb::bson::Document>("messages");
+    let (viewer, peer) = match user {
+        Some(user) if user.username == user2 => (&user2, &user1),
+        _ => (&user1, &user2),
+    };
+
+    let visibility = match PrivacyService::visibility(&db, viewer, peer).await {
+        Ok(visibility) => visibility,
+        Err(e) => {
+            log::error!("Erreur MongoDB: {}", e);
+            return HttpResponse::InternalServerError()
+                .json(ApiResponse::<()>::err(format!("Erreur: {}", e)));
+        }
+    };
 
     let filter = doc! {
         "$or": [
@@ -176,7 +251,10 @@ pub async fn get_conversation(
         Ok(mut cursor) => {
             let mut messages = Vec::new();
             while let Ok(Some(doc)) = cursor.try_next().await {
-                if let Ok(msg) = convert_doc_to_message(doc) {
+                if let Ok(mut msg) = convert_doc_to_message(doc) {
+                    if &msg.from == viewer && !visibility.read_receipts {
+                        msg.read = false;
+                    }
                     messages.push(msg);
                 }
             }
@@ -234,6 +312,135 @@ pub async fn delete_conversation(
     }
 }
 
+// PUT /api/messages/conversation/{reader}/{peer}/read
+// Marque comme lus les messages reçus de `peer`
+pub async fn mark_conversation_read(
+    db: web::Data<Database>,
+    bus: web::Data<EventBus>,
+    path: web::Path<(String, String)>,
+) -> HttpResponse {
+    let (reader, peer) = path.into_inner();
+    let messages_collection = db.collection::<mongodb::bson::Document>("messages");
+    let read_at = chrono::Utc::now().to_rfc3339();
+
+    let visibility = match PrivacyService::visibility(&db, &reader, &peer).await {
+        Ok(visibility) => visibility,
+        Err(e) => {
+            log::error!("Erreur MongoDB: {}", e);
+            return HttpResponse::InternalServerError()
+                .json(ApiResponse::<()>::err(format!("Erreur: {}", e)));
+        }
+    };
+
+    let filter = doc! {
+        "from": &peer,
+        "to": &reader,
+        "read": { "$ne": true }
+    };
+
+    match messages_collection
+        .update_many(filter, doc! { "$set": { "read": true, "read_at": &read_at } }, None)
+        .await
+    {
+        Ok(result) => {
+            if result.modified_count > 0 {
+                let data = doc! {
+                    "reader": &reader,
+                    "peer": &peer,
+                    "count": result.modified_count as i64,
+                    "read_at": &read_at,
+                };
+                // Confirmation de lecture masquée : rien ne doit parvenir à l'expéditeur
+                if visibility.read_receipts {
+                    if let Err(e) = WebhookService::enqueue(&db, "message.read", data).await {
+                        log::error!("Erreur webhook message.read: {}", e);
+                    }
+                }
+                bus.publish(AppEvent::MessagesRead {
+                    reader: reader.clone(),
+                    peer: peer.clone(),
+                    count: result.modified_count,
+                    read_at: read_at.clone(),
+                    receipt_hidden: !visibility.read_receipts,
+                });
+            }
+
+            HttpResponse::Ok().json(ApiResponse::ok(json!({
+                "read_count": result.modified_count,
+                "read_at": read_at,
+            })))
+        }
+        Err(e) => {
+            log::error!("Erreur MongoDB: {}", e);
+            HttpResponse::InternalServerError()
+                .json(ApiResponse::<()>::err(format!("Erreur: {}", e)))
+        }
+    }
+}
+
+#[derive(serde::Deserialize)]
+pub struct SendMessageRequest {
+    pub from: String,
+    pub to: String,
+    pub message: String,
+}
+
+// POST /api/messages/send
+// Limité par expéditeur et par adresse IP ; la diffusion (flux, push, webhooks) suit
+// l'insertion via la surveillance de la collection `messages`
+pub async fn send_message(
+    http_req: HttpRequest,
+    user: Option<AuthenticatedUser>,
+    db: web::Data<Database>,
+    limiter: web::Data<Arc<RateLimiter>>,
+    req: web::Json<SendMessageRequest>,
+) -> HttpResponse {
+    if req.from.is_empty() || req.to.is_empty() || req.message.trim().is_empty() {
+        return HttpResponse::BadRequest()
+            .json(ApiResponse::<()>::err("from, to et message sont requis".to_string()));
+    }
+    if req.from == req.to {
+        return HttpResponse::BadRequest()
+            .json(ApiResponse::<()>::err("Impossible de s'envoyer un message".to_string()));
+    }
+    if let Err(response) = ensure_user(user.as_ref(), &req.from) {
+        return response;
+    }
+    if let Err(response) = limiter.check(RateLimitAction::SendMessage, &http_req, &req.from).await {
+        return response;
+    }
+
+    let mut message = Message {
+        id: None,
+        from: req.from.clone(),
+        to: req.to.clone(),
+        message: req.message.clone(),
+        timestamp: Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+        read: false,
+        is_connect: false,
+    };
+    let document = doc! {
+        "from": &message.from,
+        "to": &message.to,
+        "message": &message.message,
+        "timestamp": &message.timestamp,
+        "read": false,
+        "is_connect": false,
+    };
+
+    match db.collection::<mongodb::bson::Document>("messages").insert_one(document, None).await {
+        Ok(result) => {
+            message.id = result.inserted_id.as_object_id().map(|oid| oid.to_string());
+            HttpResponse::Created().json(ApiResponse::ok(message))
+        }
+        Err(e) => {
+            log::error!("Erreur MongoDB: {}", e);
+            HttpResponse::InternalServerError()
+                .json(ApiResponse::<()>::err(format!("Erreur: {}", e)))
+        }
+    }
+}
+
 fn convert_doc_to_message(doc: mongodb::bson::Document) -> Result<Message, Box<dyn std::error::Error>> {
     let id = doc.get_object_id("_id").ok().map(|oid| oid.to_string());
     let from = doc.get_str("from").unwrap_or("").to_string();
@@ -260,122 +467,110 @@ fn convert_doc_to_message(doc: mongodb::bson::Document) -> Result<Message, Box<d
 #[derive(serde::Deserialize)]
 pub struct CreateSystemNotificationRequest {
     pub to: String,
-    pub title: String,
-    pub message: String,
+    /// Obligatoires sans `template_key`
+    pub title: Option<String>,
+    pub message: Option<String>,
+    /// Modèle rendu dans la langue du destinataire à la place de `title`/`message`
+    pub template_key: Option<String>,
+    #[serde(default)]
+    pub variables: HashMap<String, String>,
+    pub r#type: Option<String>,
     pub priority: Option<String>,
     pub action_url: Option<String>,
-    pub created_by: String,
+    /// Pris en compte uniquement si l'authentification est désactivée ; sinon, nom du
+    /// service (clé d'API) ou de l'administrateur appelant
+    pub created_by: Option<String>,
+    /// Publication différée (RFC 3339)
+    pub send_at: Option<String>,
+    /// Date après laquelle la notification n'est plus affichée (RFC 3339)
+    pub expires_at: Option<String>,
+    /// Remplace la notification non lue de même clé au lieu d'en créer une nouvelle
+    pub collapse_key: Option<String>,
+    /// Message agrégé avec `{count}`, ex: "{count} nouvelles demandes"
+    pub collapse_summary: Option<String>,
+    /// Lien de la notification agrégée, `action_url` à défaut
+    pub collapse_action_url: Option<String>,
 }
 
 pub async fn create_system_notification(
+    caller: Caller,
     db: web::Data<Database>,
     req: web::Json<CreateSystemNotificationRequest>,
 ) -> HttpResponse {
-    let notifications_collection = db.collection::<mongodb::bson::Document>("system_notifications");
-
-    let now = chrono::Utc::now().to_rfc3339();
-
-    let doc = doc! {
-        "from": "meet-voice.fr",
-        "to": &req.to,
-        "type": "system",
-        "title": &req.title,
-        "message": &req.message,
-        "timestamp": &now,
-        "read": false,
-        "priority": req.priority.as_deref().unwrap_or("normal"),
-        "action_url": &req.action_url,
-        "created_by": &req.created_by,
+    let (send_at, expires_at) = match parse_schedule(req.send_at.as_deref(), req.expires_at.as_deref()) {
+        Ok(schedule) => schedule,
+        Err(response) => return response,
     };
 
-    match notifications_collection.insert_one(doc, None).await {
-        Ok(result) => {
-            HttpResponse::Created().json(ApiResponse::ok(json!({
-                "id": result.inserted_id.to_string(),
-                "message": "Notification créée avec succès"
-            })))
-        }
-        Err(e) => {
-            log::error!("Erreur MongoDB: {}", e);
-            HttpResponse::InternalServerError()
-                .json(ApiResponse::<()>::err(format!("Erreur: {}", e)))
+    let (title, message, locale, template_type, template_priority) = match &req.template_key {
+        Some(key) => {
+            let template = match TemplateService::get(&db, key).await {
+                Ok(Some(template)) => template,
+                Ok(None) => {
+                    return HttpResponse::BadRequest()
+                        .json(ApiResponse::<()>::err(format!("Modèle inconnu: {}", key)));
+                }
+                Err(e) => {
+                    log::error!("Erreur MongoDB: {}", e);
+                    return HttpResponse::InternalServerError()
+                        .json(ApiResponse::<()>::err(format!("Erreur: {}", e)));
+                }
+            };
+            let locale = match TemplateService::recipient_locale(&db, &req.to).await {
+                Ok(locale) => locale,
+                Err(e) => {
+                    log::error!("Erreur MongoDB: {}", e);
+                    return HttpResponse::InternalServerError()
+                        .json(ApiResponse::<()>::err(format!("Erreur: {}", e)));
+                }
+            };
+            match template.render(&locale, &req.variables) {
+                Ok(rendered) => (
+                    rendered.title,
+                    rendered.message,
+                    Some(rendered.locale),
+                    template.r#type,
+                    template.priority,
+                ),
+                Err(e) => return HttpResponse::BadRequest().json(ApiResponse::<()>::err(e)),
+            }
         }
-    }
-}
-
-// 2. POST /api/requests/group-access
-#[derive(serde::Deserialize)]
-pub struct CreateGroupAccessRequest {
-    pub requester_username: String,
-    pub group_id: String,
-    pub group_name: String,
-    pub group_owner: String,
-}
+        None => match (&req.title, &req.message) {
+            (Some(title), Some(message)) => (title.clone(), message.clone(), None, None, None),
+            _ => {
+                return HttpResponse::BadRequest()
+                    .json(ApiResponse::<()>::err("title et message requis sans template_key".to_string()));
+            }
+        },
+    };
 
-pub async fn create_group_access_request(
-    db: web::Data<Database>,
-    req: web::Json<CreateGroupAccessRequest>,
-) -> HttpResponse {
-    let requests_collection = db.collection::<mongodb::bson::Document>("group_access_requests");
-
-    let now = chrono::Utc::now().to_rfc3339();
-
-    let doc = doc! {
-        "requester_username": &req.requester_username,
-        "group_id": &req.group_id,
-        "group_name": &req.group_name,
-        "group_owner": &req.group_owner,
-        "status": "pending",
-        "timestamp": &now,
-        "response_timestamp": null,
-        "response_message": null,
+    let notification = NewNotification {
+        to: req.to.clone(),
+        r#type: req.r#type.clone().or(template_type).unwrap_or_else(|| "system".to_string()),
+        title,
+        message,
+        priority: req.priority.clone().or(template_priority).unwrap_or_else(|| "normal".to_string()),
+        action_url: req.action_url.clone(),
+        created_by: caller.0.or_else(|| req.created_by.clone()).unwrap_or_else(|| "system".to_string()),
+        send_at,
+        expires_at,
+        collapse_key: req.collapse_key.clone(),
+        collapse_summary: req.collapse_summary.clone(),
+        collapse_action_url: req.collapse_action_url.clone(),
     };
 
-    match requests_collection.insert_one(doc, None).await {
-        Ok(result) => {
+    match NotificationService::create(&db, &notification).await {
+        Ok(Some(id)) => {
             HttpResponse::Created().json(ApiResponse::ok(json!({
-                "id": result.inserted_id.to_string(),
-                "message": "Demande d'accès au groupe créée"
+                "id": id,
+                "locale": locale,
+                "message": "Notification créée avec succès"
             })))
         }
-        Err(e) => {
-            log::error!("Erreur MongoDB: {}", e);
-            HttpResponse::InternalServerError()
-                .json(ApiResponse::<()>::err(format!("Erreur: {}", e)))
-        }
-    }
-}
-
-// 3. POST /api/requests/private-photos-permission
-#[derive(serde::Deserialize)]
-pub struct CreatePhotoPermissionRequest {
-    pub requester_username: String,
-    pub target_username: String,
-}
-
-pub async fn create_photo_permission_request(
-    db: web::Data<Database>,
-    req: web::Json<CreatePhotoPermissionRequest>,
-) -> HttpResponse {
-    let requests_collection = db.collection::<mongodb::bson::Document>("photo_permission_requests");
-
-    let now = chrono::Utc::now().to_rfc3339();
-
-    let doc = doc! {
-        "requester_username": &req.requester_username,
-        "target_username": &req.target_username,
-        "status": "pending",
-        "timestamp": &now,
-        "response_timestamp": null,
-        "response_message": null,
-        "permission_expires_at": null,
-    };
-
-    match requests_collection.insert_one(doc, None).await {
-        Ok(result) => {
-            HttpResponse::Created().json(ApiResponse::ok(json!({
-                "id": result.inserted_id.to_string(),
-                "message": "Demande de permission photos créée"
+        Ok(None) => {
+            HttpResponse::Ok().json(ApiResponse::ok(json!({
+                "id": null,
+                "message": "Notification désactivée par le destinataire"
             })))
         }
         Err(e) => {
@@ -386,48 +581,28 @@ pub async fn create_photo_permission_request(
     }
 }
 
-// 4. POST /api/requests/event-participation
-#[derive(serde::Deserialize)]
-pub struct CreateEventParticipationRequest {
-    pub requester_username: String,
-    pub event_id: String,
-    pub event_name: String,
-    pub event_creator: String,
-    pub participation_role: Option<String>,
-}
-
-pub async fn create_event_participation_request(
-    db: web::Data<Database>,
-    req: web::Json<CreateEventParticipationRequest>,
-) -> HttpResponse {
-    let requests_collection = db.collection::<mongodb::bson::Document>("event_participation_requests");
-
-    let now = chrono::Utc::now().to_rfc3339();
-
-    let doc = doc! {
-        "requester_username": &req.requester_username,
-        "event_id": &req.event_id,
-        "event_name": &req.event_name,
-        "event_creator": &req.event_creator,
-        "status": "pending",
-        "timestamp": &now,
-        "response_timestamp": null,
-        "response_message": null,
-        "participation_role": req.participation_role.as_deref().unwrap_or("participant"),
+/// Valide et normalise `send_at` / `expires_at` d'une notification programmée
+pub fn parse_schedule(
+    send_at: Option<&str>,
+    expires_at: Option<&str>,
+) -> Result<(Option<String>, Option<String>), HttpResponse> {
+    let parse = |field: &str, value: Option<&str>| match value {
+        None => Ok(None),
+        Some(value) => normalize_timestamp(value).map(Some).ok_or_else(|| {
+            HttpResponse::BadRequest()
+                .json(ApiResponse::<()>::err(format!("{} doit être une date RFC 3339", field)))
+        }),
     };
 
-    match requests_collection.insert_one(doc, None).await {
-        Ok(result) => {
-            HttpResponse::Created().json(ApiResponse::ok(json!({
-                "id": result.inserted_id.to_string(),
-                "message": "Demande de participation créée"
-            })))
-        }
-        Err(e) => {
-            log::error!("Erreur MongoDB: {}", e);
-            HttpResponse::InternalServerError()
-                .json(ApiResponse::<()>::err(format!("Erreur: {}", e)))
+    let send_at = parse("send_at", send_at)?;
+    let expires_at = parse("expires_at", expires_at)?;
+
+    if let (Some(send_at), Some(expires_at)) = (&send_at, &expires_at) {
+        if expires_at <= send_at {
+            return Err(HttpResponse::BadRequest()
+                .json(ApiResponse::<()>::err("expires_at doit être postérieur à send_at".to_string())));
         }
     }
-}
 
+    Ok((send_at, expires_at))
+}
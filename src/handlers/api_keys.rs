@@ -0,0 +1,100 @@
+use actix_web::{web, HttpResponse};
+use mongodb::{
+    bson::{Bson, Document},
+    Database,
+};
+use serde_json::json;
+use crate::ApiResponse;
+use crate::services::api_key_service::{ApiKeyService, NewApiKey, DEFAULT_ROTATION_GRACE_SECS};
+
+#[derive(serde::Deserialize)]
+pub struct RotateQuery {
+    /// Durée de validité restante de l'ancienne clé ; 0 pour l'invalider immédiatement
+    pub grace_secs: Option<i64>,
+}
+
+fn api_key_to_json(mut api_key: Document) -> serde_json::Value {
+    if let Ok(oid) = api_key.get_object_id("_id") {
+        api_key.insert("id", oid.to_hex());
+    }
+    api_key.remove("_id");
+    Bson::Document(api_key).into_relaxed_extjson()
+}
+
+fn database_error(e: mongodb::error::Error) -> HttpResponse {
+    log::error!("Erreur MongoDB: {}", e);
+    HttpResponse::InternalServerError()
+        .json(ApiResponse::<()>::err(format!("Erreur: {}", e)))
+}
+
+fn not_found() -> HttpResponse {
+    HttpResponse::NotFound().json(ApiResponse::<()>::err("Clé d'API introuvable ou révoquée".to_string()))
+}
+
+// POST /api/api-keys
+pub async fn create_api_key(
+    db: web::Data<Database>,
+    req: web::Json<NewApiKey>,
+) -> HttpResponse {
+    if let Err(e) = req.validate() {
+        return HttpResponse::BadRequest().json(ApiResponse::<()>::err(e));
+    }
+
+    match ApiKeyService::create(&db, &req).await {
+        Ok((id, key)) => HttpResponse::Created().json(ApiResponse::ok(json!({
+            "id": id.to_hex(),
+            "name": &req.name,
+            "scopes": &req.scopes,
+            // Seule occasion de lire la clé : seule son empreinte est conservée
+            "key": key,
+        }))),
+        Err(e) => database_error(e),
+    }
+}
+
+// GET /api/api-keys
+pub async fn list_api_keys(db: web::Data<Database>) -> HttpResponse {
+    match ApiKeyService::list(&db).await {
+        Ok(keys) => {
+            let keys: Vec<serde_json::Value> = keys.into_iter().map(api_key_to_json).collect();
+            HttpResponse::Ok().json(ApiResponse::ok(json!({
+                "count": keys.len(),
+                "api_keys": keys,
+            })))
+        }
+        Err(e) => database_error(e),
+    }
+}
+
+// POST /api/api-keys/{id}/rotate?grace_secs=3600
+pub async fn rotate_api_key(
+    db: web::Data<Database>,
+    id: web::Path<String>,
+    query: web::Query<RotateQuery>,
+) -> HttpResponse {
+    let grace_secs = query.grace_secs.unwrap_or(DEFAULT_ROTATION_GRACE_SECS).max(0);
+    match ApiKeyService::rotate(&db, &id, grace_secs).await {
+        Ok(Some(key)) => HttpResponse::Ok().json(ApiResponse::ok(json!({
+            "id": id.into_inner(),
+            "key": key,
+            "previous_key_valid_for_secs": grace_secs,
+        }))),
+        Ok(None) => not_found(),
+        Err(e) => database_error(e),
+    }
+}
+
+// DELETE /api/api-keys/{id}
+pub async fn revoke_api_key(
+    db: web::Data<Database>,
+    id: web::Path<String>,
+) -> HttpResponse {
+    match ApiKeyService::revoke(&db, &id).await {
+        Ok(true) => HttpResponse::Ok().json(ApiResponse::ok(json!({
+            "id": id.into_inner(),
+            "revoked": true,
+        }))),
+        Ok(false) => not_found(),
+        Err(e) => database_error(e),
+    }
+}
@@ -0,0 +1,89 @@
+use actix_web::{web, HttpResponse};
+use mongodb::{bson::doc, Database};
+use serde_json::json;
+use crate::ApiResponse;
+
+/// Collections comptées par `/api/debug/stats`
+const DEBUG_COLLECTIONS: [&str; 6] = [
+    "messages",
+    "conversations",
+    "system_notifications",
+    "user_preferences",
+    "device_tokens",
+    "webhook_deliveries",
+];
+
+// Endpoints de diagnostic, réservés aux administrateurs (voir `authz`)
+
+// GET /api/debug/messages/count
+pub async fn count_messages(db: web::Data<Database>) -> HttpResponse {
+    let collection = db.collection::<mongodb::bson::Document>("messages");
+
+    match collection.count_documents(doc! {}, None).await {
+        Ok(count) => HttpResponse::Ok().json(ApiResponse::ok(json!({
+            "total_messages": count,
+            "database": db.name(),
+            "collection": "messages",
+        }))),
+        Err(e) => {
+            log::error!("Erreur MongoDB: {}", e);
+            HttpResponse::InternalServerError()
+                .json(ApiResponse::<()>::err(format!("Erreur: {}", e)))
+        }
+    }
+}
+
+// GET /api/debug/collections
+pub async fn list_collections(db: web::Data<Database>) -> HttpResponse {
+    match db.list_collection_names(None).await {
+        Ok(collections) => HttpResponse::Ok().json(ApiResponse::ok(json!({
+            "collections": collections,
+            "database": db.name(),
+        }))),
+        Err(e) => {
+            log::error!("Erreur MongoDB: {}", e);
+            HttpResponse::InternalServerError()
+                .json(ApiResponse::<()>::err(format!("Erreur: {}", e)))
+        }
+    }
+}
+
+// GET /api/debug/stats
+pub async fn collection_stats(db: web::Data<Database>) -> HttpResponse {
+    let mut stats = serde_json::Map::new();
+
+    for collection_name in DEBUG_COLLECTIONS {
+        let collection = db.collection::<mongodb::bson::Document>(collection_name);
+        let stat = match collection.count_documents(doc! {}, None).await {
+            Ok(count) => json!({ "count": count }),
+            Err(e) => json!({ "error": e.to_string() }),
+        };
+        stats.insert(collection_name.to_string(), stat);
+    }
+
+    HttpResponse::Ok().json(ApiResponse::ok(stats))
+}
+
+// GET /api/debug/users
+pub async fn list_users(db: web::Data<Database>) -> HttpResponse {
+    let collection = db.collection::<mongodb::bson::Document>("messages");
+
+    let users = match collection.distinct("from", None, None).await {
+        Ok(from_users) => collection.distinct("to", None, None).await.map(|to_users| (from_users, to_users)),
+        Err(e) => Err(e),
+    };
+
+    match users {
+        Ok((from_users, to_users)) => HttpResponse::Ok().json(ApiResponse::ok(json!({
+            "from_count": from_users.len(),
+            "to_count": to_users.len(),
+            "from_users": from_users,
+            "to_users": to_users,
+        }))),
+        Err(e) => {
+            log::error!("Erreur MongoDB: {}", e);
+            HttpResponse::InternalServerError()
+                .json(ApiResponse::<()>::err(format!("Erreur: {}", e)))
+        }
+    }
+}
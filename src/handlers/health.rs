@@ -1,32 +1,90 @@
-use actix_web::HttpResponse;
-use serde_json::json;
+use actix_web::{web, HttpResponse};
+use mongodb::Database;
+use serde_json::{json, Value};
+use std::fmt::Display;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use crate::config::mongo;
+use crate::config::HealthConfig;
 
-pub async fn index() -> HttpResponse {
-    HttpResponse::Ok().json(json!({
-        "name": "Messagerie Rush API",
-        "version": "1.0.0",
-        "framework": "Actix-web (Rust)",
-        "endpoints": {
-            "health": "/health",
-            "messages": {
-                "history": "GET /api/messages/history/{username}?limit=100",
-                "conversation": "GET /api/messages/conversation/{user1}/{user2}?limit=100",
-                "send": "POST /api/messages/send"
-            },
-            "users": {
-                "connect": "POST /api/users/connect",
-                "disconnect": "POST /api/users/disconnect",
-                "online": "GET /api/users/online",
-                "status": "GET /api/users/status/{username}"
-            }
-        }
-    }))
+// Sondes hors /api : pas d'authentification, pas d'enveloppe ApiResponse
+
+/// Exécute une vérification dans le délai imparti ; renvoie son résultat et sa durée
+async fn timed<F, E>(timeout: Duration, check: F) -> (Result<(), String>, u128)
+where
+    F: Future<Output = Result<(), E>>,
+    E: Display,
+{
+    let started = Instant::now();
+    let result = match tokio::time::timeout(timeout, check).await {
+        Ok(Ok(())) => Ok(()),
+        Ok(Err(e)) => Err(e.to_string()),
+        Err(_) => Err(format!("délai dépassé ({} ms)", timeout.as_millis())),
+    };
+    (result, started.elapsed().as_millis())
+}
+
+fn check_report(result: &Result<(), String>, latency_ms: Option<u128>, required: bool) -> Value {
+    let mut report = json!({
+        "status": if result.is_ok() { "up" } else { "down" },
+        "required": required,
+        "latency_ms": latency_ms,
+    });
+    if let Err(e) = result {
+        report["error"] = json!(e);
+    }
+    report
 }
 
-pub async fn health() -> HttpResponse {
+// GET /health/live
+// Le processus répond ; les dépendances ne sont pas vérifiées
+pub async fn live() -> HttpResponse {
     HttpResponse::Ok().json(json!({
-        "status": "OK",
-        "timestamp": chrono::Utc::now()
+        "status": "ok",
+        "timestamp": chrono::Utc::now(),
     }))
 }
 
+// GET /health/ready
+// 503 si une dépendance requise (MongoDB, PostgreSQL avec HEALTH_REQUIRE_POSTGRES) est indisponible
+pub async fn ready(
+    db: web::Data<Database>,
+    pg_client: web::Data<Option<Arc<tokio_postgres::Client>>>,
+    config: web::Data<HealthConfig>,
+) -> HttpResponse {
+    let timeout = Duration::from_millis(config.timeout_ms);
+
+    let mongo_check = timed(timeout, mongo::ping(&db));
+    let postgres_check = async {
+        match pg_client.as_ref() {
+            Some(client) if !client.is_closed() => {
+                let (result, latency) = timed(timeout, async { client.simple_query("SELECT 1").await.map(|_| ()) }).await;
+                (result, Some(latency))
+            }
+            Some(_) => (Err("connexion fermée".to_string()), None),
+            None => (Err("non connecté".to_string()), None),
+        }
+    };
+    let ((mongo_result, mongo_latency), (postgres_result, postgres_latency)) =
+        tokio::join!(mongo_check, postgres_check);
+
+    let ready = mongo_result.is_ok() && (postgres_result.is_ok() || !config.require_postgres);
+    if let Err(e) = &mongo_result {
+        log::warn!("🩺 MongoDB indisponible: {}", e);
+    }
+
+    let body = json!({
+        "status": if ready { "ready" } else { "not_ready" },
+        "timestamp": chrono::Utc::now(),
+        "checks": {
+            "mongodb": check_report(&mongo_result, Some(mongo_latency), true),
+            "postgresql": check_report(&postgres_result, postgres_latency, config.require_postgres),
+        },
+    });
+    if ready {
+        HttpResponse::Ok().json(body)
+    } else {
+        HttpResponse::ServiceUnavailable().json(body)
+    }
+}
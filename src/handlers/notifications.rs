@@ -0,0 +1,265 @@
+use actix_web::{web, HttpResponse};
+use mongodb::{
+    bson::{Bson, Document},
+    Database,
+};
+use serde_json::json;
+use std::sync::Arc;
+use crate::ApiResponse;
+use crate::auth::Caller;
+use crate::config::CampaignConfig;
+use crate::services::campaign_service::{CampaignService, NewCampaign};
+use crate::services::notification_service::{NotificationFilter, NotificationService};
+
+const DEFAULT_NOTIFICATIONS_LIMIT: i64 = 50;
+const MAX_NOTIFICATIONS_LIMIT: i64 = 200;
+
+#[derive(serde::Deserialize)]
+pub struct NotificationsQuery {
+    /// `true` pour ne garder que les non lues
+    #[serde(default)]
+    pub unread: bool,
+    pub priority: Option<String>,
+    pub r#type: Option<String>,
+    /// Pagination : notifications antérieures à ce timestamp
+    pub before: Option<String>,
+    pub limit: Option<i64>,
+}
+
+/// Remplace `_id` par un `id` lisible
+fn document_to_json(mut document: Document) -> serde_json::Value {
+    if let Ok(oid) = document.get_object_id("_id") {
+        document.insert("id", oid.to_hex());
+    }
+    document.remove("_id");
+    Bson::Document(document).into_relaxed_extjson()
+}
+
+fn database_error(e: mongodb::error::Error) -> HttpResponse {
+    log::error!("Erreur MongoDB: {}", e);
+    HttpResponse::InternalServerError()
+        .json(ApiResponse::<()>::err(format!("Erreur: {}", e)))
+}
+
+fn not_found() -> HttpResponse {
+    HttpResponse::NotFound()
+        .json(ApiResponse::<()>::err("Notification introuvable".to_string()))
+}
+
+// GET /api/notifications/{username}
+pub async fn list_notifications(
+    db: web::Data<Database>,
+    username: web::Path<String>,
+    query: web::Query<NotificationsQuery>,
+) -> HttpResponse {
+    let username = username.into_inner();
+    let query = query.into_inner();
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_NOTIFICATIONS_LIMIT)
+        .clamp(1, MAX_NOTIFICATIONS_LIMIT);
+
+    let filter = NotificationFilter {
+        unread_only: query.unread,
+        priority: query.priority,
+        r#type: query.r#type,
+        before: query.before,
+    };
+
+    match NotificationService::list(&db, &username, &filter, limit).await {
+        Ok(notifications) => {
+            let next_before = if notifications.len() == limit as usize {
+                notifications
+                    .last()
+                    .and_then(|last| last.get_str("timestamp").ok())
+                    .map(|ts| ts.to_string())
+            } else {
+                None
+            };
+            let notifications: Vec<serde_json::Value> = notifications
+                .into_iter()
+                .map(document_to_json)
+                .collect();
+
+            HttpResponse::Ok().json(ApiResponse::ok(json!({
+                "username": username,
+                "notifications": notifications,
+                "count": notifications.len(),
+                "next_before": next_before,
+            })))
+        }
+        Err(e) => database_error(e),
+    }
+}
+
+// GET /api/notifications/{username}/unread-count
+pub async fn unread_count(
+    db: web::Data<Database>,
+    username: web::Path<String>,
+) -> HttpResponse {
+    let username = username.into_inner();
+    match NotificationService::unread_count(&db, &username).await {
+        Ok(count) => HttpResponse::Ok().json(ApiResponse::ok(json!({
+            "username": username,
+            "unread_count": count,
+        }))),
+        Err(e) => database_error(e),
+    }
+}
+
+// PUT /api/notifications/{username}/{id}/read
+pub async fn mark_read(
+    db: web::Data<Database>,
+    path: web::Path<(String, String)>,
+) -> HttpResponse {
+    let (username, id) = path.into_inner();
+    match NotificationService::mark_read(&db, &username, &id).await {
+        Ok(true) => HttpResponse::Ok().json(ApiResponse::ok(json!({
+            "id": id,
+            "read": true,
+        }))),
+        Ok(false) => not_found(),
+        Err(e) => database_error(e),
+    }
+}
+
+// PUT /api/notifications/{username}/read-all
+pub async fn mark_all_read(
+    db: web::Data<Database>,
+    username: web::Path<String>,
+) -> HttpResponse {
+    match NotificationService::mark_all_read(&db, &username).await {
+        Ok(modified_count) => HttpResponse::Ok().json(ApiResponse::ok(json!({
+            "modified_count": modified_count,
+        }))),
+        Err(e) => database_error(e),
+    }
+}
+
+// DELETE /api/notifications/{username}/{id}
+pub async fn delete_notification(
+    db: web::Data<Database>,
+    path: web::Path<(String, String)>,
+) -> HttpResponse {
+    let (username, id) = path.into_inner();
+    match NotificationService::delete(&db, &username, &id).await {
+        Ok(true) => HttpResponse::Ok().json(ApiResponse::ok(json!({
+            "id": id,
+            "deleted": true,
+        }))),
+        Ok(false) => not_found(),
+        Err(e) => database_error(e),
+    }
+}
+
+// ============ CAMPAGNES ============
+
+fn campaign_to_json(campaign: Document) -> serde_json::Value {
+    let total = campaign.get_i64("total").ok();
+    // Destinataires traités : notifiés ou ignorés selon leurs préférences
+    let processed = campaign.get_i64("sent").unwrap_or(0) + campaign.get_i64("skipped").unwrap_or(0);
+    let mut campaign = document_to_json(campaign);
+    campaign["progress"] = match total {
+        Some(0) => json!(1.0),
+        Some(total) => json!(processed as f64 / total as f64),
+        None => json!(null),
+    };
+    campaign
+}
+
+// POST /api/notifications/campaigns
+pub async fn create_campaign(
+    caller: Caller,
+    db: web::Data<Database>,
+    pg_client: web::Data<Option<Arc<tokio_postgres::Client>>>,
+    config: web::Data<CampaignConfig>,
+    req: web::Json<NewCampaign>,
+) -> HttpResponse {
+    // Les segments sont lus dans compte_compte
+    let Some(client) = pg_client.as_ref().clone() else {
+        return HttpResponse::ServiceUnavailable()
+            .json(ApiResponse::<()>::err("PostgreSQL non disponible".to_string()));
+    };
+
+    let mut campaign = req.into_inner();
+    if let Some(name) = caller.0 {
+        campaign.created_by = name;
+    }
+    if campaign.created_by.is_empty() {
+        campaign.created_by = "system".to_string();
+    }
+    match crate::handlers::parse_schedule(campaign.send_at.as_deref(), campaign.expires_at.as_deref()) {
+        Ok((send_at, expires_at)) => {
+            campaign.send_at = send_at;
+            campaign.expires_at = expires_at;
+        }
+        Err(response) => return response,
+    }
+
+    match CampaignService::create(&db, &campaign).await {
+        Ok(id) => {
+            tokio::spawn(CampaignService::run(db.get_ref().clone(), client, id, campaign, config.get_ref().clone()));
+            HttpResponse::Accepted().json(ApiResponse::ok(json!({
+                "id": id.to_hex(),
+                "message": "Campagne lancée"
+            })))
+        }
+        Err(e) => {
+            log::error!("Erreur création campagne: {}", e);
+            HttpResponse::InternalServerError()
+                .json(ApiResponse::<()>::err(format!("Erreur: {}", e)))
+        }
+    }
+}
+
+// GET /api/notifications/campaigns
+pub async fn list_campaigns(
+    db: web::Data<Database>,
+    query: web::Query<NotificationsQuery>,
+) -> HttpResponse {
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_NOTIFICATIONS_LIMIT)
+        .clamp(1, MAX_NOTIFICATIONS_LIMIT);
+
+    match CampaignService::list(&db, limit).await {
+        Ok(campaigns) => {
+            let campaigns: Vec<serde_json::Value> = campaigns.into_iter().map(campaign_to_json).collect();
+            HttpResponse::Ok().json(ApiResponse::ok(json!({
+                "campaigns": campaigns,
+                "count": campaigns.len(),
+            })))
+        }
+        Err(e) => database_error(e),
+    }
+}
+
+// GET /api/notifications/campaigns/{id}
+pub async fn get_campaign(
+    db: web::Data<Database>,
+    id: web::Path<String>,
+) -> HttpResponse {
+    match CampaignService::get(&db, &id).await {
+        Ok(Some(campaign)) => HttpResponse::Ok().json(ApiResponse::ok(campaign_to_json(campaign))),
+        Ok(None) => HttpResponse::NotFound()
+            .json(ApiResponse::<()>::err("Campagne introuvable".to_string())),
+        Err(e) => database_error(e),
+    }
+}
+
+// POST /api/notifications/campaigns/{id}/cancel
+pub async fn cancel_campaign(
+    db: web::Data<Database>,
+    id: web::Path<String>,
+) -> HttpResponse {
+    match CampaignService::cancel(&db, &id).await {
+        Ok(Some(removed)) => HttpResponse::Ok().json(ApiResponse::ok(json!({
+            "id": id.into_inner(),
+            "status": "cancelled",
+            "removed_notifications": removed,
+        }))),
+        Ok(None) => HttpResponse::Conflict()
+            .json(ApiResponse::<()>::err("Campagne introuvable ou déjà terminée".to_string())),
+        Err(e) => database_error(e),
+    }
+}
@@ -0,0 +1,78 @@
+use actix_web::{web, HttpResponse};
+use mongodb::Database;
+use serde_json::json;
+use crate::ApiResponse;
+use crate::services::preference_service::{Channel, PreferenceService, UserPreferences};
+
+#[derive(serde::Deserialize)]
+pub struct PreferenceCheckQuery {
+    pub r#type: String,
+    /// in_app, push ou email
+    pub channel: String,
+}
+
+// GET /api/preferences/{username}
+pub async fn get_preferences(
+    db: web::Data<Database>,
+    username: web::Path<String>,
+) -> HttpResponse {
+    match PreferenceService::get(&db, &username).await {
+        Ok(preferences) => HttpResponse::Ok().json(ApiResponse::ok(preferences)),
+        Err(e) => {
+            log::error!("Erreur MongoDB: {}", e);
+            HttpResponse::InternalServerError()
+                .json(ApiResponse::<()>::err(format!("Erreur: {}", e)))
+        }
+    }
+}
+
+// PUT /api/preferences/{username}
+pub async fn update_preferences(
+    db: web::Data<Database>,
+    username: web::Path<String>,
+    req: web::Json<UserPreferences>,
+) -> HttpResponse {
+    let preferences = UserPreferences {
+        username: username.into_inner(),
+        ..req.into_inner()
+    };
+    if let Err(e) = preferences.validate() {
+        return HttpResponse::BadRequest().json(ApiResponse::<()>::err(e));
+    }
+
+    match PreferenceService::update(&db, &preferences).await {
+        Ok(preferences) => HttpResponse::Ok().json(ApiResponse::ok(preferences)),
+        Err(e) => {
+            log::error!("Erreur MongoDB: {}", e);
+            HttpResponse::InternalServerError()
+                .json(ApiResponse::<()>::err(format!("Erreur: {}", e)))
+        }
+    }
+}
+
+// GET /api/preferences/{username}/check?type=...&channel=...
+// Consulté par les canaux de diffusion externes avant d'envoyer une notification
+pub async fn check_preference(
+    db: web::Data<Database>,
+    username: web::Path<String>,
+    query: web::Query<PreferenceCheckQuery>,
+) -> HttpResponse {
+    let Some(channel) = Channel::parse(&query.channel) else {
+        return HttpResponse::BadRequest()
+            .json(ApiResponse::<()>::err("Canal attendu: in_app, push ou email".to_string()));
+    };
+
+    match PreferenceService::get(&db, &username).await {
+        Ok(preferences) => HttpResponse::Ok().json(ApiResponse::ok(json!({
+            "username": username.into_inner(),
+            "type": &query.r#type,
+            "channel": &query.channel,
+            "allowed": preferences.allows(&query.r#type, channel),
+        }))),
+        Err(e) => {
+            log::error!("Erreur MongoDB: {}", e);
+            HttpResponse::InternalServerError()
+                .json(ApiResponse::<()>::err(format!("Erreur: {}", e)))
+        }
+    }
+}
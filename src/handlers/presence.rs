@@ -0,0 +1,180 @@
+use actix_web::{web, HttpResponse};
+use mongodb::Database;
+use serde_json::json;
+use std::sync::Arc;
+use crate::ApiResponse;
+use crate::auth::AuthenticatedUser;
+use crate::authz::ensure_user;
+use crate::services::presence_service::{PresenceRegistry, PresenceService};
+use crate::services::privacy_service::{PrivacyService, Visibility};
+
+#[derive(serde::Deserialize)]
+pub struct ConnectionRequest {
+    pub username: String,
+    /// Identifiant de la connexion (socket, onglet) ; généré à la connexion s'il est absent
+    pub connection_id: Option<String>,
+}
+
+#[derive(serde::Deserialize)]
+pub struct HeartbeatRequest {
+    pub username: String,
+    pub connection_id: String,
+}
+
+fn username_required() -> HttpResponse {
+    HttpResponse::BadRequest().json(ApiResponse::<()>::err("Le nom d'utilisateur est requis".to_string()))
+}
+
+// POST /api/users/connect
+pub async fn connect(
+    user: Option<AuthenticatedUser>,
+    presence: web::Data<Arc<PresenceRegistry>>,
+    pg_client: web::Data<Option<Arc<tokio_postgres::Client>>>,
+    req: web::Json<ConnectionRequest>,
+) -> HttpResponse {
+    if req.username.trim().is_empty() {
+        return username_required();
+    }
+    if let Err(response) = ensure_user(user.as_ref(), &req.username) {
+        return response;
+    }
+    let req = req.into_inner();
+    let connection_id =
+        PresenceService::connect(&presence, pg_client.as_deref(), &req.username, req.connection_id).await;
+
+    HttpResponse::Ok().json(ApiResponse::ok(json!({
+        "username": req.username,
+        "connection_id": connection_id,
+        "status": "connected",
+    })))
+}
+
+// POST /api/users/disconnect
+// Sans `connection_id`, toutes les connexions de l'utilisateur sont fermées
+pub async fn disconnect(
+    user: Option<AuthenticatedUser>,
+    presence: web::Data<Arc<PresenceRegistry>>,
+    pg_client: web::Data<Option<Arc<tokio_postgres::Client>>>,
+    req: web::Json<ConnectionRequest>,
+) -> HttpResponse {
+    if req.username.trim().is_empty() {
+        return username_required();
+    }
+    if let Err(response) = ensure_user(user.as_ref(), &req.username) {
+        return response;
+    }
+    PresenceService::disconnect(&presence, pg_client.as_deref(), &req.username, req.connection_id.as_deref()).await;
+
+    HttpResponse::Ok().json(ApiResponse::ok(json!({
+        "username": &req.username,
+        "status": "disconnected",
+    })))
+}
+
+// POST /api/users/heartbeat
+pub async fn heartbeat(
+    user: Option<AuthenticatedUser>,
+    presence: web::Data<Arc<PresenceRegistry>>,
+    req: web::Json<HeartbeatRequest>,
+) -> HttpResponse {
+    if let Err(response) = ensure_user(user.as_ref(), &req.username) {
+        return response;
+    }
+    if PresenceService::heartbeat(&presence, &req.username, &req.connection_id) {
+        HttpResponse::Ok().json(ApiResponse::ok(json!({
+            "username": &req.username,
+            "connection_id": &req.connection_id,
+        })))
+    } else {
+        // Connexion fermée par le nettoyage : le client doit rappeler /connect
+        HttpResponse::NotFound()
+            .json(ApiResponse::<()>::err("Connexion inconnue ou expirée".to_string()))
+    }
+}
+
+fn mongo_error(e: mongodb::error::Error) -> HttpResponse {
+    log::error!("Erreur MongoDB: {}", e);
+    HttpResponse::InternalServerError().json(ApiResponse::<()>::err(format!("Erreur: {}", e)))
+}
+
+/// Utilisateur authentifié consultant sa propre présence : aucun réglage de
+/// confidentialité ne s'applique. Sans authentification, personne n'est dans ce cas.
+fn is_own_presence(user: Option<&AuthenticatedUser>, username: &str) -> bool {
+    user.is_some_and(|user| user.username == username)
+}
+
+// GET /api/users/online
+// Les utilisateurs qui masquent leur statut n'apparaissent pas ; les réglages de
+// l'utilisateur authentifié s'appliquent aussi (réciprocité)
+pub async fn online_users(
+    user: Option<AuthenticatedUser>,
+    db: web::Data<Database>,
+    presence: web::Data<Arc<PresenceRegistry>>,
+    pg_client: web::Data<Option<Arc<tokio_postgres::Client>>>,
+) -> HttpResponse {
+    let viewer = user.map(|user| user.username);
+    let mut users = PresenceService::online_users(&presence, pg_client.as_deref()).await;
+    let mut privacy_users = users.clone();
+    privacy_users.extend(viewer.clone());
+    let privacy = match PrivacyService::get_many(&db, &privacy_users).await {
+        Ok(privacy) => privacy,
+        Err(e) => return mongo_error(e),
+    };
+    let viewer_privacy = viewer
+        .as_ref()
+        .and_then(|viewer| privacy.get(viewer))
+        .copied()
+        .unwrap_or_default();
+    users.retain(|username| {
+        let user_privacy = privacy.get(username).copied().unwrap_or_default();
+        Visibility::between(&viewer_privacy, &user_privacy).online_status
+    });
+
+    HttpResponse::Ok().json(ApiResponse::ok(json!({
+        "count": users.len(),
+        "users": users,
+    })))
+}
+
+// GET /api/users/status/{username}
+pub async fn user_status(
+    user: Option<AuthenticatedUser>,
+    db: web::Data<Database>,
+    presence: web::Data<Arc<PresenceRegistry>>,
+    pg_client: web::Data<Option<Arc<tokio_postgres::Client>>>,
+    username: web::Path<String>,
+) -> HttpResponse {
+    // L'utilisateur voit toujours sa propre présence
+    let visibility = if is_own_presence(user.as_ref(), &username) {
+        None
+    } else {
+        let viewer = user.as_ref().map(|user| user.username.as_str()).unwrap_or("");
+        match PrivacyService::visibility(&db, viewer, &username).await {
+            Ok(visibility) => Some(visibility),
+            Err(e) => return mongo_error(e),
+        }
+    };
+
+    let status = PresenceService::status(&presence, pg_client.as_deref(), &username).await;
+    let status = match visibility {
+        Some(visibility) => visibility.apply(status),
+        None => status,
+    };
+    HttpResponse::Ok().json(ApiResponse::ok(status))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user(username: &str) -> AuthenticatedUser {
+        AuthenticatedUser { username: username.to_string(), is_admin: false }
+    }
+
+    #[test]
+    fn only_the_authenticated_user_bypasses_privacy() {
+        assert!(is_own_presence(Some(&user("alice")), "alice"));
+        assert!(!is_own_presence(Some(&user("bob")), "alice"));
+        assert!(!is_own_presence(None, "alice"));
+    }
+}
@@ -0,0 +1,44 @@
+use actix_web::{web, HttpResponse};
+use mongodb::Database;
+use serde_json::json;
+use crate::ApiResponse;
+use crate::services::privacy_service::{PrivacyService, PrivacySettings};
+
+// GET /api/privacy/{username}
+pub async fn get_privacy(
+    db: web::Data<Database>,
+    username: web::Path<String>,
+) -> HttpResponse {
+    match PrivacyService::get(&db, &username).await {
+        Ok(settings) => HttpResponse::Ok().json(ApiResponse::ok(settings)),
+        Err(e) => {
+            log::error!("Erreur MongoDB: {}", e);
+            HttpResponse::InternalServerError()
+                .json(ApiResponse::<()>::err(format!("Erreur: {}", e)))
+        }
+    }
+}
+
+// PUT /api/privacy/{username}
+// Réciproque : masquer une information empêche aussi de la voir chez les autres
+pub async fn update_privacy(
+    db: web::Data<Database>,
+    username: web::Path<String>,
+    req: web::Json<PrivacySettings>,
+) -> HttpResponse {
+    let settings = req.into_inner();
+    match PrivacyService::update(&db, &username, &settings).await {
+        Ok(()) => {
+            log::info!("🔒 Confidentialité mise à jour pour {}", username);
+            HttpResponse::Ok().json(ApiResponse::ok(json!({
+                "username": username.into_inner(),
+                "privacy": settings,
+            })))
+        }
+        Err(e) => {
+            log::error!("Erreur MongoDB: {}", e);
+            HttpResponse::InternalServerError()
+                .json(ApiResponse::<()>::err(format!("Erreur: {}", e)))
+        }
+    }
+}
@@ -0,0 +1,112 @@
+use actix_web::{web, HttpResponse};
+use mongodb::Database;
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::Arc;
+use crate::ApiResponse;
+use crate::services::push_service::{DeviceToken, Platform, PushDispatcher, PushMessage, PushService};
+
+#[derive(serde::Deserialize)]
+pub struct RegisterDeviceRequest {
+    pub token: String,
+    /// fcm, apns ou web_push
+    pub platform: Platform,
+}
+
+#[derive(serde::Deserialize)]
+pub struct UnregisterDeviceRequest {
+    pub token: String,
+}
+
+fn database_error(e: mongodb::error::Error) -> HttpResponse {
+    log::error!("Erreur MongoDB: {}", e);
+    HttpResponse::InternalServerError()
+        .json(ApiResponse::<()>::err(format!("Erreur: {}", e)))
+}
+
+// POST /api/push/devices/{username}
+pub async fn register_device(
+    db: web::Data<Database>,
+    username: web::Path<String>,
+    req: web::Json<RegisterDeviceRequest>,
+) -> HttpResponse {
+    let req = req.into_inner();
+    let device = DeviceToken {
+        username: username.into_inner(),
+        token: req.token,
+        platform: req.platform,
+        created_at: None,
+        updated_at: None,
+    };
+    if let Err(e) = device.validate() {
+        return HttpResponse::BadRequest().json(ApiResponse::<()>::err(e));
+    }
+
+    match PushService::register(&db, &device).await {
+        Ok(false) => HttpResponse::Conflict()
+            .json(ApiResponse::<()>::err("Jeton déjà enregistré par un autre utilisateur".to_string())),
+        Ok(true) => HttpResponse::Created().json(ApiResponse::ok(json!({
+            "username": device.username,
+            "platform": device.platform,
+            "message": "Appareil enregistré"
+        }))),
+        Err(e) => database_error(e),
+    }
+}
+
+// GET /api/push/devices/{username}
+pub async fn list_devices(
+    db: web::Data<Database>,
+    username: web::Path<String>,
+) -> HttpResponse {
+    match PushService::list(&db, &username).await {
+        Ok(devices) => HttpResponse::Ok().json(ApiResponse::ok(json!({
+            "username": username.into_inner(),
+            "count": devices.len(),
+            "devices": devices,
+        }))),
+        Err(e) => database_error(e),
+    }
+}
+
+// DELETE /api/push/devices/{username}
+// Le jeton est passé dans le corps : une URL Web Push ne tient pas dans un segment de chemin
+pub async fn unregister_device(
+    db: web::Data<Database>,
+    username: web::Path<String>,
+    req: web::Json<UnregisterDeviceRequest>,
+) -> HttpResponse {
+    match PushService::unregister(&db, &username, &req.token).await {
+        Ok(true) => HttpResponse::Ok().json(ApiResponse::ok(json!({ "deleted": true }))),
+        Ok(false) => HttpResponse::NotFound()
+            .json(ApiResponse::<()>::err("Appareil introuvable".to_string())),
+        Err(e) => database_error(e),
+    }
+}
+
+// POST /api/push/devices/{username}/test
+// Envoie un push de test sur tous les appareils de l'utilisateur
+pub async fn send_test_push(
+    db: web::Data<Database>,
+    dispatcher: web::Data<Arc<PushDispatcher>>,
+    username: web::Path<String>,
+) -> HttpResponse {
+    if !dispatcher.is_enabled() {
+        return HttpResponse::ServiceUnavailable()
+            .json(ApiResponse::<()>::err("Aucun fournisseur de push configuré".to_string()));
+    }
+
+    let message = PushMessage {
+        title: "Test".to_string(),
+        body: "Les notifications push fonctionnent sur cet appareil.".to_string(),
+        data: HashMap::from([("type".to_string(), "push_test".to_string())]),
+    };
+
+    match dispatcher.send_to_user(&db, &username, &message).await {
+        Ok(delivered) => HttpResponse::Ok().json(ApiResponse::ok(json!({
+            "username": username.into_inner(),
+            "delivered": delivered,
+        }))),
+        Err(e) => database_error(e),
+    }
+}
@@ -0,0 +1,421 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use mongodb::{
+    bson::{Bson, Document},
+    Database,
+};
+use serde_json::json;
+use std::sync::Arc;
+use crate::ApiResponse;
+use crate::auth::AuthenticatedUser;
+use crate::authz::{ensure_one_of, ensure_user};
+use crate::config::RequestExpiryConfig;
+use crate::rate_limit::{RateLimitAction, RateLimiter};
+use crate::services::request_kinds::REQUEST_KINDS;
+use crate::services::request_service::{
+    ListCursor, ListFilter, RequestActor, RequestDirection, RequestError, RequestKind,
+    RequestService, RequestStatus,
+};
+
+const DEFAULT_INBOX_LIMIT: i64 = 50;
+const MAX_INBOX_LIMIT: i64 = 200;
+
+/// Enregistre les routes d'un type de demande sous /api/requests/{path} :
+/// création, listes reçues/envoyées, consultation, réponse, annulation et expiration.
+pub fn configure<K: RequestKind>(cfg: &mut web::ServiceConfig) {
+    let base = format!("/api/requests/{}", K::DESCRIPTOR.path);
+
+    cfg.route(&base, web::post().to(create_request::<K>))
+        .route(&format!("{}/incoming/{{username}}", base), web::get().to(list_incoming::<K>))
+        .route(&format!("{}/outgoing/{{username}}", base), web::get().to(list_outgoing::<K>))
+        .route(&format!("{}/{{id}}", base), web::get().to(get_request::<K>))
+        .route(&format!("{}/{{id}}/respond", base), web::post().to(respond_request::<K>))
+        .route(&format!("{}/{{id}}/cancel", base), web::post().to(cancel_request::<K>))
+        .route(&format!("{}/{{id}}/expire", base), web::post().to(expire_request::<K>));
+}
+
+#[derive(serde::Deserialize)]
+pub struct InboxQuery {
+    /// Filtre sur le statut (pending, approved, rejected...)
+    pub status: Option<String>,
+    /// Pagination : `next_before` de la page précédente (un timestamp seul est aussi accepté)
+    pub before: Option<String>,
+    pub limit: Option<i64>,
+}
+
+impl InboxQuery {
+    fn limit(&self) -> i64 {
+        self.limit
+            .unwrap_or(DEFAULT_INBOX_LIMIT)
+            .clamp(1, MAX_INBOX_LIMIT)
+    }
+
+    fn cursor(&self) -> Option<ListCursor> {
+        self.before.as_deref().map(ListCursor::parse)
+    }
+}
+
+#[derive(serde::Deserialize)]
+pub struct RespondRequest<E> {
+    pub responder: String,
+    /// "approved" ou "rejected"
+    pub status: String,
+    pub response_message: Option<String>,
+    #[serde(flatten)]
+    pub extra: E,
+}
+
+#[derive(serde::Deserialize)]
+pub struct CancelRequest {
+    pub requester_username: String,
+}
+
+fn error_response(e: RequestError) -> HttpResponse {
+    let body = ApiResponse::<()>::err(e.to_string());
+    match e {
+        RequestError::NotFound => HttpResponse::NotFound().json(body),
+        RequestError::Invalid(_) => HttpResponse::BadRequest().json(body),
+        RequestError::Forbidden(_) => HttpResponse::Forbidden().json(body),
+        RequestError::Conflict(_) => HttpResponse::Conflict().json(body),
+        RequestError::Database(e) => {
+            log::error!("Erreur MongoDB: {}", e);
+            HttpResponse::InternalServerError().json(body)
+        }
+    }
+}
+
+/// Convertit un document de demande en JSON avec un `id` lisible et son discriminant.
+///
+/// Les demandes en attente reçoivent leur date d'expiration ; celles dont le délai est
+/// dépassé apparaissent déjà `expired` en attendant le passage de la tâche d'expiration.
+fn request_to_json(mut request_doc: Document, kind: &str, ttl: Option<chrono::Duration>) -> serde_json::Value {
+    if let Ok(oid) = request_doc.get_object_id("_id") {
+        request_doc.insert("id", oid.to_hex());
+    }
+    request_doc.remove("_id");
+    request_doc.insert("kind", kind);
+
+    let pending = request_doc.get_str("status") == Ok(RequestStatus::Pending.as_str());
+    let created_at = request_doc
+        .get_str("timestamp")
+        .ok()
+        .and_then(|ts| chrono::DateTime::parse_from_rfc3339(ts).ok());
+    if let (true, Some(ttl), Some(created_at)) = (pending, ttl, created_at) {
+        let expires_at = created_at.with_timezone(&chrono::Utc) + ttl;
+        request_doc.insert("expires_at", expires_at.to_rfc3339());
+        if expires_at <= chrono::Utc::now() {
+            request_doc.insert("status", RequestStatus::Expired.as_str());
+        }
+    }
+
+    Bson::Document(request_doc).into_relaxed_extjson()
+}
+
+// POST /api/requests/{path}
+// Limité par demandeur et par adresse IP, tous types de demandes confondus
+async fn create_request<K: RequestKind>(
+    http_req: HttpRequest,
+    user: Option<AuthenticatedUser>,
+    db: web::Data<Database>,
+    limiter: web::Data<Arc<RateLimiter>>,
+    req: web::Json<K::Payload>,
+) -> HttpResponse {
+    if let Err(response) = ensure_user(user.as_ref(), K::requester(&req)) {
+        return response;
+    }
+    if let Err(response) = limiter.check(RateLimitAction::CreateRequest, &http_req, K::requester(&req)).await {
+        return response;
+    }
+    match RequestService::create::<K>(&db, &req).await {
+        Ok(request_doc) => {
+            let id = request_doc
+                .get_object_id("_id")
+                .map(|oid| oid.to_hex())
+                .unwrap_or_default();
+            HttpResponse::Created().json(ApiResponse::ok(json!({
+                "id": id,
+                "message": K::DESCRIPTOR.created_message
+            })))
+        }
+        Err(e) => error_response(e),
+    }
+}
+
+// GET /api/requests/{path}/{id}
+// Réservé au demandeur et au propriétaire
+async fn get_request<K: RequestKind>(
+    user: Option<AuthenticatedUser>,
+    db: web::Data<Database>,
+    expiry: web::Data<RequestExpiryConfig>,
+    id: web::Path<String>,
+) -> HttpResponse {
+    match RequestService::get::<K>(&db, &id).await {
+        Ok(request_doc) => {
+            let participants = [
+                request_doc.get_str("requester_username").unwrap_or(""),
+                request_doc.get_str(K::DESCRIPTOR.owner_field).unwrap_or(""),
+            ];
+            if let Err(response) = ensure_one_of(user.as_ref(), &participants) {
+                return response;
+            }
+            let ttl = expiry.ttl_for(&K::DESCRIPTOR);
+            HttpResponse::Ok().json(ApiResponse::ok(request_to_json(request_doc, K::DESCRIPTOR.kind, ttl)))
+        }
+        Err(e) => error_response(e),
+    }
+}
+
+// GET /api/requests/{path}/incoming/{username}
+async fn list_incoming<K: RequestKind>(
+    db: web::Data<Database>,
+    expiry: web::Data<RequestExpiryConfig>,
+    username: web::Path<String>,
+    query: web::Query<InboxQuery>,
+) -> HttpResponse {
+    list_requests::<K>(&db, &expiry, &username, RequestDirection::Incoming, &query).await
+}
+
+// GET /api/requests/{path}/outgoing/{username}
+async fn list_outgoing<K: RequestKind>(
+    db: web::Data<Database>,
+    expiry: web::Data<RequestExpiryConfig>,
+    username: web::Path<String>,
+    query: web::Query<InboxQuery>,
+) -> HttpResponse {
+    list_requests::<K>(&db, &expiry, &username, RequestDirection::Outgoing, &query).await
+}
+
+async fn list_requests<K: RequestKind>(
+    db: &Database,
+    expiry: &RequestExpiryConfig,
+    username: &str,
+    direction: RequestDirection,
+    query: &InboxQuery,
+) -> HttpResponse {
+    let descriptor = &K::DESCRIPTOR;
+    let ttl = expiry.ttl_for(descriptor);
+    let cutoff = expiry.cutoff_for(descriptor);
+    let limit = query.limit();
+    let result = RequestService::list(
+        db,
+        descriptor,
+        username,
+        direction,
+        &ListFilter {
+            status: query.status.as_deref(),
+            expiry_cutoff: cutoff.as_deref(),
+            before: query.cursor().as_ref(),
+        },
+        limit,
+    )
+    .await;
+
+    match result {
+        Ok(requests) => {
+            let next_before = (requests.len() == limit as usize)
+                .then(|| requests.last().map(ListCursor::encode))
+                .flatten();
+            let requests: Vec<serde_json::Value> = requests
+                .into_iter()
+                .map(|request_doc| request_to_json(request_doc, descriptor.kind, ttl))
+                .collect();
+            HttpResponse::Ok().json(ApiResponse::ok(json!({
+                "username": username,
+                "direction": direction.as_str(),
+                "kind": descriptor.kind,
+                "count": requests.len(),
+                "requests": requests,
+                "next_before": next_before,
+            })))
+        }
+        Err(e) => error_response(e),
+    }
+}
+
+// POST /api/requests/{path}/{id}/respond
+// Le propriétaire (group_owner, target_username, event_creator) est vérifié par la transition
+async fn respond_request<K: RequestKind>(
+    user: Option<AuthenticatedUser>,
+    db: web::Data<Database>,
+    id: web::Path<String>,
+    req: web::Json<RespondRequest<K::ResponseExtra>>,
+) -> HttpResponse {
+    if let Err(response) = ensure_user(user.as_ref(), &req.responder) {
+        return response;
+    }
+    let status = match RequestStatus::parse(&req.status) {
+        Some(status @ (RequestStatus::Approved | RequestStatus::Rejected)) => status,
+        _ => {
+            return HttpResponse::BadRequest()
+                .json(ApiResponse::<()>::err("Statut attendu: approved ou rejected".to_string()));
+        }
+    };
+
+    let result = RequestService::transition::<K>(
+        &db,
+        &id,
+        RequestActor::Owner,
+        &req.responder,
+        status,
+        req.response_message.as_deref(),
+        K::response_fields(&req.extra, status),
+    )
+    .await;
+
+    match result {
+        Ok(request_doc) => {
+            HttpResponse::Ok().json(ApiResponse::ok(request_to_json(request_doc, K::DESCRIPTOR.kind, None)))
+        }
+        Err(e) => error_response(e),
+    }
+}
+
+// POST /api/requests/{path}/{id}/cancel
+async fn cancel_request<K: RequestKind>(
+    user: Option<AuthenticatedUser>,
+    db: web::Data<Database>,
+    id: web::Path<String>,
+    req: web::Json<CancelRequest>,
+) -> HttpResponse {
+    if let Err(response) = ensure_user(user.as_ref(), &req.requester_username) {
+        return response;
+    }
+    let result = RequestService::transition::<K>(
+        &db,
+        &id,
+        RequestActor::Requester,
+        &req.requester_username,
+        RequestStatus::Cancelled,
+        None,
+        Document::new(),
+    )
+    .await;
+
+    match result {
+        Ok(request_doc) => {
+            HttpResponse::Ok().json(ApiResponse::ok(request_to_json(request_doc, K::DESCRIPTOR.kind, None)))
+        }
+        Err(e) => error_response(e),
+    }
+}
+
+// POST /api/requests/{path}/{id}/expire
+async fn expire_request<K: RequestKind>(
+    db: web::Data<Database>,
+    id: web::Path<String>,
+) -> HttpResponse {
+    let result = RequestService::transition::<K>(
+        &db,
+        &id,
+        RequestActor::System,
+        "",
+        RequestStatus::Expired,
+        None,
+        Document::new(),
+    )
+    .await;
+
+    match result {
+        Ok(request_doc) => {
+            HttpResponse::Ok().json(ApiResponse::ok(request_to_json(request_doc, K::DESCRIPTOR.kind, None)))
+        }
+        Err(e) => error_response(e),
+    }
+}
+
+// GET /api/requests/incoming/{username}
+pub async fn get_incoming_requests(
+    db: web::Data<Database>,
+    expiry: web::Data<RequestExpiryConfig>,
+    username: web::Path<String>,
+    query: web::Query<InboxQuery>,
+) -> HttpResponse {
+    get_inbox(&db, &expiry, &username, RequestDirection::Incoming, &query).await
+}
+
+// GET /api/requests/outgoing/{username}
+pub async fn get_outgoing_requests(
+    db: web::Data<Database>,
+    expiry: web::Data<RequestExpiryConfig>,
+    username: web::Path<String>,
+    query: web::Query<InboxQuery>,
+) -> HttpResponse {
+    get_inbox(&db, &expiry, &username, RequestDirection::Outgoing, &query).await
+}
+
+/// Boîte de réception unifiée : fusionne tous les types de demandes
+async fn get_inbox(
+    db: &Database,
+    expiry: &RequestExpiryConfig,
+    username: &str,
+    direction: RequestDirection,
+    query: &InboxQuery,
+) -> HttpResponse {
+    let limit = query.limit();
+    let cursor = query.cursor();
+
+    let mut requests: Vec<((String, String), serde_json::Value)> = Vec::new();
+    let mut pending_counts = serde_json::Map::new();
+    let mut pending_total = 0;
+
+    for descriptor in &REQUEST_KINDS {
+        let ttl = expiry.ttl_for(descriptor);
+        let cutoff = expiry.cutoff_for(descriptor);
+        // Chaque collection est triée puis limitée : la fusion ne garde ensuite que les plus récentes
+        let kind_requests = match RequestService::list(
+            db,
+            descriptor,
+            username,
+            direction,
+            &ListFilter {
+                status: query.status.as_deref(),
+                expiry_cutoff: cutoff.as_deref(),
+                before: cursor.as_ref(),
+            },
+            limit,
+        )
+        .await
+        {
+            Ok(kind_requests) => kind_requests,
+            Err(e) => return error_response(e),
+        };
+
+        for request_doc in kind_requests {
+            // Même ordre que le tri MongoDB : timestamp puis identifiant
+            let position = (
+                request_doc.get_str("timestamp").unwrap_or("").to_string(),
+                ListCursor::encode(&request_doc),
+            );
+            requests.push((position, request_to_json(request_doc, descriptor.kind, ttl)));
+        }
+
+        match RequestService::count_pending(db, descriptor, username, direction, cutoff.as_deref()).await {
+            Ok(count) => {
+                pending_total += count;
+                pending_counts.insert(descriptor.kind.to_string(), json!(count));
+            }
+            Err(e) => return error_response(e),
+        }
+    }
+    pending_counts.insert("total".to_string(), json!(pending_total));
+
+    // Fusion de toutes les collections : plus récent en premier
+    requests.sort_by(|a, b| b.0.cmp(&a.0));
+    requests.truncate(limit as usize);
+
+    let next_before = if requests.len() == limit as usize {
+        requests.last().map(|((_, cursor), _)| cursor.clone())
+    } else {
+        None
+    };
+
+    let requests: Vec<serde_json::Value> = requests.into_iter().map(|(_, request)| request).collect();
+
+    HttpResponse::Ok().json(ApiResponse::ok(json!({
+        "username": username,
+        "direction": direction.as_str(),
+        "requests": requests,
+        "count": requests.len(),
+        "pending_counts": pending_counts,
+        "next_before": next_before,
+    })))
+}
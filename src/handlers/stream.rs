@@ -0,0 +1,90 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+use crate::services::stream_service::StreamHub;
+
+/// Intervalle des commentaires de maintien de connexion (proxys, répartiteurs de charge)
+const STREAM_KEEPALIVE_SECS: u64 = 15;
+
+/// Délai de reconnexion suggéré au navigateur
+const STREAM_RETRY_MS: u64 = 3000;
+
+#[derive(serde::Deserialize)]
+pub struct StreamQuery {
+    /// Équivalent de l'en-tête `Last-Event-ID`, pour les clients qui ne peuvent pas le fixer
+    pub last_event_id: Option<u64>,
+}
+
+// GET /api/stream/{username}
+// Repli SSE du WebSocket : événements `message`, `read`, `notification` et `typing`.
+// À la reconnexion, les événements postérieurs à `Last-Event-ID` sont renvoyés ; s'ils ne sont
+// plus tous disponibles, un événement `resync` invite le client à recharger par l'API.
+pub async fn stream_events(
+    req: HttpRequest,
+    hub: web::Data<Arc<StreamHub>>,
+    username: web::Path<String>,
+    query: web::Query<StreamQuery>,
+) -> HttpResponse {
+    let username = username.into_inner();
+    let last_event_id = req
+        .headers()
+        .get("Last-Event-ID")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .or(query.last_event_id);
+
+    let subscription = hub.subscribe(&username, last_event_id);
+    log::info!(
+        "📡 Flux SSE ouvert pour {} ({} événements rejoués)",
+        username,
+        subscription.replay.len()
+    );
+
+    let mut pending = VecDeque::from([format!("retry: {}\n\n", STREAM_RETRY_MS)]);
+    if subscription.gap {
+        pending.push_back("event: resync\ndata: {}\n\n".to_string());
+    }
+    pending.extend(subscription.replay.iter().map(|event| event.to_sse()));
+
+    let mut keepalive = tokio::time::interval(Duration::from_secs(STREAM_KEEPALIVE_SECS));
+    keepalive.reset();
+    let state = (pending, subscription.receiver, subscription.last_id, keepalive, username);
+
+    let body = futures_util::stream::unfold(state, |(mut pending, mut receiver, mut last_id, mut keepalive, username)| async move {
+        loop {
+            if let Some(frame) = pending.pop_front() {
+                let chunk = Ok::<_, actix_web::Error>(web::Bytes::from(frame));
+                return Some((chunk, (pending, receiver, last_id, keepalive, username)));
+            }
+
+            tokio::select! {
+                event = receiver.recv() => match event {
+                    Ok(event) if event.username == username => {
+                        // Déjà envoyé lors de la reprise
+                        if let Some(id) = event.id {
+                            if id <= last_id {
+                                continue;
+                            }
+                            last_id = id;
+                        }
+                        pending.push_back(event.to_sse());
+                    }
+                    Ok(_) => {}
+                    Err(RecvError::Lagged(_)) => {
+                        pending.push_back("event: resync\ndata: {}\n\n".to_string());
+                    }
+                    Err(RecvError::Closed) => return None,
+                },
+                _ = keepalive.tick() => pending.push_back(": ping\n\n".to_string()),
+            }
+        }
+    });
+
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(("Cache-Control", "no-cache"))
+        .insert_header(("X-Accel-Buffering", "no"))
+        .streaming(body)
+}
@@ -0,0 +1,99 @@
+use actix_web::{web, HttpResponse};
+use mongodb::Database;
+use serde_json::json;
+use crate::ApiResponse;
+use crate::services::template_service::{NotificationTemplate, TemplateService};
+
+fn database_error(e: mongodb::error::Error) -> HttpResponse {
+    log::error!("Erreur MongoDB: {}", e);
+    HttpResponse::InternalServerError()
+        .json(ApiResponse::<()>::err(format!("Erreur: {}", e)))
+}
+
+fn not_found() -> HttpResponse {
+    HttpResponse::NotFound()
+        .json(ApiResponse::<()>::err("Modèle introuvable".to_string()))
+}
+
+// POST /api/notifications/templates
+pub async fn create_template(
+    db: web::Data<Database>,
+    req: web::Json<NotificationTemplate>,
+) -> HttpResponse {
+    if let Err(e) = req.validate() {
+        return HttpResponse::BadRequest().json(ApiResponse::<()>::err(e));
+    }
+
+    match TemplateService::create(&db, &req).await {
+        Ok(true) => HttpResponse::Created().json(ApiResponse::ok(json!({
+            "key": &req.key,
+            "message": "Modèle créé"
+        }))),
+        Ok(false) => HttpResponse::Conflict()
+            .json(ApiResponse::<()>::err(format!("Le modèle {} existe déjà", req.key))),
+        Err(e) => database_error(e),
+    }
+}
+
+// GET /api/notifications/templates
+pub async fn list_templates(db: web::Data<Database>) -> HttpResponse {
+    match TemplateService::list(&db).await {
+        Ok(templates) => HttpResponse::Ok().json(ApiResponse::ok(json!({
+            "count": templates.len(),
+            "templates": templates,
+        }))),
+        Err(e) => database_error(e),
+    }
+}
+
+// GET /api/notifications/templates/{key}
+pub async fn get_template(
+    db: web::Data<Database>,
+    key: web::Path<String>,
+) -> HttpResponse {
+    match TemplateService::get(&db, &key).await {
+        Ok(Some(template)) => HttpResponse::Ok().json(ApiResponse::ok(template)),
+        Ok(None) => not_found(),
+        Err(e) => database_error(e),
+    }
+}
+
+// PUT /api/notifications/templates/{key}
+pub async fn update_template(
+    db: web::Data<Database>,
+    key: web::Path<String>,
+    req: web::Json<NotificationTemplate>,
+) -> HttpResponse {
+    // La clé de l'URL fait foi
+    let template = NotificationTemplate {
+        key: key.into_inner(),
+        ..req.into_inner()
+    };
+    if let Err(e) = template.validate() {
+        return HttpResponse::BadRequest().json(ApiResponse::<()>::err(e));
+    }
+
+    match TemplateService::update(&db, &template).await {
+        Ok(true) => HttpResponse::Ok().json(ApiResponse::ok(json!({
+            "key": template.key,
+            "message": "Modèle mis à jour"
+        }))),
+        Ok(false) => not_found(),
+        Err(e) => database_error(e),
+    }
+}
+
+// DELETE /api/notifications/templates/{key}
+pub async fn delete_template(
+    db: web::Data<Database>,
+    key: web::Path<String>,
+) -> HttpResponse {
+    match TemplateService::delete(&db, &key).await {
+        Ok(true) => HttpResponse::Ok().json(ApiResponse::ok(json!({
+            "key": key.into_inner(),
+            "deleted": true,
+        }))),
+        Ok(false) => not_found(),
+        Err(e) => database_error(e),
+    }
+}
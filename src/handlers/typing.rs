@@ -0,0 +1,119 @@
+use actix_web::{web, HttpResponse};
+use mongodb::{bson::doc, options::CountOptions, Database};
+use serde_json::json;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+use crate::ApiResponse;
+use crate::auth::AuthenticatedUser;
+use crate::authz::ensure_user;
+use crate::events::{AppEvent, EventBus};
+use crate::services::typing_service::TypingRegistry;
+
+#[derive(serde::Deserialize)]
+pub struct TypingRequest {
+    pub from: String,
+    pub to: String,
+    /// `true` au début de la frappe, `false` à l'arrêt (ou à l'envoi du message)
+    pub typing: bool,
+}
+
+#[derive(serde::Deserialize)]
+pub struct TypingQuery {
+    /// Attente (longue interrogation) du prochain changement, en secondes
+    pub wait_secs: Option<u64>,
+}
+
+/// Durée maximale d'une longue interrogation
+const MAX_TYPING_WAIT_SECS: u64 = 30;
+
+/// Au moins un message échangé entre les deux utilisateurs
+async fn share_conversation(db: &Database, from: &str, to: &str) -> Result<bool, mongodb::error::Error> {
+    let filter = doc! {
+        "$or": [
+            { "from": from, "to": to },
+            { "from": to, "to": from },
+        ]
+    };
+    let count = db
+        .collection::<mongodb::bson::Document>("messages")
+        .count_documents(filter, CountOptions::builder().limit(1).build())
+        .await?;
+    Ok(count > 0)
+}
+
+// POST /api/typing
+// Diffusé uniquement entre utilisateurs ayant déjà une conversation
+pub async fn set_typing(
+    user: Option<AuthenticatedUser>,
+    db: web::Data<Database>,
+    typing: web::Data<Arc<TypingRegistry>>,
+    bus: web::Data<EventBus>,
+    req: web::Json<TypingRequest>,
+) -> HttpResponse {
+    if req.from.trim().is_empty() || req.to.trim().is_empty() || req.from == req.to {
+        return HttpResponse::BadRequest()
+            .json(ApiResponse::<()>::err("Expéditeur et destinataire distincts requis".to_string()));
+    }
+    if let Err(response) = ensure_user(user.as_ref(), &req.from) {
+        return response;
+    }
+    match share_conversation(&db, &req.from, &req.to).await {
+        Ok(true) => {}
+        Ok(false) => {
+            return HttpResponse::Forbidden()
+                .json(ApiResponse::<()>::err("Aucune conversation avec ce destinataire".to_string()));
+        }
+        Err(e) => {
+            log::error!("Erreur MongoDB: {}", e);
+            return HttpResponse::InternalServerError()
+                .json(ApiResponse::<()>::err(format!("Erreur: {}", e)));
+        }
+    }
+
+    let broadcast = typing.set_typing(&bus, &req.from, &req.to, req.typing);
+    HttpResponse::Ok().json(ApiResponse::ok(json!({
+        "from": &req.from,
+        "to": &req.to,
+        "typing": req.typing,
+        "broadcast": broadcast,
+        "expires_in_secs": if req.typing { typing.config().ttl_secs } else { 0 },
+    })))
+}
+
+// GET /api/typing/{username}?wait_secs=25
+// Pour les clients sans canal temps réel ; avec `wait_secs`, la réponse est renvoyée dès
+// qu'un indicateur destiné à l'utilisateur change
+pub async fn get_typing(
+    typing: web::Data<Arc<TypingRegistry>>,
+    bus: web::Data<EventBus>,
+    username: web::Path<String>,
+    query: web::Query<TypingQuery>,
+) -> HttpResponse {
+    let username = username.into_inner();
+    let mut change = None;
+
+    if let Some(wait_secs) = query.wait_secs.filter(|secs| *secs > 0) {
+        let mut events = bus.subscribe();
+        let wait = Duration::from_secs(wait_secs.min(MAX_TYPING_WAIT_SECS));
+        let next_change = async {
+            loop {
+                match events.recv().await {
+                    Ok(AppEvent::Typing { from, to, typing }) if to == username => {
+                        return Some(json!({ "from": from, "typing": typing }));
+                    }
+                    Ok(_) | Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => return None,
+                }
+            }
+        };
+        change = tokio::time::timeout(wait, next_change).await.ok().flatten();
+    }
+
+    let users = typing.typing_to(&username);
+    HttpResponse::Ok().json(ApiResponse::ok(json!({
+        "username": username,
+        "typing": users,
+        "change": change,
+    })))
+}
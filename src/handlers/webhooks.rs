@@ -0,0 +1,152 @@
+use actix_web::{web, HttpResponse};
+use mongodb::{
+    bson::{Bson, Document},
+    Database,
+};
+use serde_json::json;
+use crate::ApiResponse;
+use crate::services::webhook_service::{NewWebhookSubscription, WebhookService};
+
+#[derive(serde::Deserialize)]
+pub struct DeadLettersQuery {
+    pub subscription_id: Option<String>,
+    pub limit: Option<i64>,
+}
+
+/// Abonnement sans son secret, qui n'est renvoyé qu'à la création
+fn subscription_to_json(mut subscription: Document) -> serde_json::Value {
+    subscription.remove("secret");
+    document_to_json(subscription)
+}
+
+fn document_to_json(mut document: Document) -> serde_json::Value {
+    if let Ok(oid) = document.get_object_id("_id") {
+        document.insert("id", oid.to_hex());
+    }
+    document.remove("_id");
+    if let Ok(oid) = document.get_object_id("subscription_id") {
+        document.insert("subscription_id", oid.to_hex());
+    }
+    Bson::Document(document).into_relaxed_extjson()
+}
+
+fn database_error(e: mongodb::error::Error) -> HttpResponse {
+    log::error!("Erreur MongoDB: {}", e);
+    HttpResponse::InternalServerError()
+        .json(ApiResponse::<()>::err(format!("Erreur: {}", e)))
+}
+
+fn not_found(message: &str) -> HttpResponse {
+    HttpResponse::NotFound().json(ApiResponse::<()>::err(message.to_string()))
+}
+
+// POST /api/webhooks
+pub async fn create_webhook(
+    db: web::Data<Database>,
+    req: web::Json<NewWebhookSubscription>,
+) -> HttpResponse {
+    if let Err(e) = req.validate() {
+        return HttpResponse::BadRequest().json(ApiResponse::<()>::err(e));
+    }
+
+    match WebhookService::create(&db, &req).await {
+        Ok((id, secret)) => HttpResponse::Created().json(ApiResponse::ok(json!({
+            "id": id.to_hex(),
+            "url": &req.url,
+            "events": &req.events,
+            // Seule occasion de lire le secret : il sert à vérifier X-Webhook-Signature
+            "secret": secret,
+        }))),
+        Err(e) => database_error(e),
+    }
+}
+
+// GET /api/webhooks
+pub async fn list_webhooks(db: web::Data<Database>) -> HttpResponse {
+    match WebhookService::list(&db).await {
+        Ok(subscriptions) => {
+            let subscriptions: Vec<serde_json::Value> =
+                subscriptions.into_iter().map(subscription_to_json).collect();
+            HttpResponse::Ok().json(ApiResponse::ok(json!({
+                "count": subscriptions.len(),
+                "webhooks": subscriptions,
+            })))
+        }
+        Err(e) => database_error(e),
+    }
+}
+
+// GET /api/webhooks/{id}
+pub async fn get_webhook(
+    db: web::Data<Database>,
+    id: web::Path<String>,
+) -> HttpResponse {
+    match WebhookService::get(&db, &id).await {
+        Ok(Some(subscription)) => HttpResponse::Ok().json(ApiResponse::ok(subscription_to_json(subscription))),
+        Ok(None) => not_found("Webhook introuvable"),
+        Err(e) => database_error(e),
+    }
+}
+
+// DELETE /api/webhooks/{id}
+pub async fn delete_webhook(
+    db: web::Data<Database>,
+    id: web::Path<String>,
+) -> HttpResponse {
+    match WebhookService::delete(&db, &id).await {
+        Ok(true) => HttpResponse::Ok().json(ApiResponse::ok(json!({
+            "id": id.into_inner(),
+            "deleted": true,
+        }))),
+        Ok(false) => not_found("Webhook introuvable"),
+        Err(e) => database_error(e),
+    }
+}
+
+// GET /api/webhooks/dead-letters?subscription_id=...&limit=50
+pub async fn list_dead_letters(
+    db: web::Data<Database>,
+    query: web::Query<DeadLettersQuery>,
+) -> HttpResponse {
+    let limit = query.limit.unwrap_or(50).clamp(1, 500);
+
+    match WebhookService::dead_letters(&db, query.subscription_id.as_deref(), limit).await {
+        Ok(deliveries) => {
+            let deliveries: Vec<serde_json::Value> = deliveries.into_iter().map(document_to_json).collect();
+            HttpResponse::Ok().json(ApiResponse::ok(json!({
+                "count": deliveries.len(),
+                "deliveries": deliveries,
+            })))
+        }
+        Err(e) => database_error(e),
+    }
+}
+
+// POST /api/webhooks/deliveries/{id}/replay
+pub async fn replay_delivery(
+    db: web::Data<Database>,
+    id: web::Path<String>,
+) -> HttpResponse {
+    match WebhookService::replay(&db, &id).await {
+        Ok(true) => HttpResponse::Accepted().json(ApiResponse::ok(json!({
+            "id": id.into_inner(),
+            "status": "pending",
+        }))),
+        Ok(false) => not_found("Livraison introuvable ou déjà en attente"),
+        Err(e) => database_error(e),
+    }
+}
+
+// POST /api/webhooks/{id}/dead-letters/replay
+pub async fn replay_dead_letters(
+    db: web::Data<Database>,
+    id: web::Path<String>,
+) -> HttpResponse {
+    match WebhookService::replay_dead_letters(&db, &id).await {
+        Ok(count) => HttpResponse::Accepted().json(ApiResponse::ok(json!({
+            "id": id.into_inner(),
+            "replayed": count,
+        }))),
+        Err(e) => database_error(e),
+    }
+}
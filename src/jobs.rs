@@ -0,0 +1,222 @@
+use mongodb::Database;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+use crate::config::{DigestConfig, PresenceConfig, RequestExpiryConfig, WebhookConfig};
+use crate::events::{event_data, AppEvent, CollectionTail, EventBus};
+use crate::services::digest_service::DigestService;
+use crate::services::mail_service::Mailer;
+use crate::services::notification_service::NotificationService;
+use crate::services::presence_service::{PresenceRegistry, PresenceService};
+use crate::services::push_service::PushDispatcher;
+use crate::services::request_kinds::{EventParticipation, GroupAccess, PhotoPermission};
+use crate::services::request_service::{RequestKind, RequestService};
+use crate::services::stream_service::StreamHub;
+use crate::services::typing_service::TypingRegistry;
+use crate::services::webhook_service::WebhookService;
+
+/// Expire périodiquement les demandes en attente (groupe, photos, événement)
+pub fn spawn_request_expiry(db: Database, config: RequestExpiryConfig) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(config.interval());
+        loop {
+            interval.tick().await;
+            expire_requests::<GroupAccess>(&db, &config).await;
+            expire_requests::<PhotoPermission>(&db, &config).await;
+            expire_requests::<EventParticipation>(&db, &config).await;
+        }
+    });
+}
+
+async fn expire_requests<K: RequestKind>(db: &Database, config: &RequestExpiryConfig) {
+    if let Some(ttl) = config.ttl_for(&K::DESCRIPTOR) {
+        if let Err(e) = RequestService::expire_stale::<K>(db, ttl).await {
+            log::error!("Erreur expiration des demandes {}: {}", K::DESCRIPTOR.kind, e);
+        }
+    }
+}
+
+/// Publie les notifications programmées arrivées à échéance
+pub fn spawn_notification_publisher(db: Database, bus: EventBus, every: Duration) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(every);
+        loop {
+            interval.tick().await;
+            match NotificationService::publish_due(&db).await {
+                Ok(published) => {
+                    for notification in published {
+                        bus.publish(AppEvent::NotificationPublished(notification));
+                    }
+                }
+                Err(e) => log::error!("Erreur publication des notifications programmées: {}", e),
+            }
+        }
+    });
+}
+
+/// Détecte les nouveaux messages et notifications et les diffuse sur le bus d'événements
+pub fn spawn_event_watcher(db: Database, bus: EventBus, every: Duration) {
+    tokio::spawn(async move {
+        let mut messages = CollectionTail::new("messages");
+        let mut notifications = CollectionTail::new("system_notifications");
+        let mut interval = tokio::time::interval(every);
+        loop {
+            interval.tick().await;
+            match messages.poll(&db).await {
+                Ok(inserted) => {
+                    for message in inserted {
+                        bus.publish(AppEvent::MessageCreated(message));
+                    }
+                }
+                Err(e) => log::error!("Erreur MongoDB: {}", e),
+            }
+            match notifications.poll(&db).await {
+                Ok(inserted) => {
+                    // Les notifications programmées sont diffusées par la tâche de publication
+                    for notification in inserted {
+                        if notification.get_bool("published").unwrap_or(true) {
+                            bus.publish(AppEvent::NotificationPublished(notification));
+                        }
+                    }
+                }
+                Err(e) => log::error!("Erreur MongoDB: {}", e),
+            }
+        }
+    });
+}
+
+/// Envoie les push déclenchés par les événements du bus
+pub fn spawn_push_dispatcher(
+    db: Database,
+    pg_client: Option<Arc<tokio_postgres::Client>>,
+    presence: Arc<PresenceRegistry>,
+    bus: &EventBus,
+    dispatcher: Arc<PushDispatcher>,
+) {
+    let mut events = bus.subscribe();
+    tokio::spawn(async move {
+        loop {
+            let event = match events.recv().await {
+                Ok(event) => event,
+                Err(RecvError::Lagged(missed)) => {
+                    log::warn!("⚠️  {} événements ignorés par le dispatcher de push", missed);
+                    continue;
+                }
+                Err(RecvError::Closed) => break,
+            };
+            let (db, pg_client, presence, dispatcher) =
+                (db.clone(), pg_client.clone(), presence.clone(), dispatcher.clone());
+            // Un fournisseur lent ne doit pas retarder les push suivants
+            tokio::spawn(async move {
+                if let Err(e) = dispatcher.handle_event(&db, &presence, pg_client.as_deref(), &event).await {
+                    log::error!("Erreur MongoDB: {}", e);
+                }
+            });
+        }
+    });
+}
+
+/// Envoie périodiquement le récapitulatif des messages non lus par email
+pub fn spawn_email_digest(
+    db: Database,
+    pg_client: Arc<tokio_postgres::Client>,
+    mailer: Mailer,
+    config: DigestConfig,
+) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(config.interval());
+        loop {
+            interval.tick().await;
+            if let Err(e) = DigestService::send_digests(&db, &pg_client, &mailer, &config).await {
+                log::error!("Erreur récapitulatif des messages non lus: {}", e);
+            }
+        }
+    });
+}
+
+/// Transforme les événements du bus en livraisons de webhooks et envoie les livraisons dues
+pub fn spawn_webhooks(db: Database, bus: &EventBus, config: WebhookConfig) {
+    let mut events = bus.subscribe();
+    let enqueue_db = db.clone();
+    tokio::spawn(async move {
+        loop {
+            let event = match events.recv().await {
+                Ok(event) => event,
+                Err(RecvError::Lagged(missed)) => {
+                    log::warn!("⚠️  {} événements ignorés pour les webhooks", missed);
+                    continue;
+                }
+                Err(RecvError::Closed) => break,
+            };
+            // Les décisions sur les demandes et les lectures sont mises en file par leurs services
+            if let AppEvent::MessageCreated(message) = event {
+                if let Err(e) = WebhookService::enqueue(&enqueue_db, "message.created", event_data(message)).await {
+                    log::error!("Erreur MongoDB: {}", e);
+                }
+            }
+        }
+    });
+
+    tokio::spawn(async move {
+        let client = match reqwest::Client::builder().timeout(Duration::from_secs(10)).build() {
+            Ok(client) => client,
+            Err(e) => {
+                log::error!("Client HTTP des webhooks indisponible: {}", e);
+                return;
+            }
+        };
+        let mut interval = tokio::time::interval(config.interval());
+        loop {
+            interval.tick().await;
+            if let Err(e) = WebhookService::deliver_due(&db, &client, &config).await {
+                log::error!("Erreur envoi des webhooks: {}", e);
+            }
+        }
+    });
+}
+
+/// Ferme les connexions sans signe de vie et resynchronise `compte_compte`
+pub fn spawn_presence_sweeper(
+    presence: Arc<PresenceRegistry>,
+    pg_client: Option<Arc<tokio_postgres::Client>>,
+    config: PresenceConfig,
+) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(config.sweep_interval());
+        loop {
+            interval.tick().await;
+            PresenceService::sweep(&presence, pg_client.as_deref(), &config).await;
+        }
+    });
+}
+
+/// Fait expirer les indicateurs de frappe non renouvelés
+pub fn spawn_typing_expiry(typing: Arc<TypingRegistry>, bus: EventBus) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(1));
+        loop {
+            interval.tick().await;
+            typing.expire(&bus);
+        }
+    });
+}
+
+/// Alimente les flux SSE depuis le bus et purge régulièrement leur historique
+pub fn spawn_stream_hub(hub: Arc<StreamHub>, bus: &EventBus) {
+    let mut events = bus.subscribe();
+    tokio::spawn(async move {
+        let mut prune = tokio::time::interval(Duration::from_secs(60));
+        loop {
+            tokio::select! {
+                event = events.recv() => match event {
+                    Ok(event) => hub.dispatch(&event),
+                    Err(RecvError::Lagged(missed)) => {
+                        log::warn!("⚠️  {} événements ignorés par les flux SSE", missed);
+                    }
+                    Err(RecvError::Closed) => break,
+                },
+                _ = prune.tick() => hub.prune(),
+            }
+        }
+    });
+}
@@ -0,0 +1,343 @@
+use actix_web::{HttpRequest, HttpResponse};
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use crate::ApiResponse;
+use crate::config::RateLimitConfig;
+
+/// Nombre de compartiments en mémoire au-delà duquel les compartiments pleins sont oubliés
+const MEMORY_PRUNE_THRESHOLD: usize = 50_000;
+
+/// Seau à jetons : `capacity` actions d'affilée, puis `refill_per_sec` par seconde
+#[derive(Debug, Clone, Copy)]
+pub struct Limit {
+    pub capacity: f64,
+    pub refill_per_sec: f64,
+}
+
+impl Limit {
+    fn scaled(&self, factor: f64) -> Limit {
+        Limit {
+            capacity: self.capacity * factor,
+            refill_per_sec: self.refill_per_sec * factor,
+        }
+    }
+}
+
+/// Action soumise à limitation
+#[derive(Debug, Clone, Copy)]
+pub enum RateLimitAction {
+    SendMessage,
+    CreateRequest,
+}
+
+impl RateLimitAction {
+    fn as_str(&self) -> &'static str {
+        match self {
+            RateLimitAction::SendMessage => "message",
+            RateLimitAction::CreateRequest => "request",
+        }
+    }
+}
+
+/// Stockage des seaux à jetons
+#[async_trait]
+trait BucketStore: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Consomme un jeton ; renvoie le délai d'attente si le seau est vide
+    async fn take(&self, key: &str, limit: &Limit) -> Result<Option<Duration>, String>;
+}
+
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+impl Bucket {
+    fn refill(&mut self, limit: &Limit, now: Instant) {
+        let elapsed = now.duration_since(self.updated_at).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * limit.refill_per_sec).min(limit.capacity);
+        self.updated_at = now;
+    }
+}
+
+/// Seaux propres à cette instance
+#[derive(Default)]
+struct MemoryStore {
+    buckets: Mutex<HashMap<String, (Bucket, Limit)>>,
+}
+
+#[async_trait]
+impl BucketStore for MemoryStore {
+    fn name(&self) -> &'static str {
+        "mémoire"
+    }
+
+    async fn take(&self, key: &str, limit: &Limit) -> Result<Option<Duration>, String> {
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+
+        if buckets.len() > MEMORY_PRUNE_THRESHOLD {
+            buckets.retain(|_, (bucket, limit)| {
+                bucket.refill(limit, now);
+                bucket.tokens < limit.capacity
+            });
+        }
+
+        let (bucket, _) = buckets.entry(key.to_string()).or_insert_with(|| {
+            (
+                Bucket {
+                    tokens: limit.capacity,
+                    updated_at: now,
+                },
+                *limit,
+            )
+        });
+        bucket.refill(limit, now);
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(None)
+        } else {
+            let wait = (1.0 - bucket.tokens) / limit.refill_per_sec;
+            Ok(Some(Duration::from_secs_f64(wait)))
+        }
+    }
+}
+
+/// Seaux partagés entre instances via Redis (script atomique)
+#[cfg(feature = "redis")]
+struct RedisStore {
+    connection: redis::aio::ConnectionManager,
+    script: redis::Script,
+}
+
+#[cfg(feature = "redis")]
+const TOKEN_BUCKET_SCRIPT: &str = r#"
+local capacity = tonumber(ARGV[1])
+local refill_per_ms = tonumber(ARGV[2])
+local now = tonumber(ARGV[3])
+local bucket = redis.call('HMGET', KEYS[1], 'tokens', 'ts')
+local tokens = tonumber(bucket[1]) or capacity
+local ts = tonumber(bucket[2]) or now
+tokens = math.min(capacity, tokens + math.max(0, now - ts) * refill_per_ms)
+local wait = 0
+if tokens >= 1 then
+  tokens = tokens - 1
+else
+  wait = math.ceil((1 - tokens) / refill_per_ms)
+end
+redis.call('HSET', KEYS[1], 'tokens', tostring(tokens), 'ts', now)
+redis.call('PEXPIRE', KEYS[1], math.ceil(capacity / refill_per_ms) + 1000)
+return wait
+"#;
+
+#[cfg(feature = "redis")]
+impl RedisStore {
+    async fn connect(url: &str) -> Result<Self, String> {
+        let client = redis::Client::open(url).map_err(|e| e.to_string())?;
+        let connection = redis::aio::ConnectionManager::new(client).await.map_err(|e| e.to_string())?;
+        Ok(RedisStore {
+            connection,
+            script: redis::Script::new(TOKEN_BUCKET_SCRIPT),
+        })
+    }
+}
+
+#[cfg(feature = "redis")]
+#[async_trait]
+impl BucketStore for RedisStore {
+    fn name(&self) -> &'static str {
+        "redis"
+    }
+
+    async fn take(&self, key: &str, limit: &Limit) -> Result<Option<Duration>, String> {
+        let mut connection = self.connection.clone();
+        let wait_ms: u64 = self
+            .script
+            .key(key)
+            .arg(limit.capacity)
+            .arg(limit.refill_per_sec / 1000.0)
+            .arg(chrono::Utc::now().timestamp_millis())
+            .invoke_async(&mut connection)
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok((wait_ms > 0).then(|| Duration::from_millis(wait_ms)))
+    }
+}
+
+/// Limitation par utilisateur et par adresse IP des actions coûteuses pour les destinataires
+pub struct RateLimiter {
+    config: RateLimitConfig,
+    /// Limite d'un utilisateur pour l'envoi de messages
+    messages: Limit,
+    /// Limite d'un utilisateur pour la création de demandes (tous types confondus)
+    requests: Limit,
+    store: Box<dyn BucketStore>,
+}
+
+impl RateLimiter {
+    /// Utilise Redis si `rate_limit.redis_url` est défini (fonctionnalité `redis`), la mémoire sinon
+    pub async fn from_config(config: RateLimitConfig) -> Self {
+        let store: Box<dyn BucketStore> = match &config.redis_url {
+            #[cfg(feature = "redis")]
+            Some(url) => match RedisStore::connect(url).await {
+                Ok(store) => Box::new(store),
+                Err(e) => {
+                    log::error!("Redis indisponible pour la limitation de débit: {}", e);
+                    Box::new(MemoryStore::default())
+                }
+            },
+            #[cfg(not(feature = "redis"))]
+            Some(_) => {
+                log::warn!("⚠️  RATE_LIMIT_REDIS_URL ignoré : compilé sans la fonctionnalité redis");
+                Box::new(MemoryStore::default())
+            }
+            None => Box::new(MemoryStore::default()),
+        };
+        let messages = Limit {
+            capacity: config.messages_burst,
+            refill_per_sec: config.messages_per_minute / 60.0,
+        };
+        let requests = Limit {
+            capacity: config.requests_burst,
+            refill_per_sec: config.requests_per_hour / 3600.0,
+        };
+        RateLimiter { config, messages, requests, store }
+    }
+
+    pub fn describe(&self) -> String {
+        if self.config.enabled {
+            format!("activée ({})", self.store.name())
+        } else {
+            "désactivée".to_string()
+        }
+    }
+
+    fn client_ip(&self, req: &HttpRequest) -> Option<String> {
+        if self.config.trust_forwarded {
+            req.connection_info().realip_remote_addr().map(str::to_string)
+        } else {
+            req.peer_addr().map(|addr| addr.ip().to_string())
+        }
+    }
+
+    /// Consomme un jeton pour l'adresse IP puis pour l'utilisateur ; 429 avec `Retry-After` si
+    /// l'un des deux seaux est vide. Une erreur du stockage laisse passer la requête.
+    ///
+    /// Le seau de l'utilisateur n'est débité que si son adresse IP n'est pas limitée.
+    pub async fn check(&self, action: RateLimitAction, req: &HttpRequest, username: &str) -> Result<(), HttpResponse> {
+        if !self.config.enabled {
+            return Ok(());
+        }
+        let limit = match action {
+            RateLimitAction::SendMessage => self.messages,
+            RateLimitAction::CreateRequest => self.requests,
+        };
+
+        let mut buckets = Vec::with_capacity(2);
+        if let Some(ip) = self.client_ip(req) {
+            buckets.push((format!("rl:{}:ip:{}", action.as_str(), ip), limit.scaled(self.config.ip_factor)));
+        }
+        buckets.push((format!("rl:{}:user:{}", action.as_str(), username), limit));
+
+        for (key, limit) in &buckets {
+            match self.store.take(key, limit).await {
+                Ok(None) => {}
+                Ok(Some(wait)) => {
+                    let retry_after = wait.as_secs_f64().ceil().max(1.0) as u64;
+                    log::warn!("🚦 Limite atteinte: {} (réessai dans {} s)", key, retry_after);
+                    return Err(HttpResponse::TooManyRequests()
+                        .insert_header(("Retry-After", retry_after.to_string()))
+                        .json(ApiResponse::<()>::err(format!(
+                            "Trop de requêtes, réessayez dans {} s",
+                            retry_after
+                        ))));
+                }
+                Err(e) => log::error!("Erreur limitation de débit ({}): {}", self.store.name(), e),
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIMIT: Limit = Limit {
+        capacity: 2.0,
+        refill_per_sec: 0.5,
+    };
+
+    #[test]
+    fn refill_is_proportional_to_elapsed_time_and_capped() {
+        let start = Instant::now();
+        let mut bucket = Bucket { tokens: 0.0, updated_at: start };
+
+        bucket.refill(&LIMIT, start + Duration::from_secs(1));
+        assert_eq!(bucket.tokens, 0.5);
+
+        bucket.refill(&LIMIT, start + Duration::from_secs(60));
+        assert_eq!(bucket.tokens, LIMIT.capacity);
+    }
+
+    #[tokio::test]
+    async fn memory_store_allows_a_burst_then_asks_to_wait() {
+        let store = MemoryStore::default();
+        assert_eq!(store.take("alice", &LIMIT).await, Ok(None));
+        assert_eq!(store.take("alice", &LIMIT).await, Ok(None));
+
+        let wait = store.take("alice", &LIMIT).await.unwrap().expect("seau vide");
+        assert!(wait > Duration::from_millis(1900) && wait <= Duration::from_secs(2));
+
+        // Chaque clé a son propre seau
+        assert_eq!(store.take("bob", &LIMIT).await, Ok(None));
+    }
+
+    fn limiter(ip_factor: f64) -> RateLimiter {
+        RateLimiter {
+            config: RateLimitConfig {
+                enabled: true,
+                messages_per_minute: 0.5,
+                messages_burst: 2.0,
+                requests_per_hour: 30.0,
+                requests_burst: 5.0,
+                ip_factor,
+                trust_forwarded: false,
+                redis_url: None,
+            },
+            messages: LIMIT,
+            requests: LIMIT,
+            store: Box::new(MemoryStore::default()),
+        }
+    }
+
+    fn from_ip(ip: &str) -> HttpRequest {
+        actix_web::test::TestRequest::default()
+            .peer_addr(format!("{}:40000", ip).parse().unwrap())
+            .to_http_request()
+    }
+
+    #[tokio::test]
+    async fn rejected_ip_does_not_charge_the_user() {
+        // Une seule requête par adresse IP, deux par utilisateur
+        let limiter = limiter(0.5);
+        let action = RateLimitAction::SendMessage;
+
+        assert!(limiter.check(action, &from_ip("10.0.0.1"), "alice").await.is_ok());
+        assert!(limiter.check(action, &from_ip("10.0.0.1"), "alice").await.is_err());
+        // Le refus précédent n'a pas entamé le seau d'alice
+        assert!(limiter.check(action, &from_ip("10.0.0.2"), "alice").await.is_ok());
+        assert!(limiter.check(action, &from_ip("10.0.0.3"), "alice").await.is_err());
+    }
+
+    #[test]
+    fn ip_limit_scales_capacity_and_rate() {
+        let scaled = LIMIT.scaled(5.0);
+        assert_eq!(scaled.capacity, 10.0);
+        assert_eq!(scaled.refill_per_sec, 2.5);
+    }
+}
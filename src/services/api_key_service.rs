@@ -0,0 +1,204 @@
+use futures_util::stream::TryStreamExt;
+use log::info;
+use mongodb::{
+    bson::{doc, oid::ObjectId, Document},
+    options::FindOptions,
+    Database,
+};
+use rand::{distributions::Alphanumeric, Rng};
+use sha2::{Digest, Sha256};
+
+/// Opérations autorisables pour une clé de service
+pub const API_KEY_SCOPES: [&str; 5] = [
+    "notifications:system",
+    "notifications:campaigns",
+    "notifications:templates",
+    "requests:expire",
+    "preferences:check",
+];
+
+/// Préfixe des clés générées, pour les repérer (journaux, détection de fuite)
+const API_KEY_PREFIX: &str = "mvk_";
+
+/// Caractères de la clé conservés en clair pour l'identifier dans les listes
+const API_KEY_HINT_CHARS: usize = 8;
+
+/// Délai par défaut pendant lequel l'ancienne clé reste valide après une rotation
+pub const DEFAULT_ROTATION_GRACE_SECS: i64 = 3600;
+
+/// Service appelant authentifié par `X-Api-Key`
+#[derive(Debug, Clone)]
+pub struct ServiceCaller {
+    /// Nom du service, repris dans `created_by`
+    pub name: String,
+    pub scopes: Vec<String>,
+}
+
+/// Clé de service à créer
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct NewApiKey {
+    pub name: String,
+    pub scopes: Vec<String>,
+}
+
+impl NewApiKey {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("Le nom du service est requis".to_string());
+        }
+        if self.scopes.is_empty() {
+            return Err("Au moins une portée est requise".to_string());
+        }
+        if let Some(scope) = self.scopes.iter().find(|s| !API_KEY_SCOPES.contains(&s.as_str())) {
+            return Err(format!("Portée inconnue: {} (attendu: {})", scope, API_KEY_SCOPES.join(", ")));
+        }
+        Ok(())
+    }
+}
+
+/// Empreinte SHA-256 stockée à la place de la clé
+fn hash_key(key: &str) -> String {
+    hex::encode(Sha256::digest(key.as_bytes()))
+}
+
+fn generate_key() -> String {
+    let secret: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(40)
+        .map(char::from)
+        .collect();
+    format!("{}{}", API_KEY_PREFIX, secret)
+}
+
+fn key_hint(key: &str) -> String {
+    key.chars().take(API_KEY_PREFIX.len() + API_KEY_HINT_CHARS).collect()
+}
+
+/// Clés d'API des services internes (collection `api_keys`).
+///
+/// Seule l'empreinte est stockée : la clé n'est lisible qu'à la création et à la rotation.
+pub struct ApiKeyService;
+
+impl ApiKeyService {
+    /// Crée une clé et renvoie son identifiant et sa valeur
+    pub async fn create(db: &Database, new_key: &NewApiKey) -> Result<(ObjectId, String), mongodb::error::Error> {
+        let key = generate_key();
+        let result = db
+            .collection::<Document>("api_keys")
+            .insert_one(
+                doc! {
+                    "name": &new_key.name,
+                    "scopes": &new_key.scopes,
+                    "key_hash": hash_key(&key),
+                    "key_hint": key_hint(&key),
+                    "previous_key_hash": null,
+                    "previous_expires_at": null,
+                    "created_at": chrono::Utc::now().to_rfc3339(),
+                    "rotated_at": null,
+                    "revoked_at": null,
+                    "last_used_at": null,
+                },
+                None,
+            )
+            .await?;
+        info!("🔑 Clé d'API créée pour {} ({})", new_key.name, new_key.scopes.join(", "));
+
+        let id = result.inserted_id.as_object_id().unwrap_or_default();
+        Ok((id, key))
+    }
+
+    /// Clés sans leurs empreintes
+    pub async fn list(db: &Database) -> Result<Vec<Document>, mongodb::error::Error> {
+        let options = FindOptions::builder()
+            .sort(doc! { "created_at": -1 })
+            .projection(doc! { "key_hash": 0, "previous_key_hash": 0 })
+            .build();
+        let mut cursor = db.collection::<Document>("api_keys").find(doc! {}, options).await?;
+
+        let mut keys = Vec::new();
+        while let Some(key) = cursor.try_next().await? {
+            keys.push(key);
+        }
+        Ok(keys)
+    }
+
+    /// Remplace la clé ; l'ancienne reste acceptée pendant `grace_secs` le temps du déploiement.
+    ///
+    /// Renvoie `None` si la clé est inconnue ou révoquée.
+    pub async fn rotate(db: &Database, id: &str, grace_secs: i64) -> Result<Option<String>, mongodb::error::Error> {
+        let Ok(oid) = ObjectId::parse_str(id) else {
+            return Ok(None);
+        };
+        let collection = db.collection::<Document>("api_keys");
+        let Some(current) = collection.find_one(doc! { "_id": oid, "revoked_at": null }, None).await? else {
+            return Ok(None);
+        };
+
+        let key = generate_key();
+        let now = chrono::Utc::now();
+        let previous_expires_at = (grace_secs > 0).then(|| (now + chrono::Duration::seconds(grace_secs)).to_rfc3339());
+        collection
+            .update_one(
+                doc! { "_id": oid },
+                doc! { "$set": {
+                    "key_hash": hash_key(&key),
+                    "key_hint": key_hint(&key),
+                    "previous_key_hash": current.get_str("key_hash").ok(),
+                    "previous_expires_at": previous_expires_at,
+                    "rotated_at": now.to_rfc3339(),
+                } },
+                None,
+            )
+            .await?;
+        info!("🔄 Clé d'API {} renouvelée", current.get_str("name").unwrap_or(id));
+        Ok(Some(key))
+    }
+
+    /// Révoque la clé (et sa version précédente) ; renvoie `false` si elle est inconnue ou déjà révoquée
+    pub async fn revoke(db: &Database, id: &str) -> Result<bool, mongodb::error::Error> {
+        let Ok(oid) = ObjectId::parse_str(id) else {
+            return Ok(false);
+        };
+        let result = db
+            .collection::<Document>("api_keys")
+            .update_one(
+                doc! { "_id": oid, "revoked_at": null },
+                doc! { "$set": { "revoked_at": chrono::Utc::now().to_rfc3339() } },
+                None,
+            )
+            .await?;
+        Ok(result.modified_count > 0)
+    }
+
+    /// Service correspondant à la clé présentée, si elle est valide
+    pub async fn authenticate(db: &Database, key: &str) -> Result<Option<ServiceCaller>, mongodb::error::Error> {
+        let hash = hash_key(key);
+        let now = chrono::Utc::now().to_rfc3339();
+        let collection = db.collection::<Document>("api_keys");
+        let filter = doc! {
+            "revoked_at": null,
+            "$or": [
+                { "key_hash": &hash },
+                { "previous_key_hash": &hash, "previous_expires_at": { "$gt": &now } },
+            ],
+        };
+        let Some(api_key) = collection.find_one(filter, None).await? else {
+            return Ok(None);
+        };
+
+        if let Ok(oid) = api_key.get_object_id("_id") {
+            collection
+                .update_one(doc! { "_id": oid }, doc! { "$set": { "last_used_at": &now } }, None)
+                .await?;
+        }
+
+        let scopes = api_key
+            .get_array("scopes")
+            .map(|scopes| scopes.iter().filter_map(|s| s.as_str().map(str::to_string)).collect())
+            .unwrap_or_default();
+        Ok(Some(ServiceCaller {
+            name: api_key.get_str("name").unwrap_or_default().to_string(),
+            scopes,
+        }))
+    }
+}
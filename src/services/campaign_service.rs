@@ -0,0 +1,429 @@
+use futures_util::stream::TryStreamExt;
+use log::info;
+use mongodb::{
+    bson::{doc, oid::ObjectId, Document},
+    options::{FindOptions, IndexOptions},
+    Database, IndexModel,
+};
+use std::collections::HashSet;
+use std::sync::Arc;
+use crate::config::CampaignConfig;
+use super::notification_service::NewNotification;
+use super::preference_service::{Channel, PreferenceService};
+
+/// Statuts d'une campagne
+pub const CAMPAIGN_RUNNING: &str = "running";
+pub const CAMPAIGN_COMPLETED: &str = "completed";
+pub const CAMPAIGN_CANCELLED: &str = "cancelled";
+pub const CAMPAIGN_FAILED: &str = "failed";
+
+/// Population ciblée par une campagne, sélectionnée dans `compte_compte`
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Segment {
+    /// Tous les utilisateurs
+    All,
+    /// Utilisateurs actuellement en ligne
+    Online,
+    /// Comptes créés à partir d'une date (YYYY-MM-DD ou RFC 3339)
+    CreatedAfter { date: String },
+}
+
+impl Segment {
+    async fn usernames(&self, pg_client: &tokio_postgres::Client) -> Result<Vec<String>, tokio_postgres::Error> {
+        let rows = match self {
+            Segment::All => {
+                pg_client
+                    .query("SELECT username FROM compte_compte ORDER BY username", &[])
+                    .await?
+            }
+            Segment::Online => {
+                pg_client
+                    .query("SELECT username FROM compte_compte WHERE is_online = true ORDER BY username", &[])
+                    .await?
+            }
+            Segment::CreatedAfter { date } => {
+                pg_client
+                    .query(
+                        "SELECT username FROM compte_compte WHERE date_joined >= $1::text::timestamptz ORDER BY username",
+                        &[date],
+                    )
+                    .await?
+            }
+        };
+
+        Ok(rows.iter().map(|row| row.get(0)).collect())
+    }
+}
+
+/// Campagne de notifications à envoyer à un segment d'utilisateurs
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct NewCampaign {
+    pub title: String,
+    pub message: String,
+    pub priority: Option<String>,
+    pub r#type: Option<String>,
+    pub action_url: Option<String>,
+    /// Remplacé par l'appelant authentifié (voir `handlers::notifications::create_campaign`)
+    #[serde(default)]
+    pub created_by: String,
+    pub segment: Segment,
+    /// Publication différée des notifications (RFC 3339)
+    pub send_at: Option<String>,
+    pub expires_at: Option<String>,
+}
+
+impl NewCampaign {
+    /// Notification modèle, dupliquée pour chaque destinataire
+    fn notification(&self) -> NewNotification {
+        NewNotification {
+            to: String::new(),
+            r#type: self.r#type.clone().unwrap_or_else(|| "broadcast".to_string()),
+            title: self.title.clone(),
+            message: self.message.clone(),
+            priority: self.priority.clone().unwrap_or_else(|| "normal".to_string()),
+            action_url: self.action_url.clone(),
+            created_by: self.created_by.clone(),
+            send_at: self.send_at.clone(),
+            expires_at: self.expires_at.clone(),
+            ..Default::default()
+        }
+    }
+}
+
+/// Campagne encore annulable : en cours d'envoi, ou terminée mais programmée pour plus tard
+fn cancellable_filter(id: ObjectId, now: &str) -> Document {
+    doc! {
+        "_id": id,
+        "$or": [
+            { "status": CAMPAIGN_RUNNING },
+            { "status": CAMPAIGN_COMPLETED, "send_at": { "$gt": now } },
+        ],
+    }
+}
+
+type CampaignError = Box<dyn std::error::Error + Send + Sync>;
+
+pub struct CampaignService;
+
+impl CampaignService {
+    /// Index utilisé à chaque lot pour retrouver les destinataires déjà notifiés
+    pub async fn ensure_indexes(db: &Database) -> Result<(), mongodb::error::Error> {
+        let index = IndexModel::builder()
+            .keys(doc! { "campaign_id": 1, "to": 1 })
+            .options(IndexOptions::builder().name("campaign_recipients".to_string()).build())
+            .build();
+        db.collection::<Document>("system_notifications").create_index(index, None).await?;
+        Ok(())
+    }
+
+    /// Enregistre la campagne et renvoie son identifiant ; l'envoi est fait par `run`
+    pub async fn create(db: &Database, campaign: &NewCampaign) -> Result<ObjectId, CampaignError> {
+        let now = chrono::Utc::now().to_rfc3339();
+        let doc = doc! {
+            "title": &campaign.title,
+            "message": &campaign.message,
+            "priority": campaign.priority.as_deref().unwrap_or("normal"),
+            "type": campaign.r#type.as_deref().unwrap_or("broadcast"),
+            "action_url": &campaign.action_url,
+            "created_by": &campaign.created_by,
+            "segment": mongodb::bson::to_document(&campaign.segment)?,
+            "send_at": &campaign.send_at,
+            "expires_at": &campaign.expires_at,
+            "status": CAMPAIGN_RUNNING,
+            "total": null,
+            "sent": 0_i64,
+            "skipped": 0_i64,
+            "created_at": &now,
+            "finished_at": null,
+            "error": null,
+        };
+
+        let result = db
+            .collection::<Document>("notification_campaigns")
+            .insert_one(doc, None)
+            .await?;
+
+        result
+            .inserted_id
+            .as_object_id()
+            .ok_or_else(|| "Identifiant de campagne invalide".into())
+    }
+
+    pub async fn get(db: &Database, id: &str) -> Result<Option<Document>, mongodb::error::Error> {
+        let Ok(oid) = ObjectId::parse_str(id) else {
+            return Ok(None);
+        };
+        db.collection::<Document>("notification_campaigns")
+            .find_one(doc! { "_id": oid }, None)
+            .await
+    }
+
+    /// Campagnes les plus récentes en premier
+    pub async fn list(db: &Database, limit: i64) -> Result<Vec<Document>, mongodb::error::Error> {
+        let options = FindOptions::builder()
+            .sort(doc! { "created_at": -1 })
+            .limit(limit)
+            .build();
+
+        let mut cursor = db
+            .collection::<Document>("notification_campaigns")
+            .find(doc! {}, options)
+            .await?;
+
+        let mut campaigns = Vec::new();
+        while let Some(campaign) = cursor.try_next().await? {
+            campaigns.push(campaign);
+        }
+        Ok(campaigns)
+    }
+
+    /// Demande l'arrêt d'une campagne en cours, ou d'une campagne programmée dont les
+    /// notifications ne sont pas encore publiées ; le lot en cours d'insertion se termine.
+    ///
+    /// Renvoie le nombre de notifications programmées supprimées, `None` si la campagne est
+    /// introuvable ou déjà terminée.
+    pub async fn cancel(db: &Database, id: &str) -> Result<Option<u64>, mongodb::error::Error> {
+        let Ok(oid) = ObjectId::parse_str(id) else {
+            return Ok(None);
+        };
+        let now = chrono::Utc::now().to_rfc3339();
+        let result = db
+            .collection::<Document>("notification_campaigns")
+            .update_one(
+                cancellable_filter(oid, &now),
+                doc! { "$set": { "status": CAMPAIGN_CANCELLED, "cancelled_at": &now } },
+                None,
+            )
+            .await?;
+        if result.modified_count == 0 {
+            return Ok(None);
+        }
+
+        let removed = Self::remove_unpublished(db, &oid.to_hex()).await?;
+        info!("🛑 Campagne {} annulée, {} notifications programmées supprimées", oid, removed);
+        Ok(Some(removed))
+    }
+
+    /// Supprime les notifications programmées d'une campagne, sans toucher à celles déjà publiées
+    async fn remove_unpublished(db: &Database, campaign_id: &str) -> Result<u64, mongodb::error::Error> {
+        let result = db
+            .collection::<Document>("system_notifications")
+            .delete_many(doc! { "campaign_id": campaign_id, "published": false }, None)
+            .await?;
+        Ok(result.deleted_count)
+    }
+
+    /// Envoie la campagne par lots dans `system_notifications` en suivant la progression
+    pub async fn run(
+        db: Database,
+        pg_client: Arc<tokio_postgres::Client>,
+        id: ObjectId,
+        campaign: NewCampaign,
+        config: CampaignConfig,
+    ) {
+        let collection = db.collection::<Document>("notification_campaigns");
+
+        let outcome = Self::deliver(&db, &pg_client, id, &campaign, &config).await;
+        let now = chrono::Utc::now().to_rfc3339();
+        // Une annulation pendant le dernier lot ne doit pas être écrasée par `completed`
+        let interrupted = matches!(outcome, Ok(false));
+        let (filter, update) = match outcome {
+            Ok(true) => (
+                doc! { "_id": id, "status": CAMPAIGN_RUNNING },
+                doc! { "$set": { "status": CAMPAIGN_COMPLETED, "finished_at": &now } },
+            ),
+            // Annulée : le statut est déjà positionné par `cancel`
+            Ok(false) => (doc! { "_id": id }, doc! { "$set": { "finished_at": &now } }),
+            Err(e) => {
+                log::error!("Erreur campagne {}: {}", id, e);
+                (
+                    doc! { "_id": id },
+                    doc! { "$set": { "status": CAMPAIGN_FAILED, "finished_at": &now, "error": e.to_string() } },
+                )
+            }
+        };
+
+        let cancelled = match collection.update_one(filter, update, None).await {
+            Ok(result) => result.matched_count == 0,
+            Err(e) => {
+                log::error!("Erreur MongoDB: {}", e);
+                return;
+            }
+        };
+
+        // Le lot inséré pendant l'annulation a pu échapper à `cancel`
+        if cancelled || interrupted {
+            if let Err(e) = Self::remove_unpublished(&db, &id.to_hex()).await {
+                log::error!("Erreur MongoDB: {}", e);
+            }
+        }
+    }
+
+    /// Reprend les campagnes restées `running` à l'arrêt précédent du serveur ; sans
+    /// PostgreSQL, elles sont marquées en échec. Renvoie le nombre de campagnes concernées.
+    pub async fn resume_interrupted(
+        db: &Database,
+        pg_client: Option<Arc<tokio_postgres::Client>>,
+        config: &CampaignConfig,
+    ) -> Result<usize, mongodb::error::Error> {
+        let collection = db.collection::<Document>("notification_campaigns");
+        let mut cursor = collection.find(doc! { "status": CAMPAIGN_RUNNING }, None).await?;
+
+        let mut interrupted = Vec::new();
+        while let Some(campaign) = cursor.try_next().await? {
+            interrupted.push(campaign);
+        }
+
+        for campaign in &interrupted {
+            let Ok(id) = campaign.get_object_id("_id") else {
+                continue;
+            };
+            let parsed = mongodb::bson::from_document::<NewCampaign>(campaign.clone());
+            match (parsed, pg_client.clone()) {
+                (Ok(new_campaign), Some(pg_client)) => {
+                    info!("🔁 Reprise de la campagne {}", id);
+                    tokio::spawn(Self::run(db.clone(), pg_client, id, new_campaign, config.clone()));
+                }
+                (parsed, _) => {
+                    let error = match parsed {
+                        Err(e) => format!("Campagne illisible: {}", e),
+                        Ok(_) => "Interrompue par un redémarrage (PostgreSQL indisponible)".to_string(),
+                    };
+                    log::warn!("⚠️  Campagne {} non reprise: {}", id, error);
+                    let now = chrono::Utc::now().to_rfc3339();
+                    collection
+                        .update_one(
+                            doc! { "_id": id, "status": CAMPAIGN_RUNNING },
+                            doc! { "$set": { "status": CAMPAIGN_FAILED, "finished_at": &now, "error": error } },
+                            None,
+                        )
+                        .await?;
+                }
+            }
+        }
+        Ok(interrupted.len())
+    }
+
+    /// Destinataires d'un lot ayant déjà reçu la campagne (reprise après redémarrage)
+    async fn already_notified(
+        notifications: &mongodb::Collection<Document>,
+        campaign_id: &str,
+        batch: &[String],
+    ) -> Result<HashSet<String>, mongodb::error::Error> {
+        let recipients = notifications
+            .distinct("to", doc! { "campaign_id": campaign_id, "to": { "$in": batch } }, None)
+            .await?;
+        Ok(recipients
+            .into_iter()
+            .filter_map(|to| to.as_str().map(str::to_string))
+            .collect())
+    }
+
+    /// Renvoie `false` si la campagne a été annulée en cours de route
+    async fn deliver(
+        db: &Database,
+        pg_client: &tokio_postgres::Client,
+        id: ObjectId,
+        campaign: &NewCampaign,
+        config: &CampaignConfig,
+    ) -> Result<bool, CampaignError> {
+        let campaigns = db.collection::<Document>("notification_campaigns");
+        let notifications = db.collection::<Document>("system_notifications");
+
+        let usernames = campaign.segment.usernames(pg_client).await?;
+        campaigns
+            .update_one(doc! { "_id": id }, doc! { "$set": { "total": usernames.len() as i64 } }, None)
+            .await?;
+        info!("📣 Campagne {}: {} destinataires", id, usernames.len());
+
+        let template = campaign.notification();
+        let campaign_id = id.to_hex();
+
+        for batch in usernames.chunks(config.batch_size) {
+            let status = campaigns
+                .find_one(doc! { "_id": id }, None)
+                .await?
+                .and_then(|c| c.get_str("status").ok().map(|s| s.to_string()));
+            if status.as_deref() != Some(CAMPAIGN_RUNNING) {
+                info!("🛑 Campagne {} interrompue", id);
+                return Ok(false);
+            }
+
+            // Une campagne reprise ne renvoie pas les notifications déjà insérées
+            let notified = Self::already_notified(&notifications, &campaign_id, batch).await?;
+            let batch: Vec<String> = batch.iter().filter(|username| !notified.contains(*username)).cloned().collect();
+
+            // Les utilisateurs ayant désactivé ce type de notification sont ignorés
+            let preferences = PreferenceService::get_many(db, &batch).await?;
+            let docs: Vec<Document> = batch
+                .iter()
+                .filter(|username| {
+                    preferences
+                        .get(*username)
+                        .is_none_or(|p| p.allows(&template.r#type, Channel::InApp))
+                })
+                .map(|username| {
+                    let mut doc = NewNotification { to: username.clone(), ..template.clone() }.to_document();
+                    doc.insert("campaign_id", &campaign_id);
+                    doc
+                })
+                .collect();
+            let sent = docs.len();
+            let skipped = batch.len() - sent;
+            if !docs.is_empty() {
+                notifications.insert_many(docs, None).await?;
+            }
+
+            campaigns
+                .update_one(
+                    doc! { "_id": id },
+                    doc! { "$inc": { "sent": sent as i64, "skipped": skipped as i64 } },
+                    None,
+                )
+                .await?;
+        }
+
+        info!("✅ Campagne {} terminée", id);
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scheduled_campaigns_stay_cancellable_after_delivery() {
+        let id = ObjectId::new();
+        let now = "2026-01-01T10:00:00+00:00";
+        assert_eq!(
+            cancellable_filter(id, now),
+            doc! {
+                "_id": id,
+                "$or": [
+                    { "status": "running" },
+                    { "status": "completed", "send_at": { "$gt": "2026-01-01T10:00:00+00:00" } },
+                ],
+            }
+        );
+    }
+
+    #[test]
+    fn campaign_notifications_are_scheduled_until_send_at() {
+        let campaign = NewCampaign {
+            title: "Nouveautés".to_string(),
+            message: "Découvrez les salons vocaux".to_string(),
+            priority: None,
+            r#type: None,
+            action_url: None,
+            created_by: "admin".to_string(),
+            segment: Segment::All,
+            send_at: Some((chrono::Utc::now() + chrono::Duration::hours(1)).to_rfc3339()),
+            expires_at: None,
+        };
+        let document = NewNotification { to: "alice".to_string(), ..campaign.notification() }.to_document();
+        // Ce sont ces notifications que `cancel` retire avant leur publication
+        assert_eq!(document.get_bool("published"), Ok(false));
+    }
+}
@@ -0,0 +1,216 @@
+use futures_util::stream::TryStreamExt;
+use log::info;
+use mongodb::{
+    bson::{doc, Document},
+    options::UpdateOptions,
+    Database,
+};
+use std::collections::HashMap;
+use crate::config::DigestConfig;
+use super::mail_service::Mailer;
+use super::preference_service::{Channel, PreferenceService};
+use super::push_service::MESSAGE_NOTIFICATION_TYPE;
+
+type DigestError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Messages non lus d'un destinataire, regroupés par expéditeur
+struct PendingDigest {
+    username: String,
+    senders: Vec<(String, i64)>,
+    total: i64,
+    /// Timestamp du message le plus récent inclus, mémorisé pour ne pas le renvoyer
+    last_message_timestamp: String,
+}
+
+/// Prénom et email d'un compte `compte_compte`
+struct Contact {
+    prenom: Option<String>,
+    email: Option<String>,
+}
+
+pub struct DigestService;
+
+impl DigestService {
+    /// Envoie un récapitulatif aux utilisateurs ayant des messages non lus depuis plus de
+    /// `unread_after_hours` heures et renvoie le nombre d'emails envoyés.
+    ///
+    /// Seuls les messages postérieurs au précédent récapitulatif sont pris en compte
+    /// (collection `email_digests`).
+    pub async fn send_digests(
+        db: &Database,
+        pg_client: &tokio_postgres::Client,
+        mailer: &Mailer,
+        config: &DigestConfig,
+    ) -> Result<usize, DigestError> {
+        // Même format que les timestamps écrits par la passerelle (toISOString)
+        let cutoff = (chrono::Utc::now() - chrono::Duration::hours(config.unread_after_hours))
+            .to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
+        let pending = Self::pending(db, &cutoff).await?;
+        if pending.is_empty() {
+            return Ok(0);
+        }
+
+        let mut usernames: Vec<String> = pending.iter().map(|p| p.username.clone()).collect();
+        let preferences = PreferenceService::get_many(db, &usernames).await?;
+        usernames.extend(pending.iter().flat_map(|p| p.senders.iter().map(|(from, _)| from.clone())));
+        let contacts = Self::contacts(pg_client, &usernames).await?;
+
+        let mut sent = 0;
+        for digest in pending {
+            // Heures calmes : le récapitulatif partira lors d'un prochain passage
+            if preferences
+                .get(&digest.username)
+                .is_some_and(|p| !p.allows(MESSAGE_NOTIFICATION_TYPE, Channel::Email))
+            {
+                continue;
+            }
+            let Some(email) = contacts.get(&digest.username).and_then(|c| c.email.as_deref()) else {
+                continue;
+            };
+
+            let (subject, body) = render(&digest, &contacts);
+            if let Err(e) = mailer.send(email, &subject, body).await {
+                log::error!("Erreur envoi du récapitulatif à {}: {}", digest.username, e);
+                continue;
+            }
+
+            db.collection::<Document>("email_digests")
+                .update_one(
+                    doc! { "username": &digest.username },
+                    doc! { "$set": {
+                        "last_message_timestamp": &digest.last_message_timestamp,
+                        "message_count": digest.total,
+                        "sent_at": chrono::Utc::now().to_rfc3339(),
+                    } },
+                    UpdateOptions::builder().upsert(true).build(),
+                )
+                .await?;
+            sent += 1;
+        }
+
+        if sent > 0 {
+            info!("📧 {} récapitulatifs de messages non lus envoyés", sent);
+        }
+        Ok(sent)
+    }
+
+    /// Messages non lus antérieurs à `cutoff` et non encore récapitulés, par destinataire
+    async fn pending(db: &Database, cutoff: &str) -> Result<Vec<PendingDigest>, mongodb::error::Error> {
+        let pipeline = vec![
+            doc! { "$match": { "read": { "$ne": true }, "timestamp": { "$lte": cutoff } } },
+            doc! { "$lookup": {
+                "from": "email_digests",
+                "localField": "to",
+                "foreignField": "username",
+                "as": "digest",
+            } },
+            doc! { "$unwind": { "path": "$digest", "preserveNullAndEmptyArrays": true } },
+            doc! { "$match": { "$expr": {
+                "$gt": ["$timestamp", { "$ifNull": ["$digest.last_message_timestamp", ""] }]
+            } } },
+            doc! { "$group": {
+                "_id": { "to": "$to", "from": "$from" },
+                "count": { "$sum": 1 },
+                "last": { "$max": "$timestamp" },
+            } },
+            doc! { "$sort": { "count": -1 } },
+            doc! { "$group": {
+                "_id": "$_id.to",
+                "senders": { "$push": { "from": "$_id.from", "count": "$count" } },
+                "total": { "$sum": "$count" },
+                "last": { "$max": "$last" },
+            } },
+        ];
+
+        let mut cursor = db.collection::<Document>("messages").aggregate(pipeline, None).await?;
+        let mut pending = Vec::new();
+        while let Some(group) = cursor.try_next().await? {
+            let senders = group
+                .get_array("senders")
+                .map(|senders| {
+                    senders
+                        .iter()
+                        .filter_map(|sender| sender.as_document())
+                        .map(|sender| {
+                            (
+                                sender.get_str("from").unwrap_or("").to_string(),
+                                count(sender.get("count")),
+                            )
+                        })
+                        .collect()
+                })
+                .unwrap_or_default();
+
+            pending.push(PendingDigest {
+                username: group.get_str("_id").unwrap_or("").to_string(),
+                senders,
+                total: count(group.get("total")),
+                last_message_timestamp: group.get_str("last").unwrap_or("").to_string(),
+            });
+        }
+
+        Ok(pending)
+    }
+
+    /// Prénoms et emails depuis `compte_compte`, en une requête
+    async fn contacts(
+        pg_client: &tokio_postgres::Client,
+        usernames: &[String],
+    ) -> Result<HashMap<String, Contact>, tokio_postgres::Error> {
+        let rows = pg_client
+            .query(
+                "SELECT username, prenom, email FROM compte_compte WHERE username = ANY($1)",
+                &[&usernames],
+            )
+            .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| {
+                (
+                    row.get::<_, String>("username"),
+                    Contact {
+                        prenom: row.get("prenom"),
+                        email: row.get::<_, Option<String>>("email").filter(|e| !e.is_empty()),
+                    },
+                )
+            })
+            .collect())
+    }
+}
+
+fn count(value: Option<&mongodb::bson::Bson>) -> i64 {
+    match value {
+        Some(mongodb::bson::Bson::Int32(n)) => *n as i64,
+        Some(mongodb::bson::Bson::Int64(n)) => *n,
+        _ => 0,
+    }
+}
+
+/// Sujet et corps texte du récapitulatif
+fn render(digest: &PendingDigest, contacts: &HashMap<String, Contact>) -> (String, String) {
+    let display_name = |username: &str| {
+        contacts
+            .get(username)
+            .and_then(|c| c.prenom.clone())
+            .filter(|prenom| !prenom.is_empty())
+            .unwrap_or_else(|| username.to_string())
+    };
+
+    let subject = if digest.total == 1 {
+        "Vous avez 1 message non lu".to_string()
+    } else {
+        format!("Vous avez {} messages non lus", digest.total)
+    };
+
+    let mut body = format!("Bonjour {},\n\n", display_name(&digest.username));
+    body.push_str("Des messages vous attendent sur MeetVoice :\n\n");
+    for (from, count) in &digest.senders {
+        let plural = if *count > 1 { "s" } else { "" };
+        body.push_str(&format!("  • {} : {} message{}\n", display_name(from), count, plural));
+    }
+    body.push_str("\nConnectez-vous pour leur répondre : https://meet-voice.fr\n\n");
+    body.push_str("Vous pouvez désactiver ces emails dans vos préférences de notification.\n");
+
+    (subject, body)
+}
@@ -0,0 +1,62 @@
+use lettre::{
+    message::{header::ContentType, Mailbox},
+    transport::smtp::authentication::Credentials,
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+};
+use crate::config::MailConfig;
+
+pub type MailError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Envoi d'emails par SMTP, configuré par la section `mail` de la configuration.
+///
+/// `tls = "none"` permet de viser un collecteur local (ex: Mailpit sur le port 1025).
+#[derive(Clone)]
+pub struct Mailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+impl Mailer {
+    pub fn from_config(config: &MailConfig) -> Result<Self, String> {
+        let host = config.host.as_str();
+        let mut builder = match config.tls.as_str() {
+            "none" => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(host),
+            "starttls" => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host)
+                .map_err(|e| format!("SMTP {}: {}", host, e))?,
+            "tls" => AsyncSmtpTransport::<Tokio1Executor>::relay(host)
+                .map_err(|e| format!("SMTP {}: {}", host, e))?,
+            other => return Err(format!("SMTP_TLS invalide: {} (attendu: none, starttls, tls)", other)),
+        };
+
+        if let Some(port) = config.port {
+            builder = builder.port(port);
+        }
+        if let Some(username) = &config.username {
+            let password = config.password.clone().unwrap_or_default();
+            builder = builder.credentials(Credentials::new(username.clone(), password));
+        }
+
+        let from = config
+            .from
+            .parse::<Mailbox>()
+            .map_err(|e| format!("SMTP_FROM invalide: {}", e))?;
+
+        Ok(Mailer {
+            transport: builder.build(),
+            from,
+        })
+    }
+
+    /// Envoie un email texte
+    pub async fn send(&self, to: &str, subject: &str, body: String) -> Result<(), MailError> {
+        let message = Message::builder()
+            .from(self.from.clone())
+            .to(to.parse::<Mailbox>()?)
+            .subject(subject)
+            .header(ContentType::TEXT_PLAIN)
+            .body(body)?;
+
+        self.transport.send(message).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,17 @@
+pub mod api_key_service;
+pub mod campaign_service;
+pub mod digest_service;
+pub mod mail_service;
+pub mod notification_service;
+pub mod preference_service;
+pub mod presence_service;
+pub mod privacy_service;
+pub mod push_providers;
+pub mod push_service;
+pub mod request_kinds;
+pub mod request_service;
+pub mod stream_service;
+pub mod template_service;
+pub mod typing_service;
+pub mod user_service;
+pub mod webhook_service;
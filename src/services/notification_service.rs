@@ -0,0 +1,361 @@
+use futures_util::stream::TryStreamExt;
+use log::info;
+use mongodb::{
+    bson::{doc, oid::ObjectId, Bson, Document},
+    options::FindOptions,
+    Database,
+};
+use super::preference_service::{Channel, PreferenceService};
+
+/// Expéditeur affiché pour toutes les notifications système
+pub const SYSTEM_SENDER: &str = "meet-voice.fr";
+
+/// Notification système à insérer dans `system_notifications`
+#[derive(Debug, Clone, Default)]
+pub struct NewNotification {
+    pub to: String,
+    pub r#type: String,
+    pub title: String,
+    pub message: String,
+    pub priority: String,
+    pub action_url: Option<String>,
+    pub created_by: String,
+    /// Publication différée (RFC 3339 UTC), immédiate si absent
+    pub send_at: Option<String>,
+    /// Au-delà de cette date la notification n'est plus listée
+    pub expires_at: Option<String>,
+    /// Une notification non lue de même clé pour le même utilisateur est remplacée au lieu d'empiler
+    pub collapse_key: Option<String>,
+    /// Message agrégé utilisé au lieu du remplacement, ex: "{count} nouvelles demandes"
+    pub collapse_summary: Option<String>,
+    /// Lien de la notification agrégée (ex: liste des demandes), `action_url` à défaut
+    pub collapse_action_url: Option<String>,
+}
+
+impl NewNotification {
+    /// Document tel que stocké dans `system_notifications`.
+    ///
+    /// Une notification programmée est enregistrée avec `published: false` et son
+    /// `timestamp` est fixé lors de la publication par la tâche planifiée.
+    pub fn to_document(&self) -> Document {
+        let now = chrono::Utc::now().to_rfc3339();
+        let published = self.send_at.as_ref().is_none_or(|send_at| *send_at <= now);
+
+        doc! {
+            "from": SYSTEM_SENDER,
+            "to": &self.to,
+            "type": &self.r#type,
+            "title": &self.title,
+            "message": &self.message,
+            "timestamp": self.send_at.as_ref().filter(|_| !published).unwrap_or(&now),
+            "read": false,
+            "priority": &self.priority,
+            "action_url": &self.action_url,
+            "created_by": &self.created_by,
+            "send_at": &self.send_at,
+            "expires_at": &self.expires_at,
+            "published": published,
+            "collapse_key": &self.collapse_key,
+            "collapse_count": 1,
+        }
+    }
+}
+
+/// Normalise une date RFC 3339 en UTC pour permettre les comparaisons de chaînes en base
+pub fn normalize_timestamp(value: &str) -> Option<String> {
+    chrono::DateTime::parse_from_rfc3339(value)
+        .ok()
+        .map(|dt| dt.with_timezone(&chrono::Utc).to_rfc3339())
+}
+
+/// Filtre des notifications visibles : publiées et non expirées
+fn visible_filter(username: &str) -> Document {
+    let now = chrono::Utc::now().to_rfc3339();
+    doc! {
+        "to": username,
+        // Les notifications antérieures à la programmation n'ont pas ce champ
+        "published": { "$ne": false },
+        "$or": [
+            { "expires_at": null },
+            { "expires_at": { "$gt": &now } },
+        ],
+    }
+}
+
+/// Critères de lecture du centre de notifications
+#[derive(Debug, Default)]
+pub struct NotificationFilter {
+    pub unread_only: bool,
+    pub priority: Option<String>,
+    pub r#type: Option<String>,
+    /// Pagination : notifications antérieures à ce timestamp
+    pub before: Option<String>,
+}
+
+pub struct NotificationService;
+
+impl NotificationService {
+    /// Enregistre une notification non lue et renvoie son identifiant, ou `None` si le
+    /// destinataire a désactivé ce type de notification dans l'application.
+    ///
+    /// Avec une `collapse_key`, la notification non lue de même clé est mise à jour
+    /// (remplacée, ou agrégée si `collapse_summary` est fourni) et son identifiant renvoyé.
+    pub async fn create(
+        db: &Database,
+        notification: &NewNotification,
+    ) -> Result<Option<String>, mongodb::error::Error> {
+        let collection = db.collection::<Document>("system_notifications");
+
+        let preferences = PreferenceService::get(db, &notification.to).await?;
+        if !preferences.allows(&notification.r#type, Channel::InApp) {
+            info!("🔕 Notification {} ignorée pour {} (préférences)", notification.r#type, notification.to);
+            return Ok(None);
+        }
+
+        if notification.send_at.is_none() {
+            if let Some(id) = Self::collapse(db, notification).await? {
+                return Ok(Some(id));
+            }
+        }
+
+        let result = collection.insert_one(notification.to_document(), None).await?;
+        info!("🔔 Notification {} → {}", notification.r#type, notification.to);
+
+        Ok(Some(
+            result
+                .inserted_id
+                .as_object_id()
+                .map(|oid| oid.to_hex())
+                .unwrap_or_else(|| result.inserted_id.to_string()),
+        ))
+    }
+
+    /// Fusionne avec la notification non lue de même `collapse_key`, si elle existe
+    async fn collapse(
+        db: &Database,
+        notification: &NewNotification,
+    ) -> Result<Option<String>, mongodb::error::Error> {
+        let Some(collapse_key) = &notification.collapse_key else {
+            return Ok(None);
+        };
+        let collection = db.collection::<Document>("system_notifications");
+
+        let mut filter = visible_filter(&notification.to);
+        filter.insert("read", false);
+        filter.insert("collapse_key", collapse_key);
+        let Some(existing) = collection.find_one(filter, None).await? else {
+            return Ok(None);
+        };
+        let Ok(oid) = existing.get_object_id("_id") else {
+            return Ok(None);
+        };
+
+        let previous_count = match existing.get("collapse_count") {
+            Some(Bson::Int32(count)) => *count as i64,
+            Some(Bson::Int64(count)) => *count,
+            _ => 1,
+        };
+        let count = previous_count + 1;
+        // Une notification agrégée ne pointe plus vers le dernier élément seulement
+        let (message, action_url) = match &notification.collapse_summary {
+            Some(summary) => (
+                summary.replace("{count}", &count.to_string()),
+                notification.collapse_action_url.as_ref().or(notification.action_url.as_ref()),
+            ),
+            None => (notification.message.clone(), notification.action_url.as_ref()),
+        };
+        let now = chrono::Utc::now().to_rfc3339();
+
+        // Le filtre sur le compteur évite de perdre une agrégation concurrente
+        let result = collection
+            .update_one(
+                doc! { "_id": oid, "read": false, "collapse_count": existing.get("collapse_count").cloned().unwrap_or(Bson::Null) },
+                doc! { "$set": {
+                    "type": &notification.r#type,
+                    "title": &notification.title,
+                    "message": &message,
+                    "timestamp": &now,
+                    "priority": &notification.priority,
+                    "action_url": action_url,
+                    "created_by": &notification.created_by,
+                    "expires_at": &notification.expires_at,
+                    "collapse_count": count,
+                } },
+                None,
+            )
+            .await?;
+
+        if result.modified_count == 0 {
+            return Ok(None);
+        }
+
+        info!("🔔 Notification {} → {} regroupée ({})", notification.r#type, notification.to, count);
+        Ok(Some(oid.to_hex()))
+    }
+
+    /// Notifications d'un utilisateur, de la plus récente à la plus ancienne
+    pub async fn list(
+        db: &Database,
+        username: &str,
+        filter: &NotificationFilter,
+        limit: i64,
+    ) -> Result<Vec<Document>, mongodb::error::Error> {
+        let collection = db.collection::<Document>("system_notifications");
+
+        let mut query = visible_filter(username);
+        if filter.unread_only {
+            query.insert("read", false);
+        }
+        if let Some(priority) = &filter.priority {
+            query.insert("priority", priority);
+        }
+        if let Some(notification_type) = &filter.r#type {
+            query.insert("type", notification_type);
+        }
+        if let Some(before) = &filter.before {
+            query.insert("timestamp", doc! { "$lt": before });
+        }
+
+        let options = FindOptions::builder()
+            .sort(doc! { "timestamp": -1 })
+            .limit(limit)
+            .build();
+
+        let mut cursor = collection.find(query, options).await?;
+        let mut notifications = Vec::new();
+        while let Some(notification) = cursor.try_next().await? {
+            notifications.push(notification);
+        }
+
+        Ok(notifications)
+    }
+
+    /// Nombre de notifications non lues
+    pub async fn unread_count(db: &Database, username: &str) -> Result<u64, mongodb::error::Error> {
+        let mut filter = visible_filter(username);
+        filter.insert("read", false);
+        db.collection::<Document>("system_notifications")
+            .count_documents(filter, None)
+            .await
+    }
+
+    /// Marque une notification comme lue. Renvoie `false` si elle n'appartient pas à l'utilisateur
+    /// ou ne lui est pas visible (programmée ou expirée).
+    pub async fn mark_read(db: &Database, username: &str, id: &str) -> Result<bool, mongodb::error::Error> {
+        let Ok(oid) = ObjectId::parse_str(id) else {
+            return Ok(false);
+        };
+        let mut filter = visible_filter(username);
+        filter.insert("_id", oid);
+        let now = chrono::Utc::now().to_rfc3339();
+        let result = db
+            .collection::<Document>("system_notifications")
+            .update_one(
+                filter,
+                doc! { "$set": { "read": true, "read_at": &now } },
+                None,
+            )
+            .await?;
+        Ok(result.matched_count > 0)
+    }
+
+    /// Marque toutes les notifications visibles non lues comme lues ; les notifications
+    /// programmées restent non lues jusqu'à leur publication
+    pub async fn mark_all_read(db: &Database, username: &str) -> Result<u64, mongodb::error::Error> {
+        let mut filter = visible_filter(username);
+        filter.insert("read", false);
+        let now = chrono::Utc::now().to_rfc3339();
+        let result = db
+            .collection::<Document>("system_notifications")
+            .update_many(
+                filter,
+                doc! { "$set": { "read": true, "read_at": &now } },
+                None,
+            )
+            .await?;
+        info!("✓ {} notifications marquées comme lues pour {}", result.modified_count, username);
+        Ok(result.modified_count)
+    }
+
+    /// Supprime une notification. Renvoie `false` si elle n'appartient pas à l'utilisateur
+    /// ou ne lui est pas visible (programmée ou expirée).
+    pub async fn delete(db: &Database, username: &str, id: &str) -> Result<bool, mongodb::error::Error> {
+        let Ok(oid) = ObjectId::parse_str(id) else {
+            return Ok(false);
+        };
+        let mut filter = visible_filter(username);
+        filter.insert("_id", oid);
+        let result = db
+            .collection::<Document>("system_notifications")
+            .delete_one(filter, None)
+            .await?;
+        Ok(result.deleted_count > 0)
+    }
+
+    /// Publie les notifications programmées dont l'heure d'envoi est passée
+    pub async fn publish_due(db: &Database) -> Result<Vec<Document>, mongodb::error::Error> {
+        let collection = db.collection::<Document>("system_notifications");
+        let now = chrono::Utc::now().to_rfc3339();
+
+        let mut cursor = collection
+            .find(doc! { "published": false, "send_at": { "$lte": &now } }, None)
+            .await?;
+        let mut due = Vec::new();
+        while let Some(notification) = cursor.try_next().await? {
+            due.push(notification);
+        }
+
+        let mut published = Vec::new();
+        for mut notification in due {
+            let Ok(oid) = notification.get_object_id("_id") else {
+                continue;
+            };
+            let result = collection
+                .update_one(
+                    doc! { "_id": oid, "published": false },
+                    doc! { "$set": { "published": true, "timestamp": &now } },
+                    None,
+                )
+                .await?;
+            if result.modified_count > 0 {
+                notification.insert("published", true);
+                notification.insert("timestamp", &now);
+                published.push(notification);
+            }
+        }
+
+        if !published.is_empty() {
+            info!("📬 {} notifications programmées publiées", published.len());
+        }
+
+        Ok(published)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn visible_filter_hides_scheduled_and_expired_notifications() {
+        let filter = visible_filter("alice");
+        assert_eq!(filter.get_str("to"), Ok("alice"));
+        assert_eq!(filter.get_document("published"), Ok(&doc! { "$ne": false }));
+        let expiry = filter.get_array("$or").unwrap();
+        assert_eq!(expiry[0].as_document(), Some(&doc! { "expires_at": null }));
+        assert!(expiry[1].as_document().unwrap().contains_key("expires_at"));
+    }
+
+    #[test]
+    fn future_notifications_are_stored_unpublished() {
+        let scheduled = NewNotification {
+            to: "alice".to_string(),
+            send_at: Some((chrono::Utc::now() + chrono::Duration::hours(1)).to_rfc3339()),
+            ..Default::default()
+        };
+        assert_eq!(scheduled.to_document().get_bool("published"), Ok(false));
+
+        let immediate = NewNotification { to: "alice".to_string(), ..Default::default() };
+        assert_eq!(immediate.to_document().get_bool("published"), Ok(true));
+    }
+}
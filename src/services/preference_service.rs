@@ -0,0 +1,258 @@
+use chrono::{NaiveTime, Utc};
+use futures_util::stream::TryStreamExt;
+use mongodb::{
+    bson::{doc, Document},
+    options::UpdateOptions,
+    Database,
+};
+use std::collections::HashMap;
+use super::template_service::{validate_locale, LOCALE_FIELD};
+
+/// Clé des préférences appliquées aux types de notification non listés
+pub const DEFAULT_NOTIFICATION_TYPE: &str = "default";
+
+/// Canal de diffusion d'une notification
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Channel {
+    InApp,
+    Push,
+    Email,
+}
+
+impl Channel {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "in_app" => Some(Channel::InApp),
+            "push" => Some(Channel::Push),
+            "email" => Some(Channel::Email),
+            _ => None,
+        }
+    }
+}
+
+fn enabled() -> bool {
+    true
+}
+
+/// Canaux autorisés pour un type de notification (tous activés par défaut)
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ChannelPreferences {
+    #[serde(default = "enabled")]
+    pub in_app: bool,
+    #[serde(default = "enabled")]
+    pub push: bool,
+    #[serde(default = "enabled")]
+    pub email: bool,
+}
+
+impl ChannelPreferences {
+    fn allows(&self, channel: Channel) -> bool {
+        match channel {
+            Channel::InApp => self.in_app,
+            Channel::Push => self.push,
+            Channel::Email => self.email,
+        }
+    }
+}
+
+/// Plage horaire sans push ni email, exprimée dans le fuseau de l'utilisateur
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct QuietHours {
+    #[serde(default = "enabled")]
+    pub enabled: bool,
+    /// "HH:MM"
+    pub start: String,
+    /// "HH:MM", peut être le lendemain (ex: 22:00 → 07:00)
+    pub end: String,
+    /// Fuseau IANA, ex: "Europe/Paris"
+    pub timezone: String,
+}
+
+impl QuietHours {
+    pub fn validate(&self) -> Result<(), String> {
+        parse_time(&self.start).ok_or_else(|| format!("Heure invalide: {} (attendu HH:MM)", self.start))?;
+        parse_time(&self.end).ok_or_else(|| format!("Heure invalide: {} (attendu HH:MM)", self.end))?;
+        self.timezone
+            .parse::<chrono_tz::Tz>()
+            .map_err(|_| format!("Fuseau horaire inconnu: {}", self.timezone))?;
+        Ok(())
+    }
+
+    pub fn contains(&self, now: chrono::DateTime<Utc>) -> bool {
+        let (Some(start), Some(end), Ok(tz)) = (
+            parse_time(&self.start),
+            parse_time(&self.end),
+            self.timezone.parse::<chrono_tz::Tz>(),
+        ) else {
+            return false;
+        };
+        if !self.enabled || start == end {
+            return false;
+        }
+
+        let local = now.with_timezone(&tz).time();
+        if start < end {
+            local >= start && local < end
+        } else {
+            local >= start || local < end
+        }
+    }
+}
+
+fn parse_time(value: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(value, "%H:%M").ok()
+}
+
+/// Préférences d'un utilisateur (collection `user_preferences`)
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct UserPreferences {
+    #[serde(default)]
+    pub username: String,
+    /// Langue des notifications (`template_service::LOCALE_FIELD`)
+    #[serde(default)]
+    pub locale: Option<String>,
+    /// Canaux par type de notification ; la clé `default` s'applique aux autres types
+    #[serde(default)]
+    pub notifications: HashMap<String, ChannelPreferences>,
+    #[serde(default)]
+    pub quiet_hours: Option<QuietHours>,
+    #[serde(default)]
+    pub updated_at: Option<String>,
+}
+
+impl UserPreferences {
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(locale) = &self.locale {
+            validate_locale(locale)?;
+        }
+        if let Some(quiet_hours) = &self.quiet_hours {
+            quiet_hours.validate()?;
+        }
+        Ok(())
+    }
+
+    /// Indique si une notification de ce type peut être diffusée sur ce canal maintenant.
+    ///
+    /// Les heures calmes ne concernent que les canaux sortants (push, email).
+    pub fn allows(&self, notification_type: &str, channel: Channel) -> bool {
+        let channel_enabled = self
+            .notifications
+            .get(notification_type)
+            .or_else(|| self.notifications.get(DEFAULT_NOTIFICATION_TYPE))
+            .is_none_or(|preferences| preferences.allows(channel));
+        if !channel_enabled {
+            return false;
+        }
+
+        channel == Channel::InApp
+            || !self
+                .quiet_hours
+                .as_ref()
+                .is_some_and(|quiet_hours| quiet_hours.contains(Utc::now()))
+    }
+}
+
+pub struct PreferenceService;
+
+impl PreferenceService {
+    /// Préférences de l'utilisateur, valeurs par défaut s'il n'en a jamais enregistré
+    pub async fn get(db: &Database, username: &str) -> Result<UserPreferences, mongodb::error::Error> {
+        let preferences = db
+            .collection::<UserPreferences>("user_preferences")
+            .find_one(doc! { "username": username }, None)
+            .await?;
+
+        Ok(preferences.unwrap_or_else(|| UserPreferences {
+            username: username.to_string(),
+            ..Default::default()
+        }))
+    }
+
+    /// Préférences de plusieurs utilisateurs en une requête ; les absents ont les valeurs par défaut
+    pub async fn get_many(
+        db: &Database,
+        usernames: &[String],
+    ) -> Result<HashMap<String, UserPreferences>, mongodb::error::Error> {
+        let mut cursor = db
+            .collection::<UserPreferences>("user_preferences")
+            .find(doc! { "username": { "$in": usernames } }, None)
+            .await?;
+
+        let mut preferences = HashMap::new();
+        while let Some(user_preferences) = cursor.try_next().await? {
+            preferences.insert(user_preferences.username.clone(), user_preferences);
+        }
+        Ok(preferences)
+    }
+
+    /// Enregistre les préférences de notification sans toucher aux autres champs du document
+    pub async fn update(db: &Database, preferences: &UserPreferences) -> Result<UserPreferences, mongodb::error::Error> {
+        let now = chrono::Utc::now().to_rfc3339();
+        let set: Document = doc! {
+            LOCALE_FIELD: &preferences.locale,
+            "notifications": mongodb::bson::to_bson(&preferences.notifications)?,
+            "quiet_hours": mongodb::bson::to_bson(&preferences.quiet_hours)?,
+            "updated_at": &now,
+        };
+
+        db.collection::<Document>("user_preferences")
+            .update_one(
+                doc! { "username": &preferences.username },
+                doc! { "$set": set },
+                UpdateOptions::builder().upsert(true).build(),
+            )
+            .await?;
+
+        Ok(UserPreferences {
+            updated_at: Some(now),
+            ..preferences.clone()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn quiet_hours(start: &str, end: &str) -> QuietHours {
+        QuietHours {
+            enabled: true,
+            start: start.to_string(),
+            end: end.to_string(),
+            timezone: "Europe/Paris".to_string(),
+        }
+    }
+
+    /// Instant UTC correspondant à l'heure donnée à Paris en hiver (UTC+1)
+    fn paris_winter(hour: u32, minute: u32) -> chrono::DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 1, 15, hour, minute, 0).unwrap() - chrono::Duration::hours(1)
+    }
+
+    #[test]
+    fn overnight_range_spans_midnight_in_the_user_timezone() {
+        let quiet = quiet_hours("22:00", "07:00");
+        assert!(quiet.contains(paris_winter(23, 30)));
+        assert!(quiet.contains(paris_winter(6, 59)));
+        assert!(quiet.contains(paris_winter(22, 0)));
+        assert!(!quiet.contains(paris_winter(7, 0)));
+        assert!(!quiet.contains(paris_winter(12, 0)));
+    }
+
+    #[test]
+    fn daytime_range_excludes_its_end() {
+        let quiet = quiet_hours("12:00", "14:00");
+        assert!(quiet.contains(paris_winter(13, 0)));
+        assert!(!quiet.contains(paris_winter(14, 0)));
+        assert!(!quiet.contains(paris_winter(11, 59)));
+    }
+
+    #[test]
+    fn disabled_empty_or_invalid_ranges_never_apply() {
+        let now = paris_winter(23, 0);
+        assert!(!QuietHours { enabled: false, ..quiet_hours("22:00", "07:00") }.contains(now));
+        assert!(!quiet_hours("22:00", "22:00").contains(now));
+        assert!(!QuietHours { timezone: "Mars/Olympus".to_string(), ..quiet_hours("22:00", "07:00") }.contains(now));
+        assert!(quiet_hours("25:00", "07:00").validate().is_err());
+    }
+}
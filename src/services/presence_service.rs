@@ -0,0 +1,334 @@
+use chrono::{DateTime, Utc};
+use log::info;
+use rand::{distributions::Alphanumeric, Rng};
+use std::collections::{BTreeSet, HashMap};
+use std::sync::RwLock;
+use crate::config::PresenceConfig;
+use super::user_service::UserService;
+
+/// Présence d'un utilisateur vue par ce serveur
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct Presence {
+    pub username: String,
+    pub is_online: bool,
+    pub last_seen: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Default)]
+struct UserPresence {
+    /// Identifiant de connexion (onglet, appareil) → dernier signe de vie
+    connections: HashMap<String, DateTime<Utc>>,
+    last_seen: Option<DateTime<Utc>>,
+}
+
+/// Registre en mémoire des connexions, partagé par les handlers et la tâche de nettoyage
+#[derive(Default)]
+pub struct PresenceRegistry {
+    users: RwLock<HashMap<String, UserPresence>>,
+}
+
+impl PresenceRegistry {
+    /// Enregistre une connexion ; renvoie `true` si l'utilisateur vient de passer en ligne
+    fn connect(&self, username: &str, connection_id: &str) -> bool {
+        let now = Utc::now();
+        let mut users = self.users.write().unwrap_or_else(|e| e.into_inner());
+        let presence = users.entry(username.to_string()).or_default();
+        let was_online = !presence.connections.is_empty();
+        presence.connections.insert(connection_id.to_string(), now);
+        presence.last_seen = Some(now);
+        !was_online
+    }
+
+    /// Ferme une connexion, ou toutes sans identifiant ; renvoie `true` si l'utilisateur
+    /// vient de passer hors ligne. Un utilisateur inconnu de ce serveur est ignoré.
+    fn disconnect(&self, username: &str, connection_id: Option<&str>) -> bool {
+        let mut users = self.users.write().unwrap_or_else(|e| e.into_inner());
+        let Some(presence) = users.get_mut(username) else {
+            return false;
+        };
+        match connection_id {
+            Some(id) => {
+                presence.connections.remove(id);
+            }
+            None => presence.connections.clear(),
+        }
+        // Hors ligne, la dernière activité est conservée dans `compte_compte`
+        if presence.connections.is_empty() {
+            users.remove(username);
+            return true;
+        }
+        presence.last_seen = Some(Utc::now());
+        false
+    }
+
+    /// Signe de vie d'une connexion ; `false` si elle est inconnue (expirée ou autre serveur)
+    fn heartbeat(&self, username: &str, connection_id: &str) -> bool {
+        let now = Utc::now();
+        let mut users = self.users.write().unwrap_or_else(|e| e.into_inner());
+        let Some(presence) = users.get_mut(username) else {
+            return false;
+        };
+        let Some(connection) = presence.connections.get_mut(connection_id) else {
+            return false;
+        };
+        *connection = now;
+        presence.last_seen = Some(now);
+        true
+    }
+
+    fn get(&self, username: &str) -> Option<Presence> {
+        let users = self.users.read().unwrap_or_else(|e| e.into_inner());
+        users.get(username).map(|presence| Presence {
+            username: username.to_string(),
+            is_online: !presence.connections.is_empty(),
+            last_seen: presence.last_seen,
+        })
+    }
+
+    pub fn is_online(&self, username: &str) -> bool {
+        self.get(username).is_some_and(|presence| presence.is_online)
+    }
+
+    fn online_users(&self) -> Vec<String> {
+        let users = self.users.read().unwrap_or_else(|e| e.into_inner());
+        users
+            .iter()
+            .filter(|(_, presence)| !presence.connections.is_empty())
+            .map(|(username, _)| username.clone())
+            .collect()
+    }
+
+    /// Ferme les connexions sans signe de vie depuis `inactive` et renvoie les utilisateurs
+    /// passés hors ligne, retirés du registre
+    fn sweep(&self, inactive: chrono::Duration) -> Vec<String> {
+        let cutoff = Utc::now() - inactive;
+        let mut users = self.users.write().unwrap_or_else(|e| e.into_inner());
+        let mut disconnected = Vec::new();
+        users.retain(|username, presence| {
+            presence.connections.retain(|_, last_heartbeat| *last_heartbeat >= cutoff);
+            if presence.connections.is_empty() {
+                disconnected.push(username.clone());
+                return false;
+            }
+            true
+        });
+        disconnected
+    }
+}
+
+fn generate_connection_id() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(16)
+        .map(char::from)
+        .collect()
+}
+
+/// Présence des utilisateurs : registre local, répercuté dans `compte_compte` quand
+/// PostgreSQL est disponible (la colonne `is_online` est aussi lue par l'API principale).
+pub struct PresenceService;
+
+impl PresenceService {
+    /// Enregistre une connexion et renvoie son identifiant (généré s'il est absent)
+    pub async fn connect(
+        registry: &PresenceRegistry,
+        pg_client: Option<&tokio_postgres::Client>,
+        username: &str,
+        connection_id: Option<String>,
+    ) -> String {
+        let connection_id = connection_id.unwrap_or_else(generate_connection_id);
+        if registry.connect(username, &connection_id) {
+            Self::store(pg_client, username, true).await;
+        }
+        info!("✅ Connexion enregistrée: {} ({})", username, connection_id);
+        connection_id
+    }
+
+    pub async fn disconnect(
+        registry: &PresenceRegistry,
+        pg_client: Option<&tokio_postgres::Client>,
+        username: &str,
+        connection_id: Option<&str>,
+    ) {
+        if registry.disconnect(username, connection_id) {
+            Self::store(pg_client, username, false).await;
+        }
+        info!("👋 Déconnexion: {}", username);
+    }
+
+    /// Renvoie `false` si la connexion est inconnue : le client doit se reconnecter
+    pub fn heartbeat(registry: &PresenceRegistry, username: &str, connection_id: &str) -> bool {
+        registry.heartbeat(username, connection_id)
+    }
+
+    /// Présence d'un utilisateur : registre local, à défaut `compte_compte`
+    pub async fn status(
+        registry: &PresenceRegistry,
+        pg_client: Option<&tokio_postgres::Client>,
+        username: &str,
+    ) -> Presence {
+        let mut presence = Self::status_many(registry, pg_client, &[username.to_string()]).await;
+        presence.remove(username).unwrap_or(Presence {
+            username: username.to_string(),
+            is_online: false,
+            last_seen: None,
+        })
+    }
+
+    /// Présence de plusieurs utilisateurs, avec une seule requête PostgreSQL
+    pub async fn status_many(
+        registry: &PresenceRegistry,
+        pg_client: Option<&tokio_postgres::Client>,
+        usernames: &[String],
+    ) -> HashMap<String, Presence> {
+        let mut presence: HashMap<String, Presence> = usernames
+            .iter()
+            .filter_map(|username| registry.get(username).map(|p| (username.clone(), p)))
+            .collect();
+
+        // Un utilisateur connecté via la passerelle Node.js n'apparaît que dans PostgreSQL
+        let missing: Vec<String> = usernames
+            .iter()
+            .filter(|username| !presence.get(*username).is_some_and(|p| p.is_online))
+            .cloned()
+            .collect();
+        if let (Some(client), false) = (pg_client, missing.is_empty()) {
+            match UserService::get_presence(client, &missing).await {
+                Ok(stored) => {
+                    for (username, stored) in stored {
+                        let local_last_seen = presence.get(&username).and_then(|p| p.last_seen);
+                        presence.insert(
+                            username.clone(),
+                            Presence {
+                                username,
+                                is_online: stored.is_online,
+                                last_seen: stored.last_seen.max(local_last_seen),
+                            },
+                        );
+                    }
+                }
+                Err(e) => log::error!("Erreur PostgreSQL: {}", e),
+            }
+        }
+
+        presence
+    }
+
+    /// Utilisateurs en ligne sur ce serveur ou d'après `compte_compte`
+    pub async fn online_users(
+        registry: &PresenceRegistry,
+        pg_client: Option<&tokio_postgres::Client>,
+    ) -> Vec<String> {
+        let mut online: BTreeSet<String> = registry.online_users().into_iter().collect();
+        if let Some(client) = pg_client {
+            match UserService::get_online_users(client).await {
+                Ok(users) => online.extend(users),
+                Err(e) => log::error!("Erreur PostgreSQL: {}", e),
+            }
+        }
+        online.into_iter().collect()
+    }
+
+    pub async fn is_online(
+        registry: &PresenceRegistry,
+        pg_client: Option<&tokio_postgres::Client>,
+        username: &str,
+    ) -> bool {
+        if registry.is_online(username) {
+            return true;
+        }
+        let Some(client) = pg_client else {
+            return false;
+        };
+        UserService::is_user_online(client, username)
+            .await
+            .unwrap_or_else(|e| {
+                log::error!("Erreur PostgreSQL: {}", e);
+                false
+            })
+    }
+
+    /// Déconnecte les connexions inactives, localement et dans `compte_compte`
+    /// (équivalent de `cleanupInactiveConnections`).
+    pub async fn sweep(
+        registry: &PresenceRegistry,
+        pg_client: Option<&tokio_postgres::Client>,
+        config: &PresenceConfig,
+    ) {
+        let disconnected = registry.sweep(chrono::Duration::minutes(config.inactive_minutes));
+        if !disconnected.is_empty() {
+            info!("🧹 Nettoyage: {} connexions inactives fermées", disconnected.len());
+        }
+
+        let Some(client) = pg_client else {
+            return;
+        };
+        for username in &disconnected {
+            Self::store(Some(client), username, false).await;
+        }
+
+        // Les utilisateurs encore connectés ici ne doivent pas être nettoyés ci-dessous
+        let online = registry.online_users();
+        if !online.is_empty() {
+            if let Err(e) = UserService::touch_online(client, &online).await {
+                log::error!("Erreur PostgreSQL: {}", e);
+            }
+        }
+        if let Err(e) = UserService::cleanup_inactive_connections(client, config.inactive_minutes as i32).await {
+            log::error!("Erreur PostgreSQL: {}", e);
+        }
+    }
+
+    async fn store(pg_client: Option<&tokio_postgres::Client>, username: &str, is_online: bool) {
+        if let Some(client) = pg_client {
+            if let Err(e) = UserService::update_connection_status(client, username, is_online).await {
+                log::error!("Erreur PostgreSQL: {}", e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entries(registry: &PresenceRegistry) -> usize {
+        registry.users.read().unwrap().len()
+    }
+
+    #[test]
+    fn last_disconnect_removes_the_entry() {
+        let registry = PresenceRegistry::default();
+        assert!(registry.connect("alice", "tab1"));
+        assert!(!registry.connect("alice", "tab2"));
+
+        assert!(!registry.disconnect("alice", Some("tab1")));
+        assert!(registry.is_online("alice"));
+        assert!(registry.disconnect("alice", Some("tab2")));
+        assert!(!registry.is_online("alice"));
+        assert_eq!(entries(&registry), 0);
+    }
+
+    #[test]
+    fn disconnect_of_unknown_user_is_ignored() {
+        let registry = PresenceRegistry::default();
+        assert!(!registry.disconnect("bob", None));
+        assert!(!registry.disconnect("bob", Some("tab1")));
+        assert_eq!(entries(&registry), 0);
+    }
+
+    #[test]
+    fn sweep_removes_inactive_users() {
+        let registry = PresenceRegistry::default();
+        registry.connect("alice", "tab1");
+        registry.connect("bob", "tab1");
+        registry.users.write().unwrap().get_mut("alice").unwrap().connections.insert(
+            "tab1".to_string(),
+            Utc::now() - chrono::Duration::minutes(10),
+        );
+
+        assert_eq!(registry.sweep(chrono::Duration::minutes(5)), vec!["alice".to_string()]);
+        assert_eq!(registry.online_users(), vec!["bob".to_string()]);
+        assert_eq!(entries(&registry), 1);
+    }
+}
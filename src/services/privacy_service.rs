@@ -0,0 +1,155 @@
+use futures_util::stream::TryStreamExt;
+use mongodb::{
+    bson::{doc, Document},
+    options::{FindOneOptions, FindOptions, UpdateOptions},
+    Database,
+};
+use std::collections::HashMap;
+use super::presence_service::Presence;
+
+/// Confidentialité d'un utilisateur, enregistrée dans `user_preferences.privacy`
+#[derive(Debug, Clone, Copy, Default, serde::Serialize, serde::Deserialize)]
+pub struct PrivacySettings {
+    #[serde(default)]
+    pub hide_read_receipts: bool,
+    #[serde(default)]
+    pub hide_online_status: bool,
+    #[serde(default)]
+    pub hide_last_seen: bool,
+}
+
+/// Ce qu'un utilisateur peut voir d'un autre.
+///
+/// Réciprocité : qui masque une information ne voit pas non plus celle des autres.
+#[derive(Debug, Clone, Copy)]
+pub struct Visibility {
+    pub read_receipts: bool,
+    pub online_status: bool,
+    pub last_seen: bool,
+}
+
+impl Visibility {
+    pub fn between(viewer: &PrivacySettings, target: &PrivacySettings) -> Self {
+        Visibility {
+            read_receipts: !viewer.hide_read_receipts && !target.hide_read_receipts,
+            online_status: !viewer.hide_online_status && !target.hide_online_status,
+            last_seen: !viewer.hide_last_seen && !target.hide_last_seen,
+        }
+    }
+
+    /// Retire d'une présence ce que le lecteur ne doit pas voir
+    pub fn apply(&self, presence: Presence) -> Presence {
+        Presence {
+            is_online: presence.is_online && self.online_status,
+            last_seen: presence.last_seen.filter(|_| self.last_seen),
+            ..presence
+        }
+    }
+}
+
+fn privacy_of(document: &Document) -> PrivacySettings {
+    document
+        .get_document("privacy")
+        .ok()
+        .and_then(|privacy| mongodb::bson::from_document(privacy.clone()).ok())
+        .unwrap_or_default()
+}
+
+pub struct PrivacyService;
+
+impl PrivacyService {
+    pub async fn get(db: &Database, username: &str) -> Result<PrivacySettings, mongodb::error::Error> {
+        let document = db
+            .collection::<Document>("user_preferences")
+            .find_one(
+                doc! { "username": username },
+                FindOneOptions::builder().projection(doc! { "privacy": 1 }).build(),
+            )
+            .await?;
+        Ok(document.as_ref().map(privacy_of).unwrap_or_default())
+    }
+
+    /// Confidentialité de plusieurs utilisateurs en une requête ; les absents ont les valeurs par défaut
+    pub async fn get_many(
+        db: &Database,
+        usernames: &[String],
+    ) -> Result<HashMap<String, PrivacySettings>, mongodb::error::Error> {
+        let mut cursor = db
+            .collection::<Document>("user_preferences")
+            .find(
+                doc! { "username": { "$in": usernames } },
+                FindOptions::builder().projection(doc! { "username": 1, "privacy": 1 }).build(),
+            )
+            .await?;
+
+        let mut settings: HashMap<String, PrivacySettings> =
+            usernames.iter().map(|username| (username.clone(), PrivacySettings::default())).collect();
+        while let Some(document) = cursor.try_next().await? {
+            if let Ok(username) = document.get_str("username") {
+                settings.insert(username.to_string(), privacy_of(&document));
+            }
+        }
+        Ok(settings)
+    }
+
+    /// Visibilité entre deux utilisateurs
+    pub async fn visibility(db: &Database, viewer: &str, target: &str) -> Result<Visibility, mongodb::error::Error> {
+        let settings = Self::get_many(db, &[viewer.to_string(), target.to_string()]).await?;
+        let default = PrivacySettings::default();
+        Ok(Visibility::between(
+            settings.get(viewer).unwrap_or(&default),
+            settings.get(target).unwrap_or(&default),
+        ))
+    }
+
+    /// Enregistre la confidentialité sans toucher aux préférences de notification
+    pub async fn update(db: &Database, username: &str, settings: &PrivacySettings) -> Result<(), mongodb::error::Error> {
+        db.collection::<Document>("user_preferences")
+            .update_one(
+                doc! { "username": username },
+                doc! { "$set": {
+                    "privacy": mongodb::bson::to_bson(settings)?,
+                    "updated_at": chrono::Utc::now().to_rfc3339(),
+                } },
+                UpdateOptions::builder().upsert(true).build(),
+            )
+            .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn presence() -> Presence {
+        Presence {
+            username: "bob".to_string(),
+            is_online: true,
+            last_seen: Some(chrono::Utc::now()),
+        }
+    }
+
+    #[test]
+    fn hiding_a_setting_hides_it_both_ways() {
+        let hidden = PrivacySettings { hide_read_receipts: true, ..Default::default() };
+        let open = PrivacySettings::default();
+
+        assert!(!Visibility::between(&hidden, &open).read_receipts);
+        assert!(!Visibility::between(&open, &hidden).read_receipts);
+        assert!(Visibility::between(&open, &open).read_receipts);
+        assert!(Visibility::between(&hidden, &open).online_status);
+    }
+
+    #[test]
+    fn apply_strips_hidden_presence_fields() {
+        let target = PrivacySettings { hide_online_status: true, hide_last_seen: true, ..Default::default() };
+        let visible = Visibility::between(&PrivacySettings::default(), &target).apply(presence());
+        assert!(!visible.is_online);
+        assert!(visible.last_seen.is_none());
+
+        let visible = Visibility::between(&PrivacySettings::default(), &PrivacySettings::default()).apply(presence());
+        assert!(visible.is_online);
+        assert!(visible.last_seen.is_some());
+    }
+}
@@ -0,0 +1,332 @@
+use async_trait::async_trait;
+use jsonwebtoken::{Algorithm, EncodingKey, Header};
+use log::info;
+use serde_json::json;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use super::push_service::{validate_web_push_endpoint, PushError, PushMessage, PushProvider};
+
+/// Durée de réutilisation des jetons d'accès (APNs refuse plus d'un renouvellement toutes les 20 min)
+const PROVIDER_TOKEN_TTL: Duration = Duration::from_secs(50 * 60);
+
+/// Jeton d'accès mis en cache par un fournisseur
+#[derive(Default)]
+struct CachedToken(Mutex<Option<(String, Instant)>>);
+
+impl CachedToken {
+    async fn get_or_refresh<F, Fut>(&self, refresh: F) -> Result<String, PushError>
+    where
+        F: FnOnce() -> Fut,
+        Fut: std::future::Future<Output = Result<String, PushError>>,
+    {
+        let mut cached = self.0.lock().await;
+        if let Some((token, issued_at)) = cached.as_ref() {
+            if issued_at.elapsed() < PROVIDER_TOKEN_TTL {
+                return Ok(token.clone());
+            }
+        }
+        let token = refresh().await?;
+        *cached = Some((token.clone(), Instant::now()));
+        Ok(token)
+    }
+}
+
+fn read_key_file(path: &str) -> Result<Vec<u8>, String> {
+    std::fs::read(path).map_err(|e| format!("Lecture de {} impossible: {}", path, e))
+}
+
+fn unix_now() -> i64 {
+    chrono::Utc::now().timestamp()
+}
+
+fn failed(provider: &str, e: impl std::fmt::Display) -> PushError {
+    PushError::Failed(format!("{}: {}", provider, e))
+}
+
+/// Compte de service Google (fichier JSON téléchargé depuis la console Firebase)
+#[derive(serde::Deserialize)]
+struct ServiceAccount {
+    project_id: String,
+    client_email: String,
+    private_key: String,
+    token_uri: String,
+}
+
+/// Firebase Cloud Messaging, API HTTP v1
+pub struct FcmProvider {
+    client: reqwest::Client,
+    account: ServiceAccount,
+    key: EncodingKey,
+    access_token: CachedToken,
+}
+
+impl FcmProvider {
+    pub fn from_service_account_file(client: reqwest::Client, path: &str) -> Result<Self, String> {
+        let account: ServiceAccount = serde_json::from_slice(&read_key_file(path)?)
+            .map_err(|e| format!("Compte de service FCM invalide: {}", e))?;
+        let key = EncodingKey::from_rsa_pem(account.private_key.as_bytes())
+            .map_err(|e| format!("Clé privée FCM invalide: {}", e))?;
+
+        Ok(FcmProvider {
+            client,
+            account,
+            key,
+            access_token: CachedToken::default(),
+        })
+    }
+
+    /// Échange un JWT signé par le compte de service contre un jeton OAuth2
+    async fn fetch_access_token(&self) -> Result<String, PushError> {
+        let now = unix_now();
+        let claims = json!({
+            "iss": &self.account.client_email,
+            "scope": "https://www.googleapis.com/auth/firebase.messaging",
+            "aud": &self.account.token_uri,
+            "iat": now,
+            "exp": now + 3600,
+        });
+        let assertion = jsonwebtoken::encode(&Header::new(Algorithm::RS256), &claims, &self.key)
+            .map_err(|e| failed("fcm", e))?;
+
+        let response = self
+            .client
+            .post(&self.account.token_uri)
+            .form(&[
+                ("grant_type", "urn:ietf:params:oauth:grant-type:jwt-bearer"),
+                ("assertion", assertion.as_str()),
+            ])
+            .send()
+            .await
+            .map_err(|e| failed("fcm", e))?;
+        if !response.status().is_success() {
+            return Err(failed("fcm", format!("échange OAuth2 refusé ({})", response.status())));
+        }
+
+        let body: serde_json::Value = response.json().await.map_err(|e| failed("fcm", e))?;
+        body.get("access_token")
+            .and_then(|token| token.as_str())
+            .map(|token| token.to_string())
+            .ok_or_else(|| failed("fcm", "access_token absent de la réponse OAuth2"))
+    }
+}
+
+#[async_trait]
+impl PushProvider for FcmProvider {
+    fn name(&self) -> &'static str {
+        "fcm"
+    }
+
+    async fn send(&self, token: &str, message: &PushMessage) -> Result<(), PushError> {
+        let access_token = self.access_token.get_or_refresh(|| self.fetch_access_token()).await?;
+        let url = format!(
+            "https://fcm.googleapis.com/v1/projects/{}/messages:send",
+            self.account.project_id
+        );
+        let body = json!({
+            "message": {
+                "token": token,
+                "notification": { "title": &message.title, "body": &message.body },
+                "data": &message.data,
+            }
+        });
+
+        let response = self
+            .client
+            .post(url)
+            .bearer_auth(access_token)
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| failed("fcm", e))?;
+
+        let status = response.status();
+        if status.is_success() {
+            return Ok(());
+        }
+        let text = response.text().await.unwrap_or_default();
+        if status == reqwest::StatusCode::NOT_FOUND || text.contains("UNREGISTERED") {
+            return Err(PushError::InvalidToken);
+        }
+        Err(failed("fcm", format!("{} {}", status, text)))
+    }
+}
+
+/// Apple Push Notification service, authentification par clé .p8
+pub struct ApnsProvider {
+    client: reqwest::Client,
+    key: EncodingKey,
+    key_id: String,
+    team_id: String,
+    /// Bundle id de l'application
+    topic: String,
+    base_url: &'static str,
+    provider_token: CachedToken,
+}
+
+impl ApnsProvider {
+    pub fn from_key_file(
+        client: reqwest::Client,
+        path: &str,
+        key_id: String,
+        team_id: String,
+        topic: String,
+        sandbox: bool,
+    ) -> Result<Self, String> {
+        let key = EncodingKey::from_ec_pem(&read_key_file(path)?)
+            .map_err(|e| format!("Clé APNs invalide: {}", e))?;
+
+        Ok(ApnsProvider {
+            client,
+            key,
+            key_id,
+            team_id,
+            topic,
+            base_url: if sandbox {
+                "https://api.sandbox.push.apple.com"
+            } else {
+                "https://api.push.apple.com"
+            },
+            provider_token: CachedToken::default(),
+        })
+    }
+
+    async fn sign_provider_token(&self) -> Result<String, PushError> {
+        let mut header = Header::new(Algorithm::ES256);
+        header.kid = Some(self.key_id.clone());
+        let claims = json!({ "iss": &self.team_id, "iat": unix_now() });
+        jsonwebtoken::encode(&header, &claims, &self.key).map_err(|e| failed("apns", e))
+    }
+}
+
+#[async_trait]
+impl PushProvider for ApnsProvider {
+    fn name(&self) -> &'static str {
+        "apns"
+    }
+
+    async fn send(&self, token: &str, message: &PushMessage) -> Result<(), PushError> {
+        let provider_token = self.provider_token.get_or_refresh(|| self.sign_provider_token()).await?;
+        let mut body = json!({
+            "aps": {
+                "alert": { "title": &message.title, "body": &message.body },
+                "sound": "default",
+            }
+        });
+        for (key, value) in &message.data {
+            body[key] = json!(value);
+        }
+
+        let response = self
+            .client
+            .post(format!("{}/3/device/{}", self.base_url, token))
+            .bearer_auth(provider_token)
+            .header("apns-topic", &self.topic)
+            .header("apns-push-type", "alert")
+            .header("apns-priority", "10")
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| failed("apns", e))?;
+
+        let status = response.status();
+        if status.is_success() {
+            return Ok(());
+        }
+        let text = response.text().await.unwrap_or_default();
+        if status == reqwest::StatusCode::GONE || text.contains("BadDeviceToken") {
+            return Err(PushError::InvalidToken);
+        }
+        Err(failed("apns", format!("{} {}", status, text)))
+    }
+}
+
+/// Web Push (navigateurs) avec authentification VAPID.
+///
+/// Le push est envoyé sans contenu : le service worker récupère ensuite les
+/// notifications via l'API, ce qui évite le chiffrement du contenu (RFC 8291).
+pub struct WebPushProvider {
+    client: reqwest::Client,
+    key: EncodingKey,
+    /// Clé publique VAPID encodée en base64url, celle fournie aux navigateurs
+    public_key: String,
+    /// Contact de l'émetteur, ex: "mailto:contact@meet-voice.fr"
+    subject: String,
+}
+
+impl WebPushProvider {
+    pub fn from_key_file(
+        client: reqwest::Client,
+        path: &str,
+        public_key: String,
+        subject: String,
+    ) -> Result<Self, String> {
+        let key = EncodingKey::from_ec_pem(&read_key_file(path)?)
+            .map_err(|e| format!("Clé VAPID invalide: {}", e))?;
+        Ok(WebPushProvider {
+            client,
+            key,
+            public_key,
+            subject,
+        })
+    }
+}
+
+#[async_trait]
+impl PushProvider for WebPushProvider {
+    fn name(&self) -> &'static str {
+        "web_push"
+    }
+
+    /// Le jeton d'un abonnement Web Push est l'URL de son point de terminaison ; elle est
+    /// revérifiée avant l'envoi pour les jetons enregistrés avant le contrôle des hôtes
+    async fn send(&self, token: &str, _message: &PushMessage) -> Result<(), PushError> {
+        let endpoint = validate_web_push_endpoint(token).map_err(|_| PushError::InvalidToken)?;
+        let claims = json!({
+            "aud": endpoint.origin().ascii_serialization(),
+            "exp": unix_now() + 12 * 3600,
+            "sub": &self.subject,
+        });
+        let jwt = jsonwebtoken::encode(&Header::new(Algorithm::ES256), &claims, &self.key)
+            .map_err(|e| failed("web_push", e))?;
+
+        let response = self
+            .client
+            .post(endpoint)
+            .header("Authorization", format!("vapid t={}, k={}", jwt, self.public_key))
+            .header("TTL", "86400")
+            .header("Urgency", "high")
+            .header("Content-Length", "0")
+            .send()
+            .await
+            .map_err(|e| failed("web_push", e))?;
+
+        let status = response.status();
+        if status.is_success() {
+            return Ok(());
+        }
+        if status == reqwest::StatusCode::NOT_FOUND || status == reqwest::StatusCode::GONE {
+            return Err(PushError::InvalidToken);
+        }
+        Err(failed("web_push", status))
+    }
+}
+
+/// Fournisseur local pour le développement et les tests : journalise au lieu d'envoyer.
+///
+/// Les jetons commençant par `invalid` sont refusés pour simuler un appareil désinstallé.
+pub struct MockPushProvider;
+
+#[async_trait]
+impl PushProvider for MockPushProvider {
+    fn name(&self) -> &'static str {
+        "mock"
+    }
+
+    async fn send(&self, token: &str, message: &PushMessage) -> Result<(), PushError> {
+        if token.starts_with("invalid") {
+            return Err(PushError::InvalidToken);
+        }
+        info!("📲 [mock] push → {}: {} — {}", token, message.title, message.body);
+        Ok(())
+    }
+}
@@ -0,0 +1,432 @@
+use async_trait::async_trait;
+use futures_util::stream::TryStreamExt;
+use log::{info, warn};
+use mongodb::{
+    bson::{doc, Document},
+    options::{IndexOptions, UpdateOptions},
+    Database, IndexModel,
+};
+use std::collections::HashMap;
+use std::sync::Arc;
+use crate::config::PushConfig;
+use crate::events::AppEvent;
+use super::preference_service::{Channel, PreferenceService};
+use super::presence_service::{PresenceRegistry, PresenceService};
+use super::request_service::is_duplicate_key;
+use super::push_providers::{ApnsProvider, FcmProvider, MockPushProvider, WebPushProvider};
+
+/// Priorités de notification système déclenchant un push
+const PUSH_PRIORITIES: [&str; 2] = ["high", "urgent"];
+
+/// Type de notification utilisé pour les préférences des nouveaux messages
+pub const MESSAGE_NOTIFICATION_TYPE: &str = "message";
+
+/// Services de push des navigateurs : le serveur n'envoie de requête qu'à ces hôtes.
+/// Un préfixe `.` accepte tous les sous-domaines.
+const WEB_PUSH_HOSTS: [&str; 4] = [
+    "fcm.googleapis.com",
+    "updates.push.services.mozilla.com",
+    "web.push.apple.com",
+    ".notify.windows.com",
+];
+
+/// Longueur maximale du texte affiché dans un push
+const PUSH_BODY_MAX_CHARS: usize = 140;
+
+/// Service de push d'un appareil
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Platform {
+    Fcm,
+    Apns,
+    WebPush,
+}
+
+/// Appareil enregistré pour recevoir des push (collection `device_tokens`)
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct DeviceToken {
+    pub username: String,
+    /// Jeton FCM/APNs, ou URL d'abonnement pour Web Push
+    pub token: String,
+    pub platform: Platform,
+    #[serde(default)]
+    pub created_at: Option<String>,
+    #[serde(default)]
+    pub updated_at: Option<String>,
+}
+
+/// Vérifie qu'une URL d'abonnement Web Push pointe vers un service de push connu, en HTTPS
+pub fn validate_web_push_endpoint(endpoint: &str) -> Result<reqwest::Url, String> {
+    let url = reqwest::Url::parse(endpoint).map_err(|_| "URL d'abonnement Web Push invalide".to_string())?;
+    if url.scheme() != "https" || !url.username().is_empty() || url.password().is_some() {
+        return Err("L'abonnement Web Push doit être une URL https sans identifiants".to_string());
+    }
+    if url.port().is_some_and(|port| port != 443) {
+        return Err("Port non autorisé pour un abonnement Web Push".to_string());
+    }
+    let host = url.host_str().unwrap_or("").to_ascii_lowercase();
+    let known = WEB_PUSH_HOSTS.iter().any(|allowed| match allowed.strip_prefix('.') {
+        Some(domain) => host.strip_suffix(domain).is_some_and(|sub| sub.len() > 1 && sub.ends_with('.')),
+        None => host == *allowed,
+    });
+    if !known {
+        return Err(format!("Service Web Push non reconnu: {}", host));
+    }
+    Ok(url)
+}
+
+impl DeviceToken {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.token.trim().is_empty() {
+            return Err("Le jeton de l'appareil est requis".to_string());
+        }
+        if self.platform == Platform::WebPush {
+            validate_web_push_endpoint(&self.token)?;
+        }
+        Ok(())
+    }
+}
+
+/// Contenu d'un push
+#[derive(Debug, Clone)]
+pub struct PushMessage {
+    pub title: String,
+    pub body: String,
+    /// Données transmises à l'application (type, identifiant, lien)
+    pub data: HashMap<String, String>,
+}
+
+#[derive(Debug)]
+pub enum PushError {
+    /// Jeton expiré ou appareil désinscrit : il faut l'oublier
+    InvalidToken,
+    Failed(String),
+}
+
+impl std::fmt::Display for PushError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PushError::InvalidToken => write!(f, "Jeton de push invalide"),
+            PushError::Failed(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+/// Fournisseur de push pour une plateforme
+#[async_trait]
+pub trait PushProvider: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    async fn send(&self, token: &str, message: &PushMessage) -> Result<(), PushError>;
+}
+
+fn truncate(text: &str) -> String {
+    if text.chars().count() <= PUSH_BODY_MAX_CHARS {
+        return text.to_string();
+    }
+    let mut truncated: String = text.chars().take(PUSH_BODY_MAX_CHARS - 1).collect();
+    truncated.push('…');
+    truncated
+}
+
+pub struct PushService;
+
+impl PushService {
+    /// Un jeton n'appartient qu'à un seul utilisateur
+    pub async fn ensure_indexes(db: &Database) -> Result<(), mongodb::error::Error> {
+        let index = IndexModel::builder()
+            .keys(doc! { "token": 1 })
+            .options(IndexOptions::builder().name("unique_token".to_string()).unique(true).build())
+            .build();
+        db.collection::<Document>("device_tokens").create_index(index, None).await?;
+        Ok(())
+    }
+
+    /// Enregistre un appareil ou met à jour celui de l'utilisateur ; renvoie `false` si le
+    /// jeton est déjà enregistré par un autre utilisateur (il doit d'abord être désinscrit)
+    pub async fn register(db: &Database, device: &DeviceToken) -> Result<bool, mongodb::error::Error> {
+        let now = chrono::Utc::now().to_rfc3339();
+        let result = db
+            .collection::<Document>("device_tokens")
+            .update_one(
+                doc! { "token": &device.token, "username": &device.username },
+                doc! {
+                    "$set": {
+                        "platform": mongodb::bson::to_bson(&device.platform)?,
+                        "updated_at": &now,
+                    },
+                    "$setOnInsert": { "created_at": &now },
+                },
+                UpdateOptions::builder().upsert(true).build(),
+            )
+            .await;
+        match result {
+            Ok(_) => {
+                info!("📱 Appareil {:?} enregistré pour {}", device.platform, device.username);
+                Ok(true)
+            }
+            // L'index unique sur `token` refuse l'insertion d'un jeton d'un autre utilisateur
+            Err(e) if is_duplicate_key(&e) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub async fn list(db: &Database, username: &str) -> Result<Vec<DeviceToken>, mongodb::error::Error> {
+        let mut cursor = db
+            .collection::<DeviceToken>("device_tokens")
+            .find(doc! { "username": username }, None)
+            .await?;
+
+        let mut devices = Vec::new();
+        while let Some(device) = cursor.try_next().await? {
+            devices.push(device);
+        }
+        Ok(devices)
+    }
+
+    /// Renvoie `false` si le jeton n'appartient pas à l'utilisateur
+    pub async fn unregister(db: &Database, username: &str, token: &str) -> Result<bool, mongodb::error::Error> {
+        let result = db
+            .collection::<Document>("device_tokens")
+            .delete_one(doc! { "username": username, "token": token }, None)
+            .await?;
+        Ok(result.deleted_count > 0)
+    }
+}
+
+/// Envoie les push via le fournisseur de chaque plateforme configurée
+pub struct PushDispatcher {
+    providers: HashMap<Platform, Arc<dyn PushProvider>>,
+}
+
+impl PushDispatcher {
+    /// Configure les fournisseurs renseignés dans la section `push` de la configuration.
+    ///
+    /// `mock = true` remplace tous les fournisseurs par le fournisseur local.
+    pub fn from_config(config: &PushConfig) -> Self {
+        let mut providers: HashMap<Platform, Arc<dyn PushProvider>> = HashMap::new();
+
+        if config.mock {
+            let mock: Arc<dyn PushProvider> = Arc::new(MockPushProvider);
+            for platform in [Platform::Fcm, Platform::Apns, Platform::WebPush] {
+                providers.insert(platform, mock.clone());
+            }
+            return PushDispatcher { providers };
+        }
+
+        let client = match reqwest::Client::builder()
+            .timeout(std::time::Duration::from_secs(10))
+            .build()
+        {
+            Ok(client) => client,
+            Err(e) => {
+                log::error!("Client HTTP de push indisponible: {}", e);
+                return PushDispatcher { providers };
+            }
+        };
+
+        if let Some(path) = &config.fcm_service_account_file {
+            match FcmProvider::from_service_account_file(client.clone(), path) {
+                Ok(provider) => {
+                    providers.insert(Platform::Fcm, Arc::new(provider));
+                }
+                Err(e) => warn!("⚠️  FCM désactivé: {}", e),
+            }
+        }
+
+        if let Some(apns) = &config.apns {
+            match ApnsProvider::from_key_file(
+                client.clone(),
+                &apns.key_file,
+                apns.key_id.clone(),
+                apns.team_id.clone(),
+                apns.topic.clone(),
+                apns.sandbox,
+            ) {
+                Ok(provider) => {
+                    providers.insert(Platform::Apns, Arc::new(provider));
+                }
+                Err(e) => warn!("⚠️  APNs désactivé: {}", e),
+            }
+        }
+
+        if let Some(web_push) = &config.web_push {
+            match WebPushProvider::from_key_file(
+                client,
+                &web_push.private_key_file,
+                web_push.public_key.clone(),
+                web_push.subject.clone(),
+            ) {
+                Ok(provider) => {
+                    providers.insert(Platform::WebPush, Arc::new(provider));
+                }
+                Err(e) => warn!("⚠️  Web Push désactivé: {}", e),
+            }
+        }
+
+        PushDispatcher { providers }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.providers.is_empty()
+    }
+
+    /// Noms des fournisseurs actifs, pour le journal de démarrage
+    pub fn provider_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .providers
+            .iter()
+            .map(|(platform, provider)| format!("{:?}={}", platform, provider.name()))
+            .collect();
+        names.sort();
+        names
+    }
+
+    /// Envoie un push sur tous les appareils de l'utilisateur et renvoie le nombre d'envois réussis.
+    ///
+    /// Les jetons refusés par le fournisseur sont supprimés.
+    pub async fn send_to_user(
+        &self,
+        db: &Database,
+        username: &str,
+        message: &PushMessage,
+    ) -> Result<usize, mongodb::error::Error> {
+        let mut delivered = 0;
+        for device in PushService::list(db, username).await? {
+            let Some(provider) = self.providers.get(&device.platform) else {
+                continue;
+            };
+            match provider.send(&device.token, message).await {
+                Ok(()) => delivered += 1,
+                Err(PushError::InvalidToken) => {
+                    info!("🗑️  Jeton {} de {} invalide, supprimé", provider.name(), username);
+                    PushService::unregister(db, username, &device.token).await?;
+                }
+                Err(e) => log::error!("Erreur push {} pour {}: {}", provider.name(), username, e),
+            }
+        }
+        Ok(delivered)
+    }
+
+    /// Envoie le push correspondant à un événement : nouveau message pour un destinataire
+    /// hors ligne, ou notification système de priorité haute.
+    pub async fn handle_event(
+        &self,
+        db: &Database,
+        presence: &PresenceRegistry,
+        pg_client: Option<&tokio_postgres::Client>,
+        event: &AppEvent,
+    ) -> Result<(), mongodb::error::Error> {
+        let (to, notification_type, message) = match event {
+            AppEvent::MessageCreated(message_doc) => {
+                let from = message_doc.get_str("from").unwrap_or("");
+                let to = message_doc.get_str("to").unwrap_or("");
+                // Un destinataire en ligne reçoit déjà le message par la passerelle temps réel
+                if to.is_empty() || PresenceService::is_online(presence, pg_client, to).await {
+                    return Ok(());
+                }
+                let mut data = HashMap::from([
+                    ("type".to_string(), MESSAGE_NOTIFICATION_TYPE.to_string()),
+                    ("from".to_string(), from.to_string()),
+                ]);
+                if let Ok(oid) = message_doc.get_object_id("_id") {
+                    data.insert("id".to_string(), oid.to_hex());
+                }
+                let message = PushMessage {
+                    title: format!("Nouveau message de {}", from),
+                    body: truncate(message_doc.get_str("message").unwrap_or("")),
+                    data,
+                };
+                (to, MESSAGE_NOTIFICATION_TYPE, message)
+            }
+            AppEvent::NotificationPublished(notification_doc) => {
+                let priority = notification_doc.get_str("priority").unwrap_or("");
+                if !PUSH_PRIORITIES.contains(&priority) {
+                    return Ok(());
+                }
+                let notification_type = notification_doc.get_str("type").unwrap_or("");
+                let mut data = HashMap::from([("type".to_string(), notification_type.to_string())]);
+                if let Ok(oid) = notification_doc.get_object_id("_id") {
+                    data.insert("id".to_string(), oid.to_hex());
+                }
+                if let Ok(action_url) = notification_doc.get_str("action_url") {
+                    data.insert("action_url".to_string(), action_url.to_string());
+                }
+                let message = PushMessage {
+                    title: notification_doc.get_str("title").unwrap_or("").to_string(),
+                    body: truncate(notification_doc.get_str("message").unwrap_or("")),
+                    data,
+                };
+                (notification_doc.get_str("to").unwrap_or(""), notification_type, message)
+            }
+            AppEvent::MessagesRead { .. } | AppEvent::Typing { .. } => return Ok(()),
+        };
+
+        let preferences = PreferenceService::get(db, to).await?;
+        if !preferences.allows(notification_type, Channel::Push) {
+            return Ok(());
+        }
+
+        let delivered = self.send_to_user(db, to, &message).await?;
+        if delivered > 0 {
+            info!("📲 Push {} → {} ({} appareils)", notification_type, to, delivered);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mock_config() -> PushConfig {
+        PushConfig {
+            mock: true,
+            fcm_service_account_file: None,
+            apns: None,
+            web_push: None,
+        }
+    }
+
+    fn message() -> PushMessage {
+        PushMessage {
+            title: "alice".to_string(),
+            body: "Salut".to_string(),
+            data: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn mock_mode_serves_every_platform() {
+        let dispatcher = PushDispatcher::from_config(&mock_config());
+        assert!(dispatcher.is_enabled());
+        assert_eq!(dispatcher.provider_names(), vec!["Apns=mock", "Fcm=mock", "WebPush=mock"]);
+    }
+
+    #[tokio::test]
+    async fn mock_provider_rejects_invalid_tokens() {
+        let provider = MockPushProvider;
+        assert!(provider.send("device-token", &message()).await.is_ok());
+        assert!(matches!(provider.send("invalid-token", &message()).await, Err(PushError::InvalidToken)));
+    }
+
+    #[test]
+    fn web_push_endpoints_must_target_a_known_push_service() {
+        assert!(validate_web_push_endpoint("https://fcm.googleapis.com/fcm/send/abc").is_ok());
+        assert!(validate_web_push_endpoint("https://db5p.notify.windows.com/w/?token=abc").is_ok());
+        assert!(validate_web_push_endpoint("https://notify.windows.com/w/").is_err());
+        assert!(validate_web_push_endpoint("http://fcm.googleapis.com/fcm/send/abc").is_err());
+        assert!(validate_web_push_endpoint("https://fcm.googleapis.com:8443/fcm/send/abc").is_err());
+        assert!(validate_web_push_endpoint("https://user@fcm.googleapis.com/fcm/send/abc").is_err());
+        assert!(validate_web_push_endpoint("https://169.254.169.254/latest/meta-data").is_err());
+        assert!(validate_web_push_endpoint("https://fcm.googleapis.com.evil.com/").is_err());
+    }
+
+    #[test]
+    fn long_bodies_are_truncated_with_an_ellipsis() {
+        let truncated = truncate(&"é".repeat(PUSH_BODY_MAX_CHARS + 10));
+        assert_eq!(truncated.chars().count(), PUSH_BODY_MAX_CHARS);
+        assert!(truncated.ends_with('…'));
+        assert_eq!(truncate("court"), "court");
+    }
+}
@@ -0,0 +1,170 @@
+use mongodb::bson::{doc, Document};
+use super::request_service::{NoExtra, RequestDescriptor, RequestKind, RequestStatus};
+
+// ============ ACCÈS À UN GROUPE ============
+
+pub struct GroupAccess;
+
+#[derive(serde::Deserialize)]
+pub struct CreateGroupAccessRequest {
+    pub requester_username: String,
+    pub group_id: String,
+    pub group_name: String,
+    pub group_owner: String,
+}
+
+impl RequestKind for GroupAccess {
+    const DESCRIPTOR: RequestDescriptor = RequestDescriptor {
+        kind: "group_access",
+        path: "group-access",
+        collection: "group_access_requests",
+        owner_field: "group_owner",
+        subject_fields: &["group_id"],
+        created_message: "Demande d'accès au groupe créée",
+    };
+
+    type Payload = CreateGroupAccessRequest;
+    type ResponseExtra = NoExtra;
+
+    fn requester(payload: &Self::Payload) -> &str {
+        &payload.requester_username
+    }
+
+    fn owner(payload: &Self::Payload) -> &str {
+        &payload.group_owner
+    }
+
+    fn extra_fields(payload: &Self::Payload) -> Document {
+        doc! {
+            "group_id": &payload.group_id,
+            "group_name": &payload.group_name,
+        }
+    }
+
+    fn subject(request_doc: &Document, locale: &str) -> String {
+        let group_name = request_doc.get_str("group_name").unwrap_or("");
+        match locale {
+            "en" => format!("access to the group \"{}\"", group_name),
+            "es" => format!("el acceso al grupo «{}»", group_name),
+            _ => format!("l'accès au groupe « {} »", group_name),
+        }
+    }
+}
+
+// ============ PHOTOS PRIVÉES ============
+
+pub struct PhotoPermission;
+
+#[derive(serde::Deserialize)]
+pub struct CreatePhotoPermissionRequest {
+    pub requester_username: String,
+    pub target_username: String,
+}
+
+#[derive(serde::Deserialize, Default)]
+pub struct PhotoPermissionResponse {
+    /// Date de fin d'accès aux photos (RFC 3339), illimité si absent
+    pub permission_expires_at: Option<String>,
+}
+
+impl RequestKind for PhotoPermission {
+    const DESCRIPTOR: RequestDescriptor = RequestDescriptor {
+        kind: "photo_permission",
+        path: "private-photos-permission",
+        collection: "photo_permission_requests",
+        owner_field: "target_username",
+        // La cible est le propriétaire lui-même : un seul couple demandeur/cible en attente
+        subject_fields: &[],
+        created_message: "Demande de permission photos créée",
+    };
+
+    type Payload = CreatePhotoPermissionRequest;
+    type ResponseExtra = PhotoPermissionResponse;
+
+    fn requester(payload: &Self::Payload) -> &str {
+        &payload.requester_username
+    }
+
+    fn owner(payload: &Self::Payload) -> &str {
+        &payload.target_username
+    }
+
+    fn extra_fields(_payload: &Self::Payload) -> Document {
+        doc! { "permission_expires_at": null }
+    }
+
+    fn subject(request_doc: &Document, locale: &str) -> String {
+        let target = request_doc.get_str("target_username").unwrap_or("");
+        match locale {
+            "en" => format!("access to {}'s private photos", target),
+            "es" => format!("el acceso a las fotos privadas de {}", target),
+            _ => format!("l'accès aux photos privées de {}", target),
+        }
+    }
+
+    fn response_fields(extra: &Self::ResponseExtra, status: RequestStatus) -> Document {
+        match (status, &extra.permission_expires_at) {
+            (RequestStatus::Approved, Some(expires_at)) => doc! { "permission_expires_at": expires_at },
+            _ => Document::new(),
+        }
+    }
+}
+
+// ============ PARTICIPATION À UN ÉVÉNEMENT ============
+
+pub struct EventParticipation;
+
+#[derive(serde::Deserialize)]
+pub struct CreateEventParticipationRequest {
+    pub requester_username: String,
+    pub event_id: String,
+    pub event_name: String,
+    pub event_creator: String,
+    pub participation_role: Option<String>,
+}
+
+impl RequestKind for EventParticipation {
+    const DESCRIPTOR: RequestDescriptor = RequestDescriptor {
+        kind: "event_participation",
+        path: "event-participation",
+        collection: "event_participation_requests",
+        owner_field: "event_creator",
+        subject_fields: &["event_id"],
+        created_message: "Demande de participation créée",
+    };
+
+    type Payload = CreateEventParticipationRequest;
+    type ResponseExtra = NoExtra;
+
+    fn requester(payload: &Self::Payload) -> &str {
+        &payload.requester_username
+    }
+
+    fn owner(payload: &Self::Payload) -> &str {
+        &payload.event_creator
+    }
+
+    fn extra_fields(payload: &Self::Payload) -> Document {
+        doc! {
+            "event_id": &payload.event_id,
+            "event_name": &payload.event_name,
+            "participation_role": payload.participation_role.as_deref().unwrap_or("participant"),
+        }
+    }
+
+    fn subject(request_doc: &Document, locale: &str) -> String {
+        let event_name = request_doc.get_str("event_name").unwrap_or("");
+        match locale {
+            "en" => format!("joining the event \"{}\"", event_name),
+            "es" => format!("la participación en el evento «{}»", event_name),
+            _ => format!("la participation à l'événement « {} »", event_name),
+        }
+    }
+}
+
+/// Tous les types de demandes, pour la boîte de réception unifiée
+pub const REQUEST_KINDS: [RequestDescriptor; 3] = [
+    GroupAccess::DESCRIPTOR,
+    PhotoPermission::DESCRIPTOR,
+    EventParticipation::DESCRIPTOR,
+];
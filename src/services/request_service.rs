@@ -0,0 +1,759 @@
+use futures_util::stream::TryStreamExt;
+use log::info;
+use mongodb::{
+    bson::{doc, oid::ObjectId, Bson, Document},
+    error::{ErrorKind, WriteFailure},
+    options::{FindOptions, IndexOptions},
+    Database, IndexModel,
+};
+use serde::de::DeserializeOwned;
+use std::collections::HashMap;
+use crate::events::event_data;
+use super::notification_service::{NewNotification, NotificationService};
+use super::template_service::{NotificationTemplate, RenderedTemplate, TemplateService, DEFAULT_LOCALE};
+use super::webhook_service::WebhookService;
+use std::fmt;
+
+/// Statuts possibles d'une demande
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RequestStatus {
+    Pending,
+    Approved,
+    Rejected,
+    Cancelled,
+    Expired,
+}
+
+impl RequestStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RequestStatus::Pending => "pending",
+            RequestStatus::Approved => "approved",
+            RequestStatus::Rejected => "rejected",
+            RequestStatus::Cancelled => "cancelled",
+            RequestStatus::Expired => "expired",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "pending" => Some(RequestStatus::Pending),
+            "approved" => Some(RequestStatus::Approved),
+            "rejected" => Some(RequestStatus::Rejected),
+            "cancelled" => Some(RequestStatus::Cancelled),
+            "expired" => Some(RequestStatus::Expired),
+            _ => None,
+        }
+    }
+}
+
+/// Qui est à l'origine d'un changement de statut
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RequestActor {
+    /// Celui qui reçoit la demande (propriétaire du groupe, de l'événement, des photos)
+    Owner,
+    Requester,
+    /// Tâches automatiques (expiration)
+    System,
+}
+
+/// Changement de statut autorisé pour un type de demande
+pub struct Transition {
+    pub from: RequestStatus,
+    pub to: RequestStatus,
+    pub actor: RequestActor,
+}
+
+pub const DEFAULT_TRANSITIONS: &[Transition] = &[
+    Transition { from: RequestStatus::Pending, to: RequestStatus::Approved, actor: RequestActor::Owner },
+    Transition { from: RequestStatus::Pending, to: RequestStatus::Rejected, actor: RequestActor::Owner },
+    Transition { from: RequestStatus::Pending, to: RequestStatus::Cancelled, actor: RequestActor::Requester },
+    Transition { from: RequestStatus::Pending, to: RequestStatus::Expired, actor: RequestActor::System },
+];
+
+/// Informations statiques d'un type de demande
+pub struct RequestDescriptor {
+    /// Discriminant exposé au client
+    pub kind: &'static str,
+    /// Segment d'URL sous /api/requests/
+    pub path: &'static str,
+    pub collection: &'static str,
+    /// Champ désignant celui qui doit répondre à la demande
+    pub owner_field: &'static str,
+    /// Champs identifiant l'objet de la demande (en plus du demandeur et du propriétaire) :
+    /// une seule demande en attente par combinaison, garantie par un index unique partiel
+    pub subject_fields: &'static [&'static str],
+    /// Message renvoyé à la création
+    pub created_message: &'static str,
+}
+
+/// Type de demande géré par le moteur générique.
+///
+/// Ajouter un nouveau type (ex: permission d'appel vocal) revient à implémenter
+/// ce trait puis à l'enregistrer dans `main.rs` via `handlers::requests::configure`.
+pub trait RequestKind: 'static {
+    const DESCRIPTOR: RequestDescriptor;
+    const TRANSITIONS: &'static [Transition] = DEFAULT_TRANSITIONS;
+
+    /// Corps de la requête de création
+    type Payload: DeserializeOwned + 'static;
+    /// Champs complémentaires acceptés lors de la réponse du propriétaire
+    type ResponseExtra: DeserializeOwned + 'static;
+
+    fn requester(payload: &Self::Payload) -> &str;
+    fn owner(payload: &Self::Payload) -> &str;
+
+    /// Champs propres au type, hors demandeur et propriétaire
+    fn extra_fields(payload: &Self::Payload) -> Document;
+
+    /// Objet de la demande dans la langue du destinataire, utilisé dans les notifications
+    /// (ex: "l'accès au groupe « X »")
+    fn subject(request_doc: &Document, locale: &str) -> String;
+
+    /// Champs ajoutés au document lors d'une réponse du propriétaire
+    fn response_fields(_extra: &Self::ResponseExtra, _status: RequestStatus) -> Document {
+        Document::new()
+    }
+}
+
+/// Aucun champ complémentaire à la réponse
+#[derive(serde::Deserialize, Default)]
+pub struct NoExtra {}
+
+#[derive(Debug)]
+pub enum RequestError {
+    NotFound,
+    Invalid(String),
+    Forbidden(String),
+    Conflict(String),
+    Database(mongodb::error::Error),
+}
+
+impl fmt::Display for RequestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RequestError::NotFound => write!(f, "Demande introuvable"),
+            RequestError::Invalid(msg) | RequestError::Forbidden(msg) | RequestError::Conflict(msg) => {
+                write!(f, "{}", msg)
+            }
+            RequestError::Database(e) => write!(f, "Erreur: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for RequestError {}
+
+impl From<mongodb::error::Error> for RequestError {
+    fn from(e: mongodb::error::Error) -> Self {
+        RequestError::Database(e)
+    }
+}
+
+/// Sens de lecture des demandes pour un utilisateur
+#[derive(Clone, Copy)]
+pub enum RequestDirection {
+    /// Demandes reçues (l'utilisateur est le propriétaire)
+    Incoming,
+    /// Demandes envoyées
+    Outgoing,
+}
+
+impl RequestDirection {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RequestDirection::Incoming => "incoming",
+            RequestDirection::Outgoing => "outgoing",
+        }
+    }
+
+    pub fn user_field(&self, descriptor: &RequestDescriptor) -> &'static str {
+        match self {
+            RequestDirection::Incoming => descriptor.owner_field,
+            RequestDirection::Outgoing => "requester_username",
+        }
+    }
+}
+
+/// Position de pagination : dernière demande reçue par le client (timestamp puis identifiant,
+/// pour ne perdre aucune demande partageant le même timestamp)
+#[derive(Debug, Clone)]
+pub struct ListCursor {
+    pub timestamp: String,
+    pub id: Option<ObjectId>,
+}
+
+impl ListCursor {
+    /// `<timestamp>_<id>`, ou un timestamp seul
+    pub fn parse(value: &str) -> Self {
+        match value.rsplit_once('_').and_then(|(timestamp, id)| Some((timestamp, ObjectId::parse_str(id).ok()?))) {
+            Some((timestamp, id)) => ListCursor { timestamp: timestamp.to_string(), id: Some(id) },
+            None => ListCursor { timestamp: value.to_string(), id: None },
+        }
+    }
+
+    /// Curseur désignant une demande
+    pub fn encode(request_doc: &Document) -> String {
+        let timestamp = request_doc.get_str("timestamp").unwrap_or("");
+        match request_doc.get_object_id("_id") {
+            Ok(id) => format!("{}_{}", timestamp, id.to_hex()),
+            Err(_) => timestamp.to_string(),
+        }
+    }
+}
+
+/// Critères de liste des demandes
+#[derive(Debug, Default)]
+pub struct ListFilter<'a> {
+    pub status: Option<&'a str>,
+    /// Timestamp avant lequel une demande en attente est considérée expirée
+    pub expiry_cutoff: Option<&'a str>,
+    pub before: Option<&'a ListCursor>,
+}
+
+/// Filtre MongoDB : une demande en attente dont le délai est dépassé compte comme expirée,
+/// même si la tâche d'expiration n'est pas encore passée
+fn list_filter(descriptor: &RequestDescriptor, username: &str, direction: RequestDirection, filter: &ListFilter) -> Document {
+    let pending = RequestStatus::Pending.as_str();
+    let mut clauses = vec![doc! { direction.user_field(descriptor): username }];
+
+    match (filter.status.map(|s| (s, RequestStatus::parse(s))), filter.expiry_cutoff) {
+        (Some((_, Some(RequestStatus::Pending))), Some(cutoff)) => {
+            clauses.push(doc! { "status": pending, "timestamp": { "$gte": cutoff } });
+        }
+        (Some((_, Some(RequestStatus::Expired))), Some(cutoff)) => clauses.push(doc! {
+            "$or": [
+                { "status": RequestStatus::Expired.as_str() },
+                { "status": pending, "timestamp": { "$lt": cutoff } },
+            ]
+        }),
+        (Some((status, _)), _) => clauses.push(doc! { "status": status }),
+        (None, _) => {}
+    }
+
+    if let Some(cursor) = filter.before {
+        clauses.push(match cursor.id {
+            Some(id) => doc! {
+                "$or": [
+                    { "timestamp": { "$lt": &cursor.timestamp } },
+                    { "timestamp": &cursor.timestamp, "_id": { "$lt": id } },
+                ]
+            },
+            None => doc! { "timestamp": { "$lt": &cursor.timestamp } },
+        });
+    }
+
+    doc! { "$and": clauses }
+}
+
+/// Valeur de `created_by` des notifications émises par le moteur de demandes
+const REQUEST_NOTIFICATION_CREATOR: &str = "messagerie";
+
+fn duplicate_pending() -> RequestError {
+    RequestError::Conflict("Une demande identique est déjà en attente".to_string())
+}
+
+/// Violation d'un index unique
+pub fn is_duplicate_key(e: &mongodb::error::Error) -> bool {
+    matches!(
+        e.kind.as_ref(),
+        ErrorKind::Write(WriteFailure::WriteError(write_error)) if write_error.code == 11000
+    )
+}
+
+pub struct RequestService;
+
+impl RequestService {
+    /// Crée l'index unique partiel des demandes en attente de chaque type
+    pub async fn ensure_indexes(db: &Database, descriptors: &[RequestDescriptor]) -> Result<(), mongodb::error::Error> {
+        for descriptor in descriptors {
+            let mut keys = doc! { "requester_username": 1, descriptor.owner_field: 1 };
+            for field in descriptor.subject_fields {
+                keys.insert(*field, 1);
+            }
+            let index = IndexModel::builder()
+                .keys(keys)
+                .options(
+                    IndexOptions::builder()
+                        .name("unique_pending".to_string())
+                        .unique(true)
+                        .partial_filter_expression(doc! { "status": RequestStatus::Pending.as_str() })
+                        .build(),
+                )
+                .build();
+            db.collection::<Document>(descriptor.collection).create_index(index, None).await?;
+        }
+        Ok(())
+    }
+
+    /// Crée une demande en attente
+    pub async fn create<K: RequestKind>(
+        db: &Database,
+        payload: &K::Payload,
+    ) -> Result<Document, RequestError> {
+        let descriptor = &K::DESCRIPTOR;
+        let requester = K::requester(payload);
+        let owner = K::owner(payload);
+
+        if requester.is_empty() || owner.is_empty() {
+            return Err(RequestError::Invalid("Demandeur et destinataire requis".to_string()));
+        }
+        if requester == owner {
+            return Err(RequestError::Invalid("Impossible de s'adresser une demande à soi-même".to_string()));
+        }
+
+        let collection = db.collection::<Document>(descriptor.collection);
+
+        let now = chrono::Utc::now().to_rfc3339();
+        let mut request_doc = doc! {
+            "requester_username": requester,
+            descriptor.owner_field: owner,
+        };
+        request_doc.extend(K::extra_fields(payload));
+
+        let mut duplicate_filter = doc! {
+            "requester_username": requester,
+            descriptor.owner_field: owner,
+            "status": RequestStatus::Pending.as_str(),
+        };
+        for field in descriptor.subject_fields {
+            duplicate_filter.insert(*field, request_doc.get(*field).cloned().unwrap_or(Bson::Null));
+        }
+        if collection.find_one(duplicate_filter, None).await?.is_some() {
+            return Err(duplicate_pending());
+        }
+        request_doc.extend(doc! {
+            "status": RequestStatus::Pending.as_str(),
+            "timestamp": &now,
+            "response_timestamp": null,
+            "response_message": null,
+        });
+
+        // L'index unique partiel départage deux créations simultanées
+        let result = match collection.insert_one(&request_doc, None).await {
+            Ok(result) => result,
+            Err(e) if is_duplicate_key(&e) => return Err(duplicate_pending()),
+            Err(e) => return Err(e.into()),
+        };
+        request_doc.insert("_id", result.inserted_id);
+        info!("📨 Demande {} créée: {} → {}", descriptor.kind, requester, owner);
+
+        Self::notify::<K>(db, &request_doc).await;
+
+        Ok(request_doc)
+    }
+
+    /// Récupère une demande par son identifiant
+    pub async fn get<K: RequestKind>(db: &Database, id: &str) -> Result<Document, RequestError> {
+        let oid = ObjectId::parse_str(id).map_err(|_| RequestError::NotFound)?;
+        db.collection::<Document>(K::DESCRIPTOR.collection)
+            .find_one(doc! { "_id": oid }, None)
+            .await?
+            .ok_or(RequestError::NotFound)
+    }
+
+    /// Liste les demandes d'un type, triées du plus récent au plus ancien
+    pub async fn list(
+        db: &Database,
+        descriptor: &RequestDescriptor,
+        username: &str,
+        direction: RequestDirection,
+        filter: &ListFilter<'_>,
+        limit: i64,
+    ) -> Result<Vec<Document>, RequestError> {
+        let filter = list_filter(descriptor, username, direction, filter);
+
+        let options = FindOptions::builder()
+            .sort(doc! { "timestamp": -1, "_id": -1 })
+            .limit(limit)
+            .build();
+
+        let mut cursor = db
+            .collection::<Document>(descriptor.collection)
+            .find(filter, options)
+            .await?;
+
+        let mut requests = Vec::new();
+        while let Some(request_doc) = cursor.try_next().await? {
+            requests.push(request_doc);
+        }
+
+        Ok(requests)
+    }
+
+    /// Compte les demandes en attente d'un type pour un utilisateur.
+    ///
+    /// Les demandes antérieures à `expiry_cutoff` sont exclues même si la tâche
+    /// d'expiration n'est pas encore passée.
+    pub async fn count_pending(
+        db: &Database,
+        descriptor: &RequestDescriptor,
+        username: &str,
+        direction: RequestDirection,
+        expiry_cutoff: Option<&str>,
+    ) -> Result<u64, RequestError> {
+        let mut filter = doc! {
+            direction.user_field(descriptor): username,
+            "status": RequestStatus::Pending.as_str(),
+        };
+        if let Some(cutoff) = expiry_cutoff {
+            filter.insert("timestamp", doc! { "$gte": cutoff });
+        }
+        Ok(db
+            .collection::<Document>(descriptor.collection)
+            .count_documents(filter, None)
+            .await?)
+    }
+
+    /// Applique un changement de statut après vérification de la table de transitions.
+    ///
+    /// `actor_username` est ignoré pour l'acteur `System`.
+    pub async fn transition<K: RequestKind>(
+        db: &Database,
+        id: &str,
+        actor: RequestActor,
+        actor_username: &str,
+        to: RequestStatus,
+        response_message: Option<&str>,
+        extra_fields: Document,
+    ) -> Result<Document, RequestError> {
+        let descriptor = &K::DESCRIPTOR;
+        let request_doc = Self::get::<K>(db, id).await?;
+
+        let expected_user = match actor {
+            RequestActor::Owner => request_doc.get_str(descriptor.owner_field).ok(),
+            RequestActor::Requester => request_doc.get_str("requester_username").ok(),
+            RequestActor::System => None,
+        };
+        if actor != RequestActor::System && expected_user != Some(actor_username) {
+            return Err(RequestError::Forbidden("Action non autorisée sur cette demande".to_string()));
+        }
+
+        let current = request_doc
+            .get_str("status")
+            .ok()
+            .and_then(RequestStatus::parse)
+            .ok_or_else(|| RequestError::Invalid("Statut de demande inconnu".to_string()))?;
+
+        let allowed = K::TRANSITIONS
+            .iter()
+            .any(|t| t.from == current && t.to == to && t.actor == actor);
+        if !allowed {
+            return Err(RequestError::Conflict(format!(
+                "Transition {} → {} non autorisée",
+                current.as_str(),
+                to.as_str()
+            )));
+        }
+
+        let now = chrono::Utc::now().to_rfc3339();
+        let mut set = doc! {
+            "status": to.as_str(),
+            "response_timestamp": &now,
+        };
+        if let Some(message) = response_message {
+            set.insert("response_message", message);
+        }
+        set.extend(extra_fields);
+
+        // Le filtre sur le statut courant évite d'écraser une réponse concurrente
+        let filter = doc! { "_id": request_doc.get("_id").cloned().unwrap_or(Bson::Null), "status": current.as_str() };
+        let result = db
+            .collection::<Document>(descriptor.collection)
+            .update_one(filter, doc! { "$set": &set }, None)
+            .await?;
+        if result.modified_count == 0 {
+            return Err(RequestError::Conflict("La demande a été modifiée entre-temps".to_string()));
+        }
+
+        info!("🔁 Demande {} {}: {} → {}", descriptor.kind, id, current.as_str(), to.as_str());
+
+        let mut updated = request_doc;
+        updated.extend(set);
+
+        Self::notify::<K>(db, &updated).await;
+
+        let mut data = event_data(updated.clone());
+        data.insert("kind", descriptor.kind);
+        if let Err(e) = WebhookService::enqueue(db, &format!("request.{}", to.as_str()), data).await {
+            log::error!("Erreur webhook demande {}: {}", descriptor.kind, e);
+        }
+
+        Ok(updated)
+    }
+
+    /// Passe en `expired` les demandes en attente plus anciennes que `ttl`
+    pub async fn expire_stale<K: RequestKind>(
+        db: &Database,
+        ttl: chrono::Duration,
+    ) -> Result<Vec<Document>, RequestError> {
+        let cutoff = (chrono::Utc::now() - ttl).to_rfc3339();
+        let filter = doc! {
+            "status": RequestStatus::Pending.as_str(),
+            "timestamp": { "$lt": &cutoff },
+        };
+
+        let mut cursor = db
+            .collection::<Document>(K::DESCRIPTOR.collection)
+            .find(filter, None)
+            .await?;
+
+        let mut stale_ids = Vec::new();
+        while let Some(request_doc) = cursor.try_next().await? {
+            if let Ok(oid) = request_doc.get_object_id("_id") {
+                stale_ids.push(oid.to_hex());
+            }
+        }
+
+        let mut expired = Vec::new();
+        for id in stale_ids {
+            match Self::transition::<K>(db, &id, RequestActor::System, "", RequestStatus::Expired, None, Document::new()).await {
+                Ok(request_doc) => expired.push(request_doc),
+                // Annulée ou traitée entre-temps
+                Err(RequestError::Conflict(_)) => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        if !expired.is_empty() {
+            info!("⌛ {} demandes {} expirées", expired.len(), K::DESCRIPTOR.kind);
+        }
+
+        Ok(expired)
+    }
+
+    /// Prévient la partie concernée du nouveau statut de la demande.
+    ///
+    /// Un échec d'envoi est journalisé sans annuler l'opération sur la demande.
+    async fn notify<K: RequestKind>(db: &Database, request_doc: &Document) {
+        let descriptor = &K::DESCRIPTOR;
+        let Some(status) = request_doc.get_str("status").ok().and_then(RequestStatus::parse) else {
+            return;
+        };
+        let requester = request_doc.get_str("requester_username").unwrap_or("");
+        let owner = request_doc.get_str(descriptor.owner_field).unwrap_or("");
+
+        let (to, priority) = match status {
+            RequestStatus::Pending => (owner, "normal"),
+            RequestStatus::Approved => (requester, "high"),
+            RequestStatus::Rejected => (requester, "normal"),
+            RequestStatus::Expired => (requester, "low"),
+            // Le demandeur est à l'origine de l'annulation : personne à prévenir
+            RequestStatus::Cancelled => return,
+        };
+        let notification_type = match status {
+            RequestStatus::Pending => "request_created".to_string(),
+            other => format!("request_{}", other.as_str()),
+        };
+
+        let locale = match TemplateService::recipient_locale(db, to).await {
+            Ok(locale) => locale,
+            Err(e) => {
+                log::error!("Erreur MongoDB: {}", e);
+                DEFAULT_LOCALE.to_string()
+            }
+        };
+        let response = match (request_doc.get_str("response_message"), locale.as_str()) {
+            (Ok(response_message), "fr") => format!(" « {} »", response_message),
+            (Ok(response_message), _) => format!(" \"{}\"", response_message),
+            (Err(_), _) => String::new(),
+        };
+        let variables = HashMap::from([
+            ("requester".to_string(), requester.to_string()),
+            ("subject".to_string(), K::subject(request_doc, &locale)),
+            ("response".to_string(), response),
+            // Laissé tel quel pour l'agrégation des notifications regroupées
+            ("count".to_string(), "{count}".to_string()),
+        ]);
+
+        let rendered = match Self::render_template(db, &notification_type, &locale, &variables).await {
+            Ok(rendered) => rendered,
+            Err(e) => {
+                log::error!("Erreur notification demande {}: {}", descriptor.kind, e);
+                return;
+            }
+        };
+        let collapse_summary = if status == RequestStatus::Pending {
+            match Self::render_template(db, REQUEST_SUMMARY_TEMPLATE, &locale, &variables).await {
+                Ok(summary) => Some(summary.message),
+                Err(e) => {
+                    log::error!("Erreur notification demande {}: {}", descriptor.kind, e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        let action_url = request_doc
+            .get_object_id("_id")
+            .ok()
+            .map(|oid| format!("/api/requests/{}/{}", descriptor.path, oid.to_hex()));
+
+        let notification = NewNotification {
+            to: to.to_string(),
+            r#type: notification_type,
+            title: rendered.title,
+            message: rendered.message,
+            priority: priority.to_string(),
+            action_url,
+            created_by: REQUEST_NOTIFICATION_CREATOR.to_string(),
+            // Plusieurs nouvelles demandes du même type se regroupent tant qu'elles ne sont pas lues
+            collapse_key: collapse_summary
+                .is_some()
+                .then(|| format!("request_created:{}", descriptor.kind)),
+            collapse_action_url: collapse_summary
+                .is_some()
+                .then(|| format!("/api/requests/{}/incoming/{}", descriptor.path, owner)),
+            collapse_summary,
+            ..Default::default()
+        };
+
+        if let Err(e) = NotificationService::create(db, &notification).await {
+            log::error!("Erreur notification demande {}: {}", descriptor.kind, e);
+        }
+    }
+
+    /// Rend un modèle de notification de demande : celui enregistré sous cette clé
+    /// (`/api/notifications/templates`), à défaut le modèle intégré
+    async fn render_template(
+        db: &Database,
+        key: &str,
+        locale: &str,
+        variables: &HashMap<String, String>,
+    ) -> Result<RenderedTemplate, String> {
+        let builtin = builtin_request_template(key).ok_or_else(|| format!("Modèle inconnu: {}", key))?;
+        let template = TemplateService::get_or_builtin(db, builtin)
+            .await
+            .map_err(|e| e.to_string())?;
+        template.render(locale, variables)
+    }
+}
+
+/// Clé du message agrégé des nouvelles demandes regroupées (`{count}` demandes)
+const REQUEST_SUMMARY_TEMPLATE: &str = "request_created_summary";
+
+/// Textes par défaut des notifications de demandes ; variables `{requester}`, `{subject}`,
+/// `{response}` (réponse du propriétaire, éventuellement vide) et `{count}` pour l'agrégat
+fn builtin_request_template(key: &str) -> Option<NotificationTemplate> {
+    let variants: &[(&str, &str, &str)] = match key {
+        "request_created" => &[
+            ("fr", "Nouvelle demande", "{requester} demande {subject}."),
+            ("en", "New request", "{requester} is requesting {subject}."),
+            ("es", "Nueva solicitud", "{requester} solicita {subject}."),
+        ],
+        "request_approved" => &[
+            ("fr", "Demande acceptée", "Votre demande concernant {subject} a été acceptée.{response}"),
+            ("en", "Request approved", "Your request for {subject} was approved.{response}"),
+            ("es", "Solicitud aceptada", "Tu solicitud sobre {subject} ha sido aceptada.{response}"),
+        ],
+        "request_rejected" => &[
+            ("fr", "Demande refusée", "Votre demande concernant {subject} a été refusée.{response}"),
+            ("en", "Request declined", "Your request for {subject} was declined.{response}"),
+            ("es", "Solicitud rechazada", "Tu solicitud sobre {subject} ha sido rechazada.{response}"),
+        ],
+        "request_expired" => &[
+            ("fr", "Demande expirée", "Votre demande concernant {subject} a expiré sans réponse."),
+            ("en", "Request expired", "Your request for {subject} expired without a reply."),
+            ("es", "Solicitud caducada", "Tu solicitud sobre {subject} caducó sin respuesta."),
+        ],
+        REQUEST_SUMMARY_TEMPLATE => &[
+            ("fr", "Nouvelles demandes", "{count} nouvelles demandes en attente"),
+            ("en", "New requests", "{count} new pending requests"),
+            ("es", "Nuevas solicitudes", "{count} nuevas solicitudes pendientes"),
+        ],
+        _ => return None,
+    };
+    Some(NotificationTemplate::builtin(key, variants))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::request_kinds::GroupAccess;
+
+    #[test]
+    fn cursor_parses_timestamp_and_id() {
+        let id = ObjectId::new();
+        let cursor = ListCursor::parse(&format!("2026-01-01T10:00:00+00:00_{}", id.to_hex()));
+        assert_eq!(cursor.timestamp, "2026-01-01T10:00:00+00:00");
+        assert_eq!(cursor.id, Some(id));
+
+        let legacy = ListCursor::parse("2026-01-01T10:00:00+00:00");
+        assert_eq!(legacy.timestamp, "2026-01-01T10:00:00+00:00");
+        assert!(legacy.id.is_none());
+    }
+
+    #[test]
+    fn cursor_round_trips_through_encode() {
+        let id = ObjectId::new();
+        let request_doc = doc! { "_id": id, "timestamp": "2026-01-01T10:00:00+00:00" };
+        let cursor = ListCursor::parse(&ListCursor::encode(&request_doc));
+        assert_eq!(cursor.id, Some(id));
+    }
+
+    fn status_clause(filter: &ListFilter) -> Document {
+        let filter = list_filter(&GroupAccess::DESCRIPTOR, "alice", RequestDirection::Incoming, filter);
+        filter.get_array("$and").unwrap()[1].as_document().unwrap().clone()
+    }
+
+    #[test]
+    fn pending_filter_excludes_requests_past_ttl() {
+        let clause = status_clause(&ListFilter {
+            status: Some("pending"),
+            expiry_cutoff: Some("2026-01-01T00:00:00+00:00"),
+            before: None,
+        });
+        assert_eq!(clause, doc! { "status": "pending", "timestamp": { "$gte": "2026-01-01T00:00:00+00:00" } });
+    }
+
+    #[test]
+    fn expired_filter_includes_pending_requests_past_ttl() {
+        let clause = status_clause(&ListFilter {
+            status: Some("expired"),
+            expiry_cutoff: Some("2026-01-01T00:00:00+00:00"),
+            before: None,
+        });
+        assert_eq!(
+            clause,
+            doc! { "$or": [
+                { "status": "expired" },
+                { "status": "pending", "timestamp": { "$lt": "2026-01-01T00:00:00+00:00" } },
+            ] }
+        );
+    }
+
+    #[test]
+    fn cursor_filter_keeps_requests_sharing_the_timestamp() {
+        let id = ObjectId::new();
+        let cursor = ListCursor { timestamp: "2026-01-01T10:00:00+00:00".to_string(), id: Some(id) };
+        let clause = status_clause(&ListFilter { before: Some(&cursor), ..Default::default() });
+        assert_eq!(
+            clause,
+            doc! { "$or": [
+                { "timestamp": { "$lt": "2026-01-01T10:00:00+00:00" } },
+                { "timestamp": "2026-01-01T10:00:00+00:00", "_id": { "$lt": id } },
+            ] }
+        );
+    }
+
+    #[test]
+    fn builtin_templates_render_in_every_locale() {
+        let variables = HashMap::from([
+            ("requester".to_string(), "bob".to_string()),
+            ("subject".to_string(), "X".to_string()),
+            ("response".to_string(), String::new()),
+            ("count".to_string(), "{count}".to_string()),
+        ]);
+        for key in ["request_created", "request_approved", "request_rejected", "request_expired", REQUEST_SUMMARY_TEMPLATE] {
+            let template = builtin_request_template(key).unwrap();
+            assert!(template.validate().is_ok());
+            for locale in crate::services::template_service::SUPPORTED_LOCALES {
+                let rendered = template.render(locale, &variables).unwrap();
+                assert_eq!(rendered.locale, locale);
+            }
+        }
+
+        let summary = builtin_request_template(REQUEST_SUMMARY_TEMPLATE).unwrap();
+        assert_eq!(summary.render("fr", &variables).unwrap().message, "{count} nouvelles demandes en attente");
+    }
+}
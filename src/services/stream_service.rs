@@ -0,0 +1,278 @@
+use mongodb::bson::{Bson, Document};
+use serde_json::json;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tokio::sync::broadcast;
+use crate::config::StreamConfig;
+use crate::events::{event_data, AppEvent};
+
+/// Capacité du canal vers les flux connectés
+const STREAM_CHANNEL_CAPACITY: usize = 4096;
+
+/// Événement destiné au flux SSE d'un utilisateur
+#[derive(Debug)]
+pub struct StreamEvent {
+    /// Absent pour les événements éphémères (frappe), qui ne sont pas rejoués
+    pub id: Option<u64>,
+    pub username: String,
+    pub event: &'static str,
+    pub data: serde_json::Value,
+    created_at: Instant,
+}
+
+impl StreamEvent {
+    /// Sérialisation au format `text/event-stream`
+    pub fn to_sse(&self) -> String {
+        let mut frame = String::new();
+        if let Some(id) = self.id {
+            frame.push_str(&format!("id: {}\n", id));
+        }
+        frame.push_str(&format!("event: {}\ndata: {}\n\n", self.event, self.data));
+        frame
+    }
+}
+
+/// Abonnement d'un client au flux d'un utilisateur
+pub struct StreamSubscription {
+    /// Événements manqués depuis `Last-Event-ID`
+    pub replay: Vec<Arc<StreamEvent>>,
+    /// Des événements manqués ne sont plus disponibles : le client doit recharger par l'API
+    pub gap: bool,
+    /// Dernier identifiant envoyé, pour ignorer les doublons du flux en direct
+    pub last_id: u64,
+    pub receiver: broadcast::Receiver<Arc<StreamEvent>>,
+}
+
+#[derive(Default)]
+struct ReplayBuffer {
+    events: VecDeque<Arc<StreamEvent>>,
+    /// Identifiant le plus récent retiré de l'historique
+    evicted_through: u64,
+}
+
+/// Répartit les événements du bus vers les flux SSE et garde un historique court par utilisateur
+pub struct StreamHub {
+    config: StreamConfig,
+    /// Premier identifiant attribué par ce processus
+    first_id: u64,
+    next_id: AtomicU64,
+    buffers: Mutex<HashMap<String, ReplayBuffer>>,
+    /// Identifiant le plus récent des historiques purgés par ancienneté
+    pruned_through: AtomicU64,
+    sender: broadcast::Sender<Arc<StreamEvent>>,
+}
+
+fn document_json(document: &Document) -> serde_json::Value {
+    Bson::Document(event_data(document.clone())).into_relaxed_extjson()
+}
+
+impl StreamHub {
+    pub fn new(config: StreamConfig) -> Self {
+        let (sender, _) = broadcast::channel(STREAM_CHANNEL_CAPACITY);
+        // Identifiants croissants d'un démarrage à l'autre, pour qu'un ancien Last-Event-ID reste comparable
+        let first_id = chrono::Utc::now().timestamp_millis().max(0) as u64 * 1000;
+        StreamHub {
+            config,
+            first_id,
+            next_id: AtomicU64::new(first_id),
+            buffers: Mutex::new(HashMap::new()),
+            pruned_through: AtomicU64::new(0),
+            sender,
+        }
+    }
+
+    /// Transmet un événement du bus aux utilisateurs concernés
+    pub fn dispatch(&self, event: &AppEvent) {
+        match event {
+            AppEvent::MessageCreated(message) => {
+                let data = document_json(message);
+                // L'expéditeur le reçoit aussi, pour ses autres appareils
+                for username in [message.get_str("to"), message.get_str("from")].into_iter().flatten() {
+                    self.push(username, "message", data.clone(), true);
+                }
+            }
+            AppEvent::NotificationPublished(notification) => {
+                if let Ok(to) = notification.get_str("to") {
+                    self.push(to, "notification", document_json(notification), true);
+                }
+            }
+            AppEvent::MessagesRead { reader, peer, count, read_at, receipt_hidden } => {
+                let data = json!({ "reader": reader, "peer": peer, "count": count, "read_at": read_at });
+                if !receipt_hidden {
+                    self.push(peer, "read", data.clone(), true);
+                }
+                self.push(reader, "read", data, true);
+            }
+            AppEvent::Typing { from, to, typing } => {
+                self.push(to, "typing", json!({ "from": from, "typing": typing }), false);
+            }
+        }
+    }
+
+    fn push(&self, username: &str, event: &'static str, data: serde_json::Value, resumable: bool) {
+        let stream_event = Arc::new(StreamEvent {
+            id: resumable.then(|| self.next_id.fetch_add(1, Ordering::Relaxed)),
+            username: username.to_string(),
+            event,
+            data,
+            created_at: Instant::now(),
+        });
+
+        if resumable && self.config.replay_size > 0 {
+            let mut buffers = self.buffers.lock().unwrap_or_else(|e| e.into_inner());
+            let buffer = buffers.entry(username.to_string()).or_default();
+            buffer.events.push_back(stream_event.clone());
+            while buffer.events.len() > self.config.replay_size {
+                if let Some(evicted) = buffer.events.pop_front().and_then(|e| e.id) {
+                    buffer.evicted_through = evicted;
+                }
+            }
+        }
+
+        let _ = self.sender.send(stream_event);
+    }
+
+    /// Ouvre un abonnement ; avec `last_event_id`, les événements manqués encore en mémoire sont rejoués
+    pub fn subscribe(&self, username: &str, last_event_id: Option<u64>) -> StreamSubscription {
+        // Abonnement avant lecture de l'historique : aucun événement ne peut passer entre les deux
+        let receiver = self.sender.subscribe();
+        let buffers = self.buffers.lock().unwrap_or_else(|e| e.into_inner());
+        let buffer = buffers.get(username);
+
+        // Sans reprise, rien n'est rejoué : tout événement reçu ensuite est nouveau, y compris
+        // celui déjà ajouté à l'historique mais pas encore diffusé pendant l'abonnement
+        let Some(last_event_id) = last_event_id else {
+            return StreamSubscription { replay: Vec::new(), gap: false, last_id: 0, receiver };
+        };
+        // Identifiant jamais attribué : sans reprise possible, et les événements suivants
+        // seraient sinon écartés comme doublons
+        if last_event_id >= self.next_id.load(Ordering::Relaxed) {
+            return StreamSubscription { replay: Vec::new(), gap: true, last_id: 0, receiver };
+        }
+
+        let replay: Vec<Arc<StreamEvent>> = buffer
+            .map(|b| b.events.iter().filter(|e| e.id > Some(last_event_id)).cloned().collect())
+            .unwrap_or_default();
+        // Des événements postérieurs à `last_event_id` ont pu être perdus : redémarrage,
+        // éviction faute de place ou purge (prudente : la purge est suivie globalement)
+        let gap = last_event_id.saturating_add(1) < self.first_id
+            || match buffer {
+                Some(buffer) => buffer.evicted_through > last_event_id,
+                None => self.pruned_through.load(Ordering::Relaxed) > last_event_id,
+            };
+        let last_id = replay.last().and_then(|e| e.id).unwrap_or(last_event_id);
+
+        StreamSubscription { replay, gap, last_id, receiver }
+    }
+
+    /// Oublie les événements trop anciens pour être rejoués
+    pub fn prune(&self) {
+        let now = Instant::now();
+        let mut buffers = self.buffers.lock().unwrap_or_else(|e| e.into_inner());
+        buffers.retain(|_, buffer| {
+            while buffer
+                .events
+                .front()
+                .is_some_and(|e| now.duration_since(e.created_at) > self.config.replay_window())
+            {
+                if let Some(pruned) = buffer.events.pop_front().and_then(|e| e.id) {
+                    buffer.evicted_through = pruned;
+                    self.pruned_through.fetch_max(pruned, Ordering::Relaxed);
+                }
+            }
+            !buffer.events.is_empty()
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mongodb::bson::doc;
+
+    fn hub(replay_size: usize) -> StreamHub {
+        StreamHub::new(StreamConfig { replay_size, replay_secs: 600 })
+    }
+
+    fn notify(hub: &StreamHub, to: &str) {
+        hub.dispatch(&AppEvent::NotificationPublished(doc! { "to": to, "title": "t" }));
+    }
+
+    /// Identifiant du dernier événement reçu par le client
+    fn latest_id(hub: &StreamHub, username: &str) -> u64 {
+        let buffers = hub.buffers.lock().unwrap();
+        buffers[username].events.back().and_then(|e| e.id).unwrap()
+    }
+
+    #[test]
+    fn replays_missed_events_without_gap() {
+        let hub = hub(10);
+        notify(&hub, "alice");
+        let first = latest_id(&hub, "alice");
+        notify(&hub, "alice");
+        notify(&hub, "bob");
+
+        let subscription = hub.subscribe("alice", Some(first));
+        assert!(!subscription.gap);
+        assert_eq!(subscription.replay.len(), 1);
+        assert_eq!(subscription.last_id, first + 1);
+    }
+
+    #[test]
+    fn evicted_events_are_reported_as_gap() {
+        let hub = hub(2);
+        notify(&hub, "alice");
+        let first = latest_id(&hub, "alice");
+        for _ in 0..3 {
+            notify(&hub, "alice");
+        }
+
+        let subscription = hub.subscribe("alice", Some(first));
+        assert!(subscription.gap);
+        assert_eq!(subscription.replay.len(), 2);
+    }
+
+    #[test]
+    fn id_from_a_previous_process_is_a_gap() {
+        let hub = hub(10);
+        let subscription = hub.subscribe("alice", Some(hub.first_id - 2));
+        assert!(subscription.gap);
+        assert!(!hub.subscribe("alice", Some(hub.first_id - 1)).gap);
+    }
+
+    #[test]
+    fn unknown_future_id_does_not_overflow_or_swallow_events() {
+        let hub = hub(10);
+        notify(&hub, "alice");
+
+        let subscription = hub.subscribe("alice", Some(u64::MAX));
+        assert!(subscription.gap);
+        assert!(subscription.replay.is_empty());
+        assert_eq!(subscription.last_id, 0);
+    }
+
+    #[test]
+    fn event_published_while_subscribing_is_delivered() {
+        let hub = hub(10);
+        notify(&hub, "alice");
+
+        // `push` a ajouté l'événement à l'historique mais ne l'a pas encore diffusé
+        let event = Arc::new(StreamEvent {
+            id: Some(hub.next_id.fetch_add(1, Ordering::Relaxed)),
+            username: "alice".to_string(),
+            event: "notification",
+            data: json!({}),
+            created_at: Instant::now(),
+        });
+        hub.buffers.lock().unwrap().get_mut("alice").unwrap().events.push_back(event.clone());
+
+        let mut subscription = hub.subscribe("alice", None);
+        let _ = hub.sender.send(event);
+
+        let received = subscription.receiver.try_recv().unwrap();
+        // Le flux écarte les identifiants <= last_id comme doublons de la reprise
+        assert!(received.id.unwrap() > subscription.last_id);
+    }
+}
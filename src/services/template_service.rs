@@ -0,0 +1,310 @@
+use futures_util::stream::TryStreamExt;
+use mongodb::{
+    bson::{doc, Document},
+    options::{FindOneOptions, FindOptions, IndexOptions},
+    Database, IndexModel,
+};
+use std::collections::HashMap;
+use super::request_service::is_duplicate_key;
+
+/// Langues disponibles pour les variantes de modèles
+pub const SUPPORTED_LOCALES: [&str; 3] = ["fr", "en", "es"];
+
+/// Langue utilisée quand le destinataire n'en a pas choisi
+pub const DEFAULT_LOCALE: &str = "fr";
+
+/// Champ de `user_preferences` portant la langue du destinataire ; lu ici pour le rendu
+/// des modèles, écrit avec les autres préférences (`PreferenceService::update`)
+pub const LOCALE_FIELD: &str = "locale";
+
+/// Langue choisie si elle est supportée, à défaut la langue par défaut
+fn resolve_locale(locale: Option<&str>) -> &str {
+    locale
+        .filter(|locale| SUPPORTED_LOCALES.contains(locale))
+        .unwrap_or(DEFAULT_LOCALE)
+}
+
+pub fn validate_locale(locale: &str) -> Result<(), String> {
+    if SUPPORTED_LOCALES.contains(&locale) {
+        Ok(())
+    } else {
+        Err(format!("Langue non supportée: {} (attendu: {})", locale, SUPPORTED_LOCALES.join(", ")))
+    }
+}
+
+fn default_locale() -> String {
+    DEFAULT_LOCALE.to_string()
+}
+
+/// Texte d'un modèle dans une langue, avec des variables `{nom}`
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct TemplateVariant {
+    pub title: String,
+    pub message: String,
+}
+
+/// Modèle de notification identifié par sa clé (collection `notification_templates`)
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct NotificationTemplate {
+    pub key: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Langue de repli si la variante du destinataire n'existe pas
+    #[serde(default = "default_locale")]
+    pub default_locale: String,
+    pub variants: HashMap<String, TemplateVariant>,
+    /// Type et priorité par défaut des notifications créées depuis ce modèle
+    #[serde(default)]
+    pub r#type: Option<String>,
+    #[serde(default)]
+    pub priority: Option<String>,
+    #[serde(default)]
+    pub created_at: Option<String>,
+    #[serde(default)]
+    pub updated_at: Option<String>,
+}
+
+/// Titre et message rendus pour un destinataire
+#[derive(Debug)]
+pub struct RenderedTemplate {
+    pub locale: String,
+    pub title: String,
+    pub message: String,
+}
+
+impl NotificationTemplate {
+    /// Modèle intégré au code, utilisé tant qu'aucun modèle n'est enregistré sous la même clé
+    pub fn builtin(key: &str, variants: &[(&str, &str, &str)]) -> Self {
+        NotificationTemplate {
+            key: key.to_string(),
+            description: None,
+            default_locale: default_locale(),
+            variants: variants
+                .iter()
+                .map(|(locale, title, message)| {
+                    (
+                        locale.to_string(),
+                        TemplateVariant {
+                            title: title.to_string(),
+                            message: message.to_string(),
+                        },
+                    )
+                })
+                .collect(),
+            r#type: None,
+            priority: None,
+            created_at: None,
+            updated_at: None,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.key.trim().is_empty() {
+            return Err("La clé du modèle est requise".to_string());
+        }
+        if self.variants.is_empty() {
+            return Err("Au moins une variante est requise".to_string());
+        }
+        for locale in self.variants.keys() {
+            validate_locale(locale)?;
+        }
+        if !self.variants.contains_key(&self.default_locale) {
+            return Err(format!("Variante manquante pour la langue par défaut {}", self.default_locale));
+        }
+        Ok(())
+    }
+
+    /// Rend le modèle dans la langue demandée, à défaut dans la langue par défaut du modèle
+    pub fn render(&self, locale: &str, variables: &HashMap<String, String>) -> Result<RenderedTemplate, String> {
+        let (locale, variant) = self
+            .variants
+            .get_key_value(locale)
+            .or_else(|| self.variants.get_key_value(&self.default_locale))
+            .ok_or_else(|| format!("Aucune variante pour le modèle {}", self.key))?;
+
+        Ok(RenderedTemplate {
+            locale: locale.clone(),
+            title: render_text(&variant.title, variables)?,
+            message: render_text(&variant.message, variables)?,
+        })
+    }
+}
+
+/// Remplace les `{variable}` ; une variable sans valeur est une erreur
+fn render_text(text: &str, variables: &HashMap<String, String>) -> Result<String, String> {
+    let mut rendered = String::with_capacity(text.len());
+    let mut missing = Vec::new();
+    let mut rest = text;
+
+    while let Some(start) = rest.find('{') {
+        rendered.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        match after.find('}') {
+            Some(end) if is_variable_name(&after[..end]) => {
+                let name = &after[..end];
+                match variables.get(name) {
+                    Some(value) => rendered.push_str(value),
+                    None => missing.push(name.to_string()),
+                }
+                rest = &after[end + 1..];
+            }
+            _ => {
+                rendered.push('{');
+                rest = after;
+            }
+        }
+    }
+    rendered.push_str(rest);
+
+    if missing.is_empty() {
+        Ok(rendered)
+    } else {
+        Err(format!("Variables manquantes: {}", missing.join(", ")))
+    }
+}
+
+fn is_variable_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+pub struct TemplateService;
+
+impl TemplateService {
+    /// Une seule définition par clé, sans quoi le rendu choisirait arbitrairement
+    pub async fn ensure_indexes(db: &Database) -> Result<(), mongodb::error::Error> {
+        let index = IndexModel::builder()
+            .keys(doc! { "key": 1 })
+            .options(IndexOptions::builder().name("unique_key".to_string()).unique(true).build())
+            .build();
+        db.collection::<Document>("notification_templates").create_index(index, None).await?;
+        Ok(())
+    }
+
+    /// Renvoie `false` si un modèle existe déjà avec cette clé
+    pub async fn create(db: &Database, template: &NotificationTemplate) -> Result<bool, mongodb::error::Error> {
+        let now = chrono::Utc::now().to_rfc3339();
+        let template = NotificationTemplate {
+            created_at: Some(now.clone()),
+            updated_at: Some(now),
+            ..template.clone()
+        };
+        match db
+            .collection::<NotificationTemplate>("notification_templates")
+            .insert_one(template, None)
+            .await
+        {
+            Ok(_) => Ok(true),
+            // L'index unique sur `key` refuse une création concurrente de la même clé
+            Err(e) if is_duplicate_key(&e) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub async fn get(db: &Database, key: &str) -> Result<Option<NotificationTemplate>, mongodb::error::Error> {
+        db.collection::<NotificationTemplate>("notification_templates")
+            .find_one(doc! { "key": key }, None)
+            .await
+    }
+
+    /// Modèle enregistré sous la clé du modèle intégré, à défaut le modèle intégré lui-même
+    pub async fn get_or_builtin(
+        db: &Database,
+        builtin: NotificationTemplate,
+    ) -> Result<NotificationTemplate, mongodb::error::Error> {
+        Ok(Self::get(db, &builtin.key).await?.unwrap_or(builtin))
+    }
+
+    pub async fn list(db: &Database) -> Result<Vec<NotificationTemplate>, mongodb::error::Error> {
+        let options = FindOptions::builder().sort(doc! { "key": 1 }).build();
+        let mut cursor = db
+            .collection::<NotificationTemplate>("notification_templates")
+            .find(doc! {}, options)
+            .await?;
+
+        let mut templates = Vec::new();
+        while let Some(template) = cursor.try_next().await? {
+            templates.push(template);
+        }
+        Ok(templates)
+    }
+
+    /// Remplace le contenu d'un modèle existant en conservant sa date de création
+    pub async fn update(db: &Database, template: &NotificationTemplate) -> Result<bool, mongodb::error::Error> {
+        let collection = db.collection::<NotificationTemplate>("notification_templates");
+        let Some(existing) = collection.find_one(doc! { "key": &template.key }, None).await? else {
+            return Ok(false);
+        };
+
+        let template = NotificationTemplate {
+            created_at: existing.created_at,
+            updated_at: Some(chrono::Utc::now().to_rfc3339()),
+            ..template.clone()
+        };
+        collection
+            .replace_one(doc! { "key": &template.key }, template, None)
+            .await?;
+        Ok(true)
+    }
+
+    /// Langue de rendu des modèles pour un destinataire
+    pub async fn recipient_locale(db: &Database, username: &str) -> Result<String, mongodb::error::Error> {
+        let options = FindOneOptions::builder().projection(doc! { LOCALE_FIELD: 1 }).build();
+        let preferences = db
+            .collection::<Document>("user_preferences")
+            .find_one(doc! { "username": username }, options)
+            .await?;
+
+        let locale = preferences.as_ref().and_then(|p| p.get_str(LOCALE_FIELD).ok());
+        Ok(resolve_locale(locale).to_string())
+    }
+
+    pub async fn delete(db: &Database, key: &str) -> Result<bool, mongodb::error::Error> {
+        let result = db
+            .collection::<Document>("notification_templates")
+            .delete_one(doc! { "key": key }, None)
+            .await?;
+        Ok(result.deleted_count > 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn variables(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn render_text_replaces_variables() {
+        let variables = variables(&[("requester", "alice"), ("subject", "tes photos")]);
+        let rendered = render_text("{requester} demande {subject}", &variables);
+        assert_eq!(rendered.unwrap(), "alice demande tes photos");
+    }
+
+    #[test]
+    fn render_text_reports_every_missing_variable() {
+        let error = render_text("{requester} et {count} autres", &HashMap::new()).unwrap_err();
+        assert_eq!(error, "Variables manquantes: requester, count");
+    }
+
+    #[test]
+    fn render_text_keeps_braces_that_are_not_variables() {
+        let rendered = render_text("{ } {a-b} {x", &variables(&[("x", "ignored")]));
+        assert_eq!(rendered.unwrap(), "{ } {a-b} {x");
+    }
+
+    #[test]
+    fn render_falls_back_to_the_default_locale() {
+        let template =
+            NotificationTemplate::builtin("greeting", &[("fr", "Bonjour", "Salut {name}"), ("en", "Hello", "Hi {name}")]);
+        let variables = variables(&[("name", "bob")]);
+
+        let rendered = template.render("en", &variables).unwrap();
+        assert_eq!((rendered.locale.as_str(), rendered.message.as_str()), ("en", "Hi bob"));
+
+        let rendered = template.render("de", &variables).unwrap();
+        assert_eq!(rendered.locale, DEFAULT_LOCALE);
+        assert_eq!(rendered.message, "Salut bob");
+    }
+}
@@ -0,0 +1,102 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Instant;
+use crate::config::TypingConfig;
+use crate::events::{AppEvent, EventBus};
+
+struct TypingState {
+    expires_at: Instant,
+    last_broadcast: Option<Instant>,
+}
+
+/// Indicateurs « X est en train d'écrire… », uniquement en mémoire.
+///
+/// Clé : (auteur, destinataire). Un indicateur non renouvelé expire après `ttl`.
+pub struct TypingRegistry {
+    config: TypingConfig,
+    states: Mutex<HashMap<(String, String), TypingState>>,
+}
+
+impl TypingRegistry {
+    pub fn new(config: TypingConfig) -> Self {
+        TypingRegistry {
+            config,
+            states: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn config(&self) -> &TypingConfig {
+        &self.config
+    }
+
+    /// Début ou fin de frappe de `from` vers `to`.
+    ///
+    /// Les débuts répétés prolongent l'indicateur mais ne sont diffusés qu'une fois par
+    /// intervalle `throttle`. Renvoie `true` si l'événement a été diffusé.
+    pub fn set_typing(&self, bus: &EventBus, from: &str, to: &str, typing: bool) -> bool {
+        let now = Instant::now();
+        let key = (from.to_string(), to.to_string());
+        let mut states = self.states.lock().unwrap_or_else(|e| e.into_inner());
+
+        let broadcast = if typing {
+            let state = states.entry(key).or_insert(TypingState {
+                expires_at: now,
+                last_broadcast: None,
+            });
+            state.expires_at = now + self.config.ttl();
+            let due = state
+                .last_broadcast
+                .is_none_or(|last| now.duration_since(last) >= self.config.throttle());
+            if due {
+                state.last_broadcast = Some(now);
+            }
+            due
+        } else {
+            // Arrêt diffusé uniquement si un indicateur était actif
+            states.remove(&key).is_some()
+        };
+        drop(states);
+
+        if broadcast {
+            bus.publish(AppEvent::Typing {
+                from: from.to_string(),
+                to: to.to_string(),
+                typing,
+            });
+        }
+        broadcast
+    }
+
+    /// Utilisateurs en train d'écrire à `username`
+    pub fn typing_to(&self, username: &str) -> Vec<String> {
+        let now = Instant::now();
+        let states = self.states.lock().unwrap_or_else(|e| e.into_inner());
+        let mut typing: Vec<String> = states
+            .iter()
+            .filter(|((_, to), state)| to == username && state.expires_at > now)
+            .map(|((from, _), _)| from.clone())
+            .collect();
+        typing.sort();
+        typing
+    }
+
+    /// Supprime les indicateurs expirés et diffuse leur fin
+    pub fn expire(&self, bus: &EventBus) {
+        let now = Instant::now();
+        let mut expired = Vec::new();
+        {
+            let mut states = self.states.lock().unwrap_or_else(|e| e.into_inner());
+            states.retain(|key, state| {
+                let alive = state.expires_at > now;
+                if !alive {
+                    expired.push(key.clone());
+                }
+                alive
+            });
+        }
+
+        for (from, to) in expired {
+            bus.publish(AppEvent::Typing { from, to, typing: false });
+        }
+    }
+}
@@ -1,39 +1,59 @@
+use chrono::{DateTime, Utc};
 use tokio_postgres::Client;
 use log::info;
+use std::collections::HashMap;
+
+/// Statut de connexion enregistré dans `compte_compte`
+#[derive(Debug, Clone)]
+pub struct StoredPresence {
+    pub is_online: bool,
+    pub last_seen: Option<DateTime<Utc>>,
+}
 
 pub struct UserService;
 
 impl UserService {
-    /// Met à jour le statut de connexion d'un utilisateur (INSERT OR UPDATE)
+    /// Met à jour le statut de connexion d'un utilisateur.
+    ///
+    /// Renvoie `false` si le compte n'existe pas : les comptes sont créés par l'API principale.
     pub async fn update_connection_status(
         client: &Client,
         username: &str,
         is_online: bool,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        // UPSERT: INSERT si n'existe pas, UPDATE sinon
+    ) -> Result<bool, tokio_postgres::Error> {
         let query = "
-            INSERT INTO compte_compte (username, is_online, connected_at, last_seen)
-            VALUES ($1, $2, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)
-            ON CONFLICT (username) DO UPDATE
+            UPDATE compte_compte
             SET is_online = $2, last_seen = CURRENT_TIMESTAMP
+            WHERE username = $1
         ";
 
-        match client.execute(query, &[&username, &is_online]).await {
-            Ok(_) => {
-                info!("✅ Statut mis à jour: {} → {}", username, if is_online { "EN LIGNE" } else { "HORS LIGNE" });
-                Ok(())
-            }
-            Err(e) => {
-                info!("❌ Erreur mise à jour statut: {}", e);
-                Err(Box::new(e))
-            }
+        let updated = client.execute(query, &[&username, &is_online]).await?;
+        if updated > 0 {
+            info!("🔄 Statut mis à jour: {} → {}", username, if is_online { "EN LIGNE" } else { "HORS LIGNE" });
+        } else {
+            log::warn!("⚠️ Utilisateur non trouvé: {}", username);
         }
+        Ok(updated > 0)
+    }
+
+    /// Rafraîchit `last_seen` des utilisateurs connectés à ce serveur
+    pub async fn touch_online(
+        client: &Client,
+        usernames: &[String],
+    ) -> Result<u64, tokio_postgres::Error> {
+        let query = "
+            UPDATE compte_compte
+            SET is_online = true, last_seen = CURRENT_TIMESTAMP
+            WHERE username = ANY($1)
+        ";
+
+        client.execute(query, &[&usernames]).await
     }
 
     /// Récupère tous les utilisateurs en ligne
     pub async fn get_online_users(
         client: &Client,
-    ) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    ) -> Result<Vec<String>, tokio_postgres::Error> {
         let query = "
             SELECT username
             FROM compte_compte
@@ -41,65 +61,75 @@ impl UserService {
             ORDER BY username
         ";
 
-        match client.query(query, &[]).await {
-            Ok(rows) => {
-                let users: Vec<String> = rows.iter()
-                    .map(|row| row.get(0))
-                    .collect();
-                info!("👥 Utilisateurs en ligne: {}", users.len());
-                Ok(users)
-            }
-            Err(e) => {
-                info!("ℹ️ Erreur récupération utilisateurs: {}", e);
-                Ok(Vec::new())
-            }
-        }
+        let rows = client.query(query, &[]).await?;
+        let users: Vec<String> = rows.iter()
+            .map(|row| row.get(0))
+            .collect();
+        info!("👥 Utilisateurs en ligne: {}", users.len());
+        Ok(users)
     }
 
     /// Vérifie si un utilisateur est en ligne
     pub async fn is_user_online(
         client: &Client,
         username: &str,
-    ) -> Result<bool, Box<dyn std::error::Error>> {
+    ) -> Result<bool, tokio_postgres::Error> {
         let query = "
             SELECT is_online
             FROM compte_compte
             WHERE username = $1
         ";
 
-        match client.query_opt(query, &[&username]).await {
-            Ok(Some(row)) => {
-                let is_online: bool = row.get(0);
-                Ok(is_online)
-            }
-            Ok(None) => Ok(false),
-            Err(_) => Ok(false),
-        }
+        let row = client.query_opt(query, &[&username]).await?;
+        Ok(row.and_then(|row| row.get::<_, Option<bool>>(0)).unwrap_or(false))
     }
 
-    /// Récupère tous les utilisateurs
-    pub async fn get_all_users(
+    /// Statut et dernière activité de plusieurs utilisateurs en une requête
+    pub async fn get_presence(
         client: &Client,
-    ) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        usernames: &[String],
+    ) -> Result<HashMap<String, StoredPresence>, tokio_postgres::Error> {
         let query = "
-            SELECT username
+            SELECT username, is_online, last_seen::timestamptz
             FROM compte_compte
-            ORDER BY username
+            WHERE username = ANY($1)
+        ";
+
+        let rows = client.query(query, &[&usernames]).await?;
+        Ok(rows
+            .iter()
+            .map(|row| {
+                (
+                    row.get::<_, String>(0),
+                    StoredPresence {
+                        is_online: row.get::<_, Option<bool>>(1).unwrap_or(false),
+                        last_seen: row.get(2),
+                    },
+                )
+            })
+            .collect())
+    }
+
+    /// Déconnecte les utilisateurs inactifs depuis plus de `inactive_minutes` minutes
+    pub async fn cleanup_inactive_connections(
+        client: &Client,
+        inactive_minutes: i32,
+    ) -> Result<Vec<String>, tokio_postgres::Error> {
+        let query = "
+            UPDATE compte_compte
+            SET is_online = false
+            WHERE is_online = true
+              AND last_seen < NOW() - ($1::int * INTERVAL '1 minute')
+            RETURNING username
         ";
 
-        match client.query(query, &[]).await {
-            Ok(rows) => {
-                let users: Vec<String> = rows.iter()
-                    .map(|row| row.get(0))
-                    .collect();
-                info!("👥 Total utilisateurs: {}", users.len());
-                Ok(users)
-            }
-            Err(e) => {
-                info!("ℹ️ Erreur récupération utilisateurs: {}", e);
-                Ok(Vec::new())
-            }
+        let rows = client.query(query, &[&inactive_minutes]).await?;
+        let users: Vec<String> = rows.iter()
+            .map(|row| row.get(0))
+            .collect();
+        if !users.is_empty() {
+            info!("🧹 Nettoyage: {} utilisateurs inactifs déconnectés", users.len());
         }
+        Ok(users)
     }
 }
-
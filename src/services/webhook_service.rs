@@ -0,0 +1,445 @@
+use futures_util::stream::TryStreamExt;
+use hmac::{Hmac, Mac};
+use log::info;
+use mongodb::{
+    bson::{doc, oid::ObjectId, Bson, Document},
+    options::FindOptions,
+    Database,
+};
+use rand::{distributions::Alphanumeric, Rng};
+use sha2::Sha256;
+use crate::config::WebhookConfig;
+
+/// Événements auxquels un abonnement peut souscrire ; `*` les couvre tous
+pub const WEBHOOK_EVENTS: [&str; 6] = [
+    "message.created",
+    "message.read",
+    "request.approved",
+    "request.rejected",
+    "request.cancelled",
+    "request.expired",
+];
+pub const ALL_WEBHOOK_EVENTS: &str = "*";
+
+/// Statuts d'une livraison
+pub const DELIVERY_PENDING: &str = "pending";
+pub const DELIVERY_DELIVERED: &str = "delivered";
+/// Abandonnée après le nombre maximal de tentatives : liste des lettres mortes
+pub const DELIVERY_DEAD: &str = "dead";
+
+/// Délai maximal entre deux tentatives
+const MAX_BACKOFF_SECS: i64 = 6 * 3600;
+
+/// Durée pendant laquelle une livraison en cours d'envoi est réservée à une instance
+const DELIVERY_LEASE_SECS: i64 = 120;
+
+/// Nombre de livraisons traitées par passage
+const DELIVERY_BATCH_SIZE: i64 = 100;
+
+/// Délai avant la tentative suivant `attempts` échecs : `backoff_secs` doublé à chaque échec
+fn backoff(config: &WebhookConfig, attempts: i32) -> chrono::Duration {
+    let exponent = (attempts - 1).clamp(0, 20) as u32;
+    let secs = config.backoff_secs.saturating_mul(2_i64.saturating_pow(exponent));
+    chrono::Duration::seconds(secs.min(MAX_BACKOFF_SECS))
+}
+
+/// Abonnement d'un service à des événements de la messagerie
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct NewWebhookSubscription {
+    pub url: String,
+    pub events: Vec<String>,
+    /// Secret de signature ; généré s'il est absent
+    pub secret: Option<String>,
+    pub description: Option<String>,
+}
+
+impl NewWebhookSubscription {
+    pub fn validate(&self) -> Result<(), String> {
+        let url = reqwest::Url::parse(&self.url).map_err(|_| format!("URL invalide: {}", self.url))?;
+        if url.scheme() != "https" && url.scheme() != "http" {
+            return Err("L'URL doit être en http ou https".to_string());
+        }
+        if self.events.is_empty() {
+            return Err("Au moins un événement est requis".to_string());
+        }
+        if let Some(event) = self
+            .events
+            .iter()
+            .find(|e| *e != ALL_WEBHOOK_EVENTS && !WEBHOOK_EVENTS.contains(&e.as_str()))
+        {
+            return Err(format!(
+                "Événement inconnu: {} (attendu: {}, ou *)",
+                event,
+                WEBHOOK_EVENTS.join(", ")
+            ));
+        }
+        if self.secret.as_ref().is_some_and(|s| s.len() < 16) {
+            return Err("Le secret doit contenir au moins 16 caractères".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Signature `sha256=<hex>` de `"{timestamp}.{corps}"`, vérifiable par le destinataire
+pub fn sign(secret: &str, timestamp: i64, body: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepte toute taille de clé");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body.as_bytes());
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+fn generate_secret() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(40)
+        .map(char::from)
+        .collect()
+}
+
+pub struct WebhookService;
+
+impl WebhookService {
+    /// Enregistre l'abonnement et renvoie son identifiant et son secret
+    pub async fn create(
+        db: &Database,
+        subscription: &NewWebhookSubscription,
+    ) -> Result<(ObjectId, String), mongodb::error::Error> {
+        let secret = subscription.secret.clone().unwrap_or_else(generate_secret);
+        let result = db
+            .collection::<Document>("webhook_subscriptions")
+            .insert_one(
+                doc! {
+                    "url": &subscription.url,
+                    "events": &subscription.events,
+                    "secret": &secret,
+                    "description": &subscription.description,
+                    "active": true,
+                    "created_at": chrono::Utc::now().to_rfc3339(),
+                },
+                None,
+            )
+            .await?;
+        info!("🪝 Webhook {} abonné à {}", subscription.url, subscription.events.join(", "));
+
+        let id = result.inserted_id.as_object_id().unwrap_or_default();
+        Ok((id, secret))
+    }
+
+    pub async fn list(db: &Database) -> Result<Vec<Document>, mongodb::error::Error> {
+        let options = FindOptions::builder().sort(doc! { "created_at": -1 }).build();
+        let mut cursor = db
+            .collection::<Document>("webhook_subscriptions")
+            .find(doc! {}, options)
+            .await?;
+
+        let mut subscriptions = Vec::new();
+        while let Some(subscription) = cursor.try_next().await? {
+            subscriptions.push(subscription);
+        }
+        Ok(subscriptions)
+    }
+
+    pub async fn get(db: &Database, id: &str) -> Result<Option<Document>, mongodb::error::Error> {
+        let Ok(oid) = ObjectId::parse_str(id) else {
+            return Ok(None);
+        };
+        db.collection::<Document>("webhook_subscriptions")
+            .find_one(doc! { "_id": oid }, None)
+            .await
+    }
+
+    /// Supprime l'abonnement ; ses livraisons en attente partiront en lettres mortes
+    pub async fn delete(db: &Database, id: &str) -> Result<bool, mongodb::error::Error> {
+        let Ok(oid) = ObjectId::parse_str(id) else {
+            return Ok(false);
+        };
+        let result = db
+            .collection::<Document>("webhook_subscriptions")
+            .delete_one(doc! { "_id": oid }, None)
+            .await?;
+        Ok(result.deleted_count > 0)
+    }
+
+    /// Crée une livraison par abonnement intéressé par l'événement
+    pub async fn enqueue(db: &Database, event: &str, data: Document) -> Result<usize, mongodb::error::Error> {
+        let mut cursor = db
+            .collection::<Document>("webhook_subscriptions")
+            .find(doc! { "active": true, "events": { "$in": [event, ALL_WEBHOOK_EVENTS] } }, None)
+            .await?;
+
+        let now = chrono::Utc::now().to_rfc3339();
+        let mut deliveries = Vec::new();
+        while let Some(subscription) = cursor.try_next().await? {
+            let Ok(subscription_id) = subscription.get_object_id("_id") else {
+                continue;
+            };
+            let id = ObjectId::new();
+            deliveries.push(doc! {
+                "_id": id,
+                "subscription_id": subscription_id,
+                "event": event,
+                "payload": {
+                    "id": id.to_hex(),
+                    "event": event,
+                    "created_at": &now,
+                    "data": data.clone(),
+                },
+                "status": DELIVERY_PENDING,
+                "attempts": 0,
+                "next_attempt_at": &now,
+                "created_at": &now,
+                "last_attempt_at": null,
+                "last_status": null,
+                "last_error": null,
+            });
+        }
+
+        let count = deliveries.len();
+        if count > 0 {
+            db.collection::<Document>("webhook_deliveries")
+                .insert_many(deliveries, None)
+                .await?;
+        }
+        Ok(count)
+    }
+
+    /// Livraisons abandonnées, les plus récentes en premier
+    pub async fn dead_letters(
+        db: &Database,
+        subscription_id: Option<&str>,
+        limit: i64,
+    ) -> Result<Vec<Document>, mongodb::error::Error> {
+        let mut filter = doc! { "status": DELIVERY_DEAD };
+        if let Some(id) = subscription_id {
+            match ObjectId::parse_str(id) {
+                Ok(oid) => filter.insert("subscription_id", oid),
+                Err(_) => return Ok(Vec::new()),
+            };
+        }
+        let options = FindOptions::builder()
+            .sort(doc! { "created_at": -1 })
+            .limit(limit)
+            .build();
+
+        let mut cursor = db
+            .collection::<Document>("webhook_deliveries")
+            .find(filter, options)
+            .await?;
+        let mut deliveries = Vec::new();
+        while let Some(delivery) = cursor.try_next().await? {
+            deliveries.push(delivery);
+        }
+        Ok(deliveries)
+    }
+
+    /// Remet une livraison (abandonnée ou déjà livrée) en file d'envoi
+    pub async fn replay(db: &Database, delivery_id: &str) -> Result<bool, mongodb::error::Error> {
+        let Ok(oid) = ObjectId::parse_str(delivery_id) else {
+            return Ok(false);
+        };
+        let result = db
+            .collection::<Document>("webhook_deliveries")
+            .update_one(
+                doc! { "_id": oid, "status": { "$in": [DELIVERY_DEAD, DELIVERY_DELIVERED] } },
+                Self::replay_update(),
+                None,
+            )
+            .await?;
+        Ok(result.modified_count > 0)
+    }
+
+    /// Remet en file toutes les lettres mortes d'un abonnement
+    pub async fn replay_dead_letters(db: &Database, subscription_id: &str) -> Result<u64, mongodb::error::Error> {
+        let Ok(oid) = ObjectId::parse_str(subscription_id) else {
+            return Ok(0);
+        };
+        let result = db
+            .collection::<Document>("webhook_deliveries")
+            .update_many(
+                doc! { "subscription_id": oid, "status": DELIVERY_DEAD },
+                Self::replay_update(),
+                None,
+            )
+            .await?;
+        Ok(result.modified_count)
+    }
+
+    fn replay_update() -> Document {
+        doc! { "$set": {
+            "status": DELIVERY_PENDING,
+            "attempts": 0,
+            "next_attempt_at": chrono::Utc::now().to_rfc3339(),
+            "last_error": null,
+        } }
+    }
+
+    /// Envoie les livraisons arrivées à échéance et renvoie le nombre de succès
+    pub async fn deliver_due(
+        db: &Database,
+        client: &reqwest::Client,
+        config: &WebhookConfig,
+    ) -> Result<usize, mongodb::error::Error> {
+        let deliveries = db.collection::<Document>("webhook_deliveries");
+        let now = chrono::Utc::now();
+        let options = FindOptions::builder()
+            .sort(doc! { "next_attempt_at": 1 })
+            .limit(DELIVERY_BATCH_SIZE)
+            .build();
+
+        let mut cursor = deliveries
+            .find(
+                doc! { "status": DELIVERY_PENDING, "next_attempt_at": { "$lte": now.to_rfc3339() } },
+                options,
+            )
+            .await?;
+        let mut due = Vec::new();
+        while let Some(delivery) = cursor.try_next().await? {
+            due.push(delivery);
+        }
+
+        let mut delivered = 0;
+        for delivery in due {
+            let Ok(oid) = delivery.get_object_id("_id") else {
+                continue;
+            };
+            // Réservation : une autre instance ne renverra pas la même livraison
+            let lease = (now + chrono::Duration::seconds(DELIVERY_LEASE_SECS)).to_rfc3339();
+            let claimed = deliveries
+                .update_one(
+                    doc! {
+                        "_id": oid,
+                        "status": DELIVERY_PENDING,
+                        "next_attempt_at": delivery.get("next_attempt_at").cloned().unwrap_or(Bson::Null),
+                    },
+                    doc! { "$set": { "next_attempt_at": &lease } },
+                    None,
+                )
+                .await?;
+            if claimed.modified_count == 0 {
+                continue;
+            }
+
+            let subscription = match delivery.get_object_id("subscription_id") {
+                Ok(subscription_id) => {
+                    db.collection::<Document>("webhook_subscriptions")
+                        .find_one(doc! { "_id": subscription_id, "active": true }, None)
+                        .await?
+                }
+                Err(_) => None,
+            };
+            // Un abonnement supprimé ou désactivé ne sera plus livré : pas de nouvelle tentative
+            let orphaned = subscription.is_none();
+            let outcome = match subscription {
+                Some(subscription) => Self::send(client, &subscription, &delivery).await,
+                None => Err((None, "Abonnement supprimé ou désactivé".to_string())),
+            };
+
+            let attempts = delivery.get_i32("attempts").unwrap_or(0) + 1;
+            let attempted_at = chrono::Utc::now();
+            let update = match outcome {
+                Ok(status) => {
+                    delivered += 1;
+                    doc! { "$set": {
+                        "status": DELIVERY_DELIVERED,
+                        "attempts": attempts,
+                        "last_attempt_at": attempted_at.to_rfc3339(),
+                        "last_status": status as i32,
+                        "last_error": null,
+                        "delivered_at": attempted_at.to_rfc3339(),
+                    } }
+                }
+                Err((status, error)) => {
+                    let dead = orphaned || attempts >= config.max_attempts;
+                    if orphaned {
+                        log::info!("🗑️  Webhook {} abandonné: {}", oid, error);
+                    } else if dead {
+                        log::warn!("⚠️  Webhook {} abandonné après {} tentatives: {}", oid, attempts, error);
+                    }
+                    doc! { "$set": {
+                        "status": if dead { DELIVERY_DEAD } else { DELIVERY_PENDING },
+                        "attempts": attempts,
+                        "next_attempt_at": (attempted_at + backoff(config, attempts)).to_rfc3339(),
+                        "last_attempt_at": attempted_at.to_rfc3339(),
+                        "last_status": status.map(|s| s as i32),
+                        "last_error": error,
+                    } }
+                }
+            };
+            deliveries.update_one(doc! { "_id": oid }, update, None).await?;
+        }
+
+        Ok(delivered)
+    }
+
+    /// POST signé vers l'abonné ; l'erreur porte le code HTTP éventuel
+    async fn send(
+        client: &reqwest::Client,
+        subscription: &Document,
+        delivery: &Document,
+    ) -> Result<u16, (Option<u16>, String)> {
+        let url = subscription.get_str("url").unwrap_or("");
+        let secret = subscription.get_str("secret").unwrap_or("");
+        let payload = delivery.get_document("payload").cloned().unwrap_or_default();
+        let body = Bson::Document(payload).into_relaxed_extjson().to_string();
+        let timestamp = chrono::Utc::now().timestamp();
+        let delivery_id = delivery.get_object_id("_id").map(|oid| oid.to_hex()).unwrap_or_default();
+
+        let response = client
+            .post(url)
+            .header("Content-Type", "application/json")
+            .header("X-Webhook-Event", delivery.get_str("event").unwrap_or(""))
+            .header("X-Webhook-Delivery", delivery_id)
+            .header("X-Webhook-Timestamp", timestamp.to_string())
+            .header("X-Webhook-Signature", sign(secret, timestamp, &body))
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| (None, e.to_string()))?;
+
+        let status = response.status();
+        if status.is_success() {
+            Ok(status.as_u16())
+        } else {
+            Err((Some(status.as_u16()), format!("Réponse HTTP {}", status)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(backoff_secs: i64) -> WebhookConfig {
+        WebhookConfig {
+            delivery_interval_secs: 5,
+            max_attempts: 8,
+            backoff_secs,
+        }
+    }
+
+    #[test]
+    fn signature_covers_timestamp_and_body() {
+        let body = r#"{"event":"message.created"}"#;
+        assert_eq!(
+            sign("whsec_test_secret", 1_700_000_000, body),
+            "sha256=ed04444cd8728ef8c244b21dd5ef30d4ed169d99687c5d75cb30e59790e65dc7"
+        );
+        assert_ne!(sign("whsec_test_secret", 1_700_000_001, body), sign("whsec_test_secret", 1_700_000_000, body));
+        assert_ne!(sign("another_secret", 1_700_000_000, body), sign("whsec_test_secret", 1_700_000_000, body));
+    }
+
+    #[test]
+    fn backoff_doubles_after_each_failure() {
+        let config = config(30);
+        assert_eq!(backoff(&config, 1), chrono::Duration::seconds(30));
+        assert_eq!(backoff(&config, 2), chrono::Duration::seconds(60));
+        assert_eq!(backoff(&config, 4), chrono::Duration::seconds(240));
+    }
+
+    #[test]
+    fn backoff_is_capped_without_overflowing() {
+        assert_eq!(backoff(&config(30), 50), chrono::Duration::seconds(MAX_BACKOFF_SECS));
+        assert_eq!(backoff(&config(i64::MAX), 3), chrono::Duration::seconds(MAX_BACKOFF_SECS));
+    }
+}